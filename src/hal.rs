@@ -1,8 +1,24 @@
+mod collection;
+mod containers;
+mod embedded;
+mod expansion;
+mod forms;
 mod links;
+mod middleware;
 mod response;
+mod streaming;
+mod template;
 
+pub use collection::*;
+pub use containers::*;
+pub use embedded::*;
+pub use expansion::*;
+pub use forms::*;
 pub use links::*;
+pub use middleware::*;
 pub use response::*;
+pub use streaming::*;
+pub use template::TemplateError;
 
 use super::response::Response;
 
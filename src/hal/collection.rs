@@ -0,0 +1,161 @@
+use serde::Serialize;
+
+use super::{HalRespondable, Link};
+
+/// Metadata describing a single page of a collection resource.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct CollectionMeta {
+    /// The total number of items in the collection
+    pub total: u64,
+    /// The index of this page, starting from 0
+    pub page:  u64,
+    /// The maximum number of items on each page
+    pub size:  u64,
+    /// The total number of pages in the collection
+    pub pages: u64,
+}
+
+impl CollectionMeta {
+    /// Create the metadata for a page of a collection, computing the number of pages.
+    ///
+    /// # Parameters
+    /// - `total` - The total number of items in the collection
+    /// - `page` - The index of this page, starting from 0
+    /// - `size` - The maximum number of items on each page
+    pub fn new(total: u64, page: u64, size: u64) -> Self {
+        let pages = if size == 0 { 0 } else { total.div_ceil(size) };
+
+        Self { total, page, size, pages }
+    }
+}
+
+/// A single page of a collection, with its items, its pagination links and its metadata, for embedding in another
+/// resource with `HalRespondable::with_embedded_collection`.
+///
+/// The pagination links - `self`, `first`, `last` and, where they exist, `prev` and `next` - all point at the
+/// endpoint of the collection itself, with the index of the page as the `page` query parameter. The items are
+/// embedded under the `item` relation, which is always serialized as an array, and the metadata is included under
+/// the `page` key.
+///
+/// # Types
+/// - `T` - The type of the items in the collection
+#[derive(Debug)]
+pub struct CollectionResponse<T> {
+    href:     String,
+    items:    Vec<T>,
+    meta:     CollectionMeta,
+    item_rel: String,
+}
+
+impl<T> CollectionResponse<T>
+where
+    T: Serialize,
+{
+    /// Create a new instance of the `CollectionResponse` struct for a page of a collection.
+    ///
+    /// # Parameters
+    /// - `href` - The endpoint of the collection, e.g. `/customers/1/orders`
+    /// - `items` - The items on this page
+    /// - `meta` - The metadata describing this page
+    pub fn new<S, I>(href: S, items: I, meta: CollectionMeta) -> Self
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = T>,
+    {
+        Self {
+            href: href.into(),
+            items: items.into_iter().collect(),
+            meta,
+            item_rel: "item".to_owned(),
+        }
+    }
+
+    /// Specify the relation to embed the items under, instead of `item`.
+    ///
+    /// # Parameters
+    /// - `item_rel` - The name of the embedded relation for the items
+    pub fn with_item_rel<S>(mut self, item_rel: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.item_rel = item_rel.into();
+        self
+    }
+
+    /// Build the HAL resource for this page of the collection.
+    pub(crate) fn into_respondable(self) -> HalRespondable<()> {
+        let page = |page: u64| Link::from(self.href.as_str()).with_query(&[("page", &page.to_string())]);
+        let last = self.meta.pages.saturating_sub(1);
+
+        let mut respondable = HalRespondable::empty()
+            .with_link("self", page(self.meta.page))
+            .with_link("first", page(0))
+            .with_link("last", page(last))
+            .with_optional_link("prev", self.meta.page.checked_sub(1).map(page))
+            .with_optional_link("next", (self.meta.page < last).then(|| page(self.meta.page + 1)))
+            .with_collection_meta("page", self.meta.clone())
+            .array_embedded(self.item_rel.clone());
+        for item in self.items {
+            respondable = respondable.with_embedded(self.item_rel.clone(), item);
+        }
+
+        respondable
+    }
+}
+
+/// Metadata describing the resources embedded under a single relation.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct EmbeddedMeta {
+    /// The number of resources in the full list, which may be more than were embedded
+    pub count:     u64,
+    /// Whether only some of the resources in the list were embedded
+    pub truncated: bool,
+    /// The number of resources that were left out because they failed to serialize
+    #[serde(skip_serializing_if = "is_zero")]
+    pub skipped:   u64,
+}
+
+/// Determine if a count is zero, so that it can be left out of the serialized metadata.
+fn is_zero(count: &u64) -> bool {
+    *count == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use assert2::check;
+
+    use super::*;
+    use crate::response::Respondable;
+
+    #[test]
+    fn compute_pages() {
+        check!(CollectionMeta::new(0, 0, 10).pages == 0);
+        check!(CollectionMeta::new(10, 0, 10).pages == 1);
+        check!(CollectionMeta::new(11, 0, 10).pages == 2);
+        check!(CollectionMeta::new(11, 0, 0).pages == 0);
+    }
+
+    #[test]
+    fn collection_links() {
+        let collection = CollectionResponse::new("/orders", vec![1, 2], CollectionMeta::new(6, 1, 2));
+        let json = serde_json::to_value(collection.into_respondable().body()).unwrap();
+
+        check!(json["_links"]["self"]["href"] == "/orders?page=1");
+        check!(json["_links"]["first"]["href"] == "/orders?page=0");
+        check!(json["_links"]["prev"]["href"] == "/orders?page=0");
+        check!(json["_links"]["next"]["href"] == "/orders?page=2");
+        check!(json["_links"]["last"]["href"] == "/orders?page=2");
+        check!(json["_embedded"]["item"] == serde_json::json!([1, 2]));
+        check!(json["page"]["total"] == 6);
+    }
+
+    #[test]
+    fn single_page() {
+        let collection = CollectionResponse::new("/orders", vec![1], CollectionMeta::new(1, 0, 10));
+        let json = serde_json::to_value(collection.into_respondable().body()).unwrap();
+
+        check!(json["_links"].get("prev").is_none());
+        check!(json["_links"].get("next").is_none());
+        check!(json["_embedded"]["item"] == serde_json::json!([1]));
+    }
+}
@@ -0,0 +1,180 @@
+use std::marker::PhantomData;
+
+use actix_http::http::StatusCode;
+use serde::{Serialize, Serializer};
+
+use super::{HalRespondable, HalResponse, IntoHal};
+
+/// The payload of a HAL collection built from a container of resources.
+///
+/// The collection itself has no fields - every resource is embedded under the collection relation instead.
+///
+/// # Types
+/// - `P` - The HAL payload type of the resources in the collection.
+#[derive(Debug)]
+pub struct HalCollection<P>(PhantomData<P>);
+
+impl<P> Serialize for HalCollection<P> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_unit_struct("HalCollection")
+    }
+}
+
+/// Wrapper around a list of resources to convert into a HAL collection, embedding each resource under a
+/// specific relation.
+///
+/// # Types
+/// - `I` - The type of the resources in the collection.
+#[derive(Debug)]
+pub struct HalItems<I> {
+    items:    Vec<I>,
+    relation: String,
+}
+
+impl<I> HalItems<I> {
+    /// Create a new collection of the provided resources, embedded under the relation `items`.
+    ///
+    /// # Parameters
+    /// - `items` - The resources in the collection
+    pub fn new(items: Vec<I>) -> Self {
+        Self {
+            items,
+            relation: "items".to_owned(),
+        }
+    }
+
+    /// Specify the relation to embed the resources under.
+    ///
+    /// # Parameters
+    /// - `relation` - The name of the relation
+    pub fn with_relation<S>(mut self, relation: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.relation = relation.into();
+        self
+    }
+}
+
+impl<I, P> IntoHal<HalCollection<P>> for HalItems<I>
+where
+    I: IntoHal<P>,
+    P: Serialize,
+{
+    /// Convert the resources into a HAL collection, embedding each one with its own links. The relation is
+    /// always serialized as an array, even with only one resource.
+    fn into_hal(self) -> HalResponse<HalCollection<P>> {
+        let relation = self.relation;
+        self.items
+            .into_iter()
+            .fold(HalRespondable::new(HalCollection(PhantomData)), |respondable, item| {
+                respondable.with_embedded(relation.clone(), item.into_hal().into_inner())
+            })
+            .array_embedded(relation)
+            .into()
+    }
+
+    fn payload(self) -> HalCollection<P> {
+        HalCollection(PhantomData)
+    }
+}
+
+impl<I, P> IntoHal<HalCollection<P>> for Vec<I>
+where
+    I: IntoHal<P>,
+    P: Serialize,
+{
+    /// Convert the resources into a HAL collection, embedding each one under the relation `items`. Use
+    /// `HalItems` to embed them under a different relation.
+    fn into_hal(self) -> HalResponse<HalCollection<P>> {
+        HalItems::new(self).into_hal()
+    }
+
+    fn payload(self) -> HalCollection<P> {
+        HalCollection(PhantomData)
+    }
+}
+
+impl<I, P> IntoHal<Option<P>> for Option<I>
+where
+    I: IntoHal<P>,
+    P: Serialize,
+{
+    /// Convert the resource into a HAL response if present, or a `404 Not Found` with no body if not.
+    fn into_hal(self) -> HalResponse<Option<P>> {
+        match self {
+            Some(item) => item.into_hal().into_inner().map_payload(Some).into(),
+            None => HalRespondable::new(None)
+                .with_status_code(StatusCode::NOT_FOUND)
+                .without_body()
+                .into(),
+        }
+    }
+
+    fn payload(self) -> Option<P> {
+        self.map(IntoHal::payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::TestRequest, Responder};
+    use assert2::check;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::{hal::Link, response::Respondable};
+
+    struct User(u32);
+
+    impl IntoHal<Value> for User {
+        fn links(&self) -> Vec<(String, Link)> {
+            vec![("self".to_owned(), format!("/users/{}", self.0).into())]
+        }
+
+        fn payload(self) -> Value {
+            json!({"id": self.0})
+        }
+    }
+
+    #[test]
+    fn vec_into_collection() {
+        let body = vec![User(1)].into_hal().into_inner().body();
+
+        let json = serde_json::to_value(body).unwrap();
+        check!(json["_embedded"]["items"] == json!([{"id": 1, "_links": {"self": {"href": "/users/1"}}}]));
+    }
+
+    #[test]
+    fn items_with_relation() {
+        let body = HalItems::new(vec![User(1), User(2)])
+            .with_relation("users")
+            .into_hal()
+            .into_inner()
+            .body();
+
+        let json = serde_json::to_value(body).unwrap();
+        check!(json["_embedded"]["users"][1]["id"] == 2);
+    }
+
+    #[test]
+    fn some_into_resource() {
+        let respondable = Some(User(1)).into_hal().into_inner();
+
+        check!(respondable.status_code() == StatusCode::OK);
+        let json = serde_json::to_value(respondable.body()).unwrap();
+        check!(json == json!({"id": 1, "_links": {"self": {"href": "/users/1"}}}));
+    }
+
+    #[test]
+    fn none_into_not_found() {
+        let req = TestRequest::default().to_http_request();
+        let response = None::<User>.into_hal().respond_to(&req);
+
+        check!(response.status() == StatusCode::NOT_FOUND);
+        check!(response.headers().get("content-type").is_none());
+    }
+}
@@ -0,0 +1,234 @@
+use serde::{ser::Error, Serialize, Serializer};
+use serde_json::Value;
+
+use super::Cardinality;
+
+/// Representation of a single resource embedded within a HAL resource.
+///
+/// The resource is serialized as soon as it is embedded, so that resources of different types can be
+/// embedded alongside each other. Any failure to serialize it is reported when the HAL resource itself is
+/// serialized.
+#[derive(Debug)]
+pub struct EmbeddedResource(Result<Value, String>);
+
+impl EmbeddedResource {
+    /// Create a new embedded resource for the provided value.
+    ///
+    /// # Parameters
+    /// - `resource` - The resource to embed
+    pub fn new<T>(resource: T) -> Self
+    where
+        T: Serialize,
+    {
+        Self(serde_json::to_value(resource).map_err(|e| e.to_string()))
+    }
+
+    /// The error the resource failed to serialize with, if it did.
+    pub fn error(&self) -> Option<&str> {
+        self.0.as_ref().err().map(String::as_str)
+    }
+
+    /// Apply a closure to the `href` of every link of the resource, including those of any resources embedded in
+    /// it, at any depth.
+    ///
+    /// # Parameters
+    /// - `visitor` - The closure to call with every `href`
+    pub fn visit_hrefs<F>(&mut self, visitor: &mut F)
+    where
+        F: FnMut(&mut String),
+    {
+        if let Ok(value) = &mut self.0 {
+            visit_hrefs(value, visitor);
+        }
+    }
+}
+
+/// Apply a closure to the `href` of every link of a serialized HAL resource, and then of every resource embedded
+/// in it.
+///
+/// # Parameters
+/// - `resource` - The serialized HAL resource
+/// - `visitor` - The closure to call with every `href`
+fn visit_hrefs<F>(resource: &mut Value, visitor: &mut F)
+where
+    F: FnMut(&mut String),
+{
+    let resource = match resource {
+        Value::Object(resource) => resource,
+        _ => return,
+    };
+
+    if let Some(Value::Object(links)) = resource.get_mut("_links") {
+        for link in links.values_mut().flat_map(one_or_many) {
+            if let Some(Value::String(href)) = link.get_mut("href") {
+                visitor(href);
+            }
+        }
+    }
+    if let Some(Value::Object(embedded)) = resource.get_mut("_embedded") {
+        for resource in embedded.values_mut().flat_map(one_or_many) {
+            visit_hrefs(resource, visitor);
+        }
+    }
+}
+
+/// The values of a relation, which is either a single value or an array of them.
+///
+/// # Parameters
+/// - `value` - The value of the relation
+fn one_or_many(value: &mut Value) -> Vec<&mut Value> {
+    match value {
+        Value::Array(values) => values.iter_mut().collect(),
+        value => vec![value],
+    }
+}
+
+impl Serialize for EmbeddedResource {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match &self.0 {
+            Ok(value) => value.serialize(serializer),
+            Err(e) => Err(S::Error::custom(e)),
+        }
+    }
+}
+
+/// Representation of a set of 1 or more embedded resources.
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum Embedded {
+    Single(EmbeddedResource),
+    Multiple(Vec<EmbeddedResource>),
+}
+
+impl Embedded {
+    pub fn push(self, new: EmbeddedResource) -> Self {
+        match self {
+            Embedded::Single(first) => Self::Multiple(vec![first, new]),
+            Embedded::Multiple(mut previous) => {
+                previous.push(new);
+                Self::Multiple(previous)
+            },
+        }
+    }
+
+    /// The number of resources embedded.
+    pub fn len(&self) -> usize {
+        match self {
+            Embedded::Single(_) => 1,
+            Embedded::Multiple(resources) => resources.len(),
+        }
+    }
+
+    /// Whether no resources are embedded, i.e. this is an empty array.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Apply a closure to the `href` of every link of every resource, including those of any resources embedded in
+    /// them.
+    ///
+    /// # Parameters
+    /// - `visitor` - The closure to call with every `href`
+    pub fn visit_hrefs<F>(&mut self, visitor: &mut F)
+    where
+        F: FnMut(&mut String),
+    {
+        match self {
+            Embedded::Single(resource) => resource.visit_hrefs(visitor),
+            Embedded::Multiple(resources) => {
+                for resource in resources {
+                    resource.visit_hrefs(visitor);
+                }
+            },
+        }
+    }
+
+    /// Remove any resources that failed to serialize, logging each of them.
+    ///
+    /// # Parameters
+    /// - `rel` - The relation the resources are embedded under, for logging
+    ///
+    /// # Returns
+    /// The remaining resources, or `None` if a single resource was removed, and the number that were removed
+    pub fn without_failures(self, rel: &str) -> (Option<Self>, u64) {
+        let failed = |resource: &EmbeddedResource| match resource.error() {
+            Some(e) => {
+                tracing::error!(rel = ?rel, e = ?e, "Skipping embedded resource that failed to serialize");
+                true
+            },
+            None => false,
+        };
+
+        match self {
+            Embedded::Single(resource) if failed(&resource) => (None, 1),
+            Embedded::Single(resource) => (Some(Embedded::Single(resource)), 0),
+            Embedded::Multiple(resources) => {
+                let total = resources.len();
+                let resources: Vec<_> = resources.into_iter().filter(|resource| !failed(resource)).collect();
+                let skipped = (total - resources.len()) as u64;
+                (Some(Embedded::Multiple(resources)), skipped)
+            },
+        }
+    }
+
+    /// Convert these embedded resources to match the requested cardinality.
+    ///
+    /// A relation with more than one resource is always an array, regardless of the cardinality requested.
+    ///
+    /// # Parameters
+    /// - `cardinality` - The cardinality to convert to
+    pub fn with_cardinality(self, cardinality: Cardinality) -> Self {
+        match (cardinality, self) {
+            (Cardinality::Array, Embedded::Single(resource)) => Embedded::Multiple(vec![resource]),
+            (Cardinality::Object, Embedded::Multiple(mut resources)) if resources.len() == 1 => {
+                Embedded::Single(resources.remove(0))
+            },
+            (_, embedded) => embedded,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use assert2::{check, let_assert};
+
+    use super::*;
+
+    #[test]
+    fn append_to_single() {
+        let first = Embedded::Single(EmbeddedResource::new(1));
+        let updated = first.push(EmbeddedResource::new(2));
+
+        let_assert!(Embedded::Multiple(resources) = updated);
+        check!(resources.len() == 2);
+        check!(serde_json::to_value(resources).unwrap() == serde_json::json!([1, 2]));
+    }
+
+    #[test]
+    fn single_as_array() {
+        let embedded = Embedded::Single(EmbeddedResource::new(1)).with_cardinality(Cardinality::Array);
+
+        check!(serde_json::to_value(embedded).unwrap() == serde_json::json!([1]));
+    }
+
+    #[test]
+    fn multiple_of_one_as_object() {
+        let embedded = Embedded::Multiple(vec![EmbeddedResource::new(1)]).with_cardinality(Cardinality::Object);
+
+        check!(serde_json::to_value(embedded).unwrap() == serde_json::json!(1));
+    }
+
+    #[test]
+    fn serialization_failure() {
+        let mut resource = HashMap::new();
+        resource.insert((1, 2), "Not a string key");
+
+        let embedded = Embedded::Single(EmbeddedResource::new(resource));
+        check!(serde_json::to_value(embedded).is_err());
+    }
+}
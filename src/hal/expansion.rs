@@ -0,0 +1,80 @@
+use std::collections::{BTreeSet, HashMap};
+
+use actix_web::{web, HttpRequest};
+
+/// The relations that a request asked to have embedded instead of linked, e.g. with `?embed=orders,items`.
+///
+/// This is used with `HalRespondable::with_expandable` for selective expansion, where a relation is normally
+/// only linked but is embedded in full when the client asks for it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Expansion {
+    requested: BTreeSet<String>,
+}
+
+impl Expansion {
+    /// Determine the relations to expand from the `embed` query parameter of the request.
+    ///
+    /// # Parameters
+    /// - `req` - The request to read the query parameter from
+    /// - `allowed` - The relations that are allowed to be expanded. Any others that are requested are ignored
+    pub fn from_request(req: &HttpRequest, allowed: &[&str]) -> Self {
+        Self::from_request_param(req, "embed", allowed)
+    }
+
+    /// Determine the relations to expand from the comma-separated list in the named query parameter of the
+    /// request.
+    ///
+    /// # Parameters
+    /// - `req` - The request to read the query parameter from
+    /// - `param` - The name of the query parameter
+    /// - `allowed` - The relations that are allowed to be expanded. Any others that are requested are ignored
+    pub fn from_request_param(req: &HttpRequest, param: &str, allowed: &[&str]) -> Self {
+        let query = match web::Query::<HashMap<String, String>>::from_query(req.query_string()) {
+            Ok(query) => query.into_inner(),
+            Err(e) => {
+                tracing::error!(e = ?e, "Failed to parse query string for expansion");
+                return Self::default();
+            },
+        };
+
+        let requested = query
+            .get(param)
+            .map(|relations| {
+                relations
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|relation| allowed.contains(relation))
+                    .map(ToOwned::to_owned)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Self { requested }
+    }
+
+    /// Determine if the provided relation should be expanded.
+    ///
+    /// # Parameters
+    /// - `relation` - The name of the relation
+    pub fn is_requested(&self, relation: &str) -> bool {
+        self.requested.contains(relation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use assert2::check;
+
+    use super::*;
+
+    #[test]
+    fn allowed_relations_only() {
+        let req = TestRequest::with_uri("/users/1?embed=orders,%20secrets").to_http_request();
+        let expansion = Expansion::from_request(&req, &["orders", "items"]);
+
+        check!(expansion.is_requested("orders"));
+        check!(!expansion.is_requested("secrets"));
+        check!(!expansion.is_requested("items"));
+    }
+}
@@ -0,0 +1,270 @@
+use std::collections::BTreeMap;
+
+use actix_http::http::{
+    header::{self, HeaderValue},
+    HeaderMap, Method, StatusCode,
+};
+use actix_web::{HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+
+use crate::response::{negotiate, Respondable, Response};
+
+/// The media type of HAL-FORMS documents.
+pub const HAL_FORMS_MEDIA_TYPE: &str = "application/prs.hal-forms+json";
+
+/// A HAL-FORMS template, describing a single operation that can be performed on a resource.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct HalFormsTemplate {
+    /// The HTTP method of the operation
+    pub method:       String,
+    /// The human-readable title of the operation
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title:        Option<String>,
+    /// The media type of the request body the operation expects
+    #[serde(rename = "contentType", skip_serializing_if = "Option::is_none")]
+    pub content_type: Option<String>,
+    /// The properties of the request body
+    pub properties:   Vec<HalFormsProperty>,
+}
+
+/// A single property of a HAL-FORMS template.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct HalFormsProperty {
+    /// The name of the property
+    pub name:     String,
+    /// Whether the property must be provided
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub required: bool,
+    /// The human-readable prompt for the property
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prompt:   Option<String>,
+}
+
+impl HalFormsTemplate {
+    /// Create a new template for an operation using the provided method, with no properties.
+    ///
+    /// # Parameters
+    /// - `method` - The HTTP method of the operation
+    pub fn new(method: Method) -> Self {
+        Self {
+            method:       method.to_string(),
+            title:        None,
+            content_type: None,
+            properties:   vec![],
+        }
+    }
+
+    /// Specify the human-readable title of the operation.
+    ///
+    /// # Parameters
+    /// - `title` - The title of the operation
+    pub fn with_title<S>(mut self, title: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.title = Some(title.into());
+
+        self
+    }
+
+    /// Specify the media type of the request body the operation expects.
+    ///
+    /// # Parameters
+    /// - `content_type` - The media type of the request body
+    pub fn with_content_type<S>(mut self, content_type: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.content_type = Some(content_type.into());
+
+        self
+    }
+
+    /// Add a property of the request body to the operation.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the property
+    /// - `required` - Whether the property must be provided
+    pub fn with_property<S>(mut self, name: S, required: bool) -> Self
+    where
+        S: Into<String>,
+    {
+        self.properties.push(HalFormsProperty {
+            name: name.into(),
+            required,
+            prompt: None,
+        });
+
+        self
+    }
+}
+
+/// Build the response to an `OPTIONS` request on a resource, advertising the operations it supports.
+///
+/// # Parameters
+/// - `methods` - The methods the resource supports, sent in the `Allow` header
+/// - `templates` - The HAL-FORMS templates describing the operations, keyed by name. The main operation is
+///   conventionally named `default`
+pub fn options<M, T, K>(methods: M, templates: T) -> OptionsResponse
+where
+    M: IntoIterator<Item = Method>,
+    T: IntoIterator<Item = (K, HalFormsTemplate)>,
+    K: Into<String>,
+{
+    OptionsResponse {
+        methods:   methods.into_iter().collect(),
+        templates: templates.into_iter().map(|(name, template)| (name.into(), template)).collect(),
+    }
+}
+
+/// Response to an `OPTIONS` request on a resource, created by `options`.
+///
+/// The supported methods are always sent in the `Allow` header. If the client explicitly accepts
+/// `application/prs.hal-forms+json` and there are any templates then they are sent as a HAL-FORMS document with
+/// a `200 OK`. Otherwise the response is a `204 No Content`.
+pub struct OptionsResponse {
+    methods:   Vec<Method>,
+    templates: BTreeMap<String, HalFormsTemplate>,
+}
+
+impl Responder for OptionsResponse {
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        let allow = self.methods.iter().map(Method::as_str).collect::<Vec<_>>().join(", ");
+        let has_body = !self.templates.is_empty() && accepts_hal_forms(req);
+
+        let mut headers = HeaderMap::new();
+        match HeaderValue::from_str(&allow) {
+            Ok(value) => {
+                headers.insert(header::ALLOW, value);
+            },
+            Err(_) => {
+                tracing::error!(allow = ?allow, "Failed to process Allow header");
+            },
+        };
+
+        Response::new(OptionsRespondable {
+            headers,
+            has_body,
+            templates: self.templates,
+        })
+        .respond_to(req)
+    }
+}
+
+/// Determine if the request explicitly accepts HAL-FORMS documents, rather than only through a wildcard.
+///
+/// # Parameters
+/// - `req` - The request being responded to
+fn accepts_hal_forms(req: &HttpRequest) -> bool {
+    let named = req
+        .headers()
+        .get(header::ACCEPT)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|accept| {
+            accept.split(',').any(|range| {
+                let media_type = range.split(';').next().unwrap_or_default();
+                media_type.trim().eq_ignore_ascii_case(HAL_FORMS_MEDIA_TYPE)
+            })
+        });
+
+    named && negotiate(req, &[HAL_FORMS_MEDIA_TYPE]).is_some()
+}
+
+/// Respondable for the response to an `OPTIONS` request.
+struct OptionsRespondable {
+    headers:   HeaderMap,
+    has_body:  bool,
+    templates: BTreeMap<String, HalFormsTemplate>,
+}
+
+/// The body of the response to an `OPTIONS` request.
+#[derive(Serialize)]
+struct OptionsBody {
+    #[serde(rename = "_templates")]
+    templates: BTreeMap<String, HalFormsTemplate>,
+}
+
+impl Respondable for OptionsRespondable {
+    type Body = OptionsBody;
+
+    fn status_code(&self) -> StatusCode {
+        if self.has_body {
+            StatusCode::OK
+        } else {
+            StatusCode::NO_CONTENT
+        }
+    }
+
+    fn headers(&self) -> HeaderMap {
+        self.headers.clone()
+    }
+
+    fn media_types(&self) -> Vec<&'static str> {
+        vec![HAL_FORMS_MEDIA_TYPE]
+    }
+
+    fn has_body(&self) -> bool {
+        self.has_body
+    }
+
+    fn body(self) -> Self::Body {
+        OptionsBody {
+            templates: self.templates,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{body::AnyBody, test::TestRequest};
+    use assert2::{check, let_assert};
+
+    use super::*;
+
+    fn response() -> OptionsResponse {
+        options(
+            vec![Method::GET, Method::PUT, Method::OPTIONS],
+            vec![(
+                "default",
+                HalFormsTemplate::new(Method::PUT).with_title("Update").with_property("name", true),
+            )],
+        )
+    }
+
+    #[test]
+    fn options_without_hal_forms() {
+        let req = TestRequest::default()
+            .insert_header((header::ACCEPT, "*/*"))
+            .to_http_request();
+        let response = response().respond_to(&req);
+
+        check!(response.status() == StatusCode::NO_CONTENT);
+        check!(response.headers().get(header::ALLOW).unwrap() == "GET, PUT, OPTIONS");
+        check!(response.headers().get(header::CONTENT_TYPE).is_none());
+    }
+
+    #[test]
+    fn options_with_hal_forms() {
+        let req = TestRequest::default()
+            .insert_header((header::ACCEPT, HAL_FORMS_MEDIA_TYPE))
+            .to_http_request();
+        let response = response().respond_to(&req);
+
+        check!(response.status() == StatusCode::OK);
+        check!(response.headers().get(header::ALLOW).unwrap() == "GET, PUT, OPTIONS");
+        check!(response.headers().get(header::CONTENT_TYPE).unwrap() == HAL_FORMS_MEDIA_TYPE);
+        let_assert!(AnyBody::Bytes(body) = response.body());
+        let json: serde_json::Value = serde_json::from_slice(body).unwrap();
+        check!(
+            json == serde_json::json!({
+                "_templates": {
+                    "default": {
+                        "method": "PUT",
+                        "title": "Update",
+                        "properties": [{"name": "name", "required": true}]
+                    }
+                }
+            })
+        );
+    }
+}
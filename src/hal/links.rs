@@ -1,24 +1,338 @@
+use std::{collections::BTreeMap, fmt::Display, iter::FromIterator};
+
+use actix_http::http::Uri;
+use actix_web::{error::UrlGenerationError, HttpRequest};
 use serde::Serialize;
 
+use super::{template, TemplateError};
+
 /// Representation of a single HAL Link.
-#[derive(Debug, Serialize, Default, Clone)]
+///
+/// Links are ordered by `href`, then by `name`, and then by their remaining fields.
+#[derive(Debug, Serialize, Default, Clone, PartialEq, Eq, PartialOrd, Ord)]
 pub struct Link {
-    pub href: String,
+    pub href:      String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name:      Option<String>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub templated: bool,
+    /// The media type of the target resource
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub r#type:    Option<String>,
+    /// The language of the target resource
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub name: Option<String>,
+    pub hreflang:  Option<String>,
+    /// Custom numeric properties of the link, e.g. the number of results of a search
+    #[serde(flatten)]
+    pub hints:     BTreeMap<String, i64>,
+    /// The context of the link when it is sent as a `Link` header, if it isn't the resource being responded to
+    #[serde(skip)]
+    pub anchor:    Option<String>,
+}
+
+/// Determine if the provided string is well-formed as a URI-reference, as defined by RFC 3986 section 4.1.
+///
+/// This checks that it only contains characters that are allowed in a URI and that every percent-encoding is
+/// complete, but not the structure of the individual components.
+fn is_uri_reference(uri: &str) -> bool {
+    let allowed = |c: char| c.is_ascii_alphanumeric() || "-._~:/?#[]@!$&'()*+,;=%".contains(c);
+    let encodings_complete = uri.split('%').skip(1).all(|encoded| {
+        encoded.len() >= 2 && encoded.chars().take(2).all(|c| c.is_ascii_hexdigit())
+    });
+
+    uri.chars().all(allowed) && encodings_complete
 }
 
+/// The properties of a link defined by HAL, which can't be used as the key of a hint.
+const LINK_PROPERTIES: &[&str] = &[
+    "href",
+    "templated",
+    "type",
+    "deprecation",
+    "name",
+    "profile",
+    "title",
+    "hreflang",
+];
+
 // Representation of a set of 1 or more HAL Links.
 #[derive(Debug, Serialize)]
 #[serde(untagged)]
 pub enum Links {
     Single(Link),
     Multiple(Vec<Link>),
+    /// A relation that is explicitly present with no links, serialized as `null`.
+    Null,
+}
+
+/// How to serialize a relation that is explicitly present but has no links.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EmptyForm {
+    /// Serialize the relation as `null`.
+    Null,
+    /// Serialize the relation as an empty array.
+    Array,
+}
+
+/// How trailing slashes on the path of a link are normalized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TrailingSlash {
+    /// Leave the path exactly as it is.
+    #[default]
+    Leave,
+    /// Remove any trailing slashes from the path, e.g. `/users/` becomes `/users`.
+    AlwaysStrip,
+    /// Add a trailing slash to the path if it doesn't have one, e.g. `/users` becomes `/users/`.
+    AlwaysAdd,
+}
+
+impl TrailingSlash {
+    /// Normalize the trailing slash on the path of an href according to this policy.
+    ///
+    /// Only the path is changed, so any query string, fragment or URI Template expression for them that follows
+    /// the path is left in place, e.g. `/users/?page=2` is stripped to `/users?page=2`. The root path, and an
+    /// absolute URL with no path at all, are always left as they are.
+    ///
+    /// # Parameters
+    /// - `href` - The href to normalize
+    ///
+    /// # Returns
+    /// The normalized href
+    pub fn normalize(self, href: &str) -> String {
+        let suffix_start = href
+            .find(['?', '#'])
+            .map(|index| if index > 0 && href.as_bytes()[index - 1] == b'{' { index - 1 } else { index })
+            .unwrap_or(href.len());
+        let (path, suffix) = href.split_at(suffix_start);
+
+        let path_start = match path.find("://") {
+            Some(scheme_end) => match path[scheme_end + 3..].find('/') {
+                Some(index) => scheme_end + 3 + index,
+                None => return href.to_owned(),
+            },
+            None => 0,
+        };
+        let (origin, path) = path.split_at(path_start);
+        if path.is_empty() || path == "/" {
+            return href.to_owned();
+        }
+
+        let path = match self {
+            TrailingSlash::Leave => return href.to_owned(),
+            TrailingSlash::AlwaysStrip => match path.trim_end_matches('/') {
+                "" => "/".to_owned(),
+                path => path.to_owned(),
+            },
+            TrailingSlash::AlwaysAdd if path.ends_with('/') => path.to_owned(),
+            TrailingSlash::AlwaysAdd => format!("{}/", path),
+        };
+
+        format!("{}{}{}", origin, path, suffix)
+    }
+}
+
+impl Link {
+    /// Build a link to the URL of the provided request.
+    ///
+    /// The scheme and host honour the `Forwarded`, `X-Forwarded-Proto` and `X-Forwarded-Host` headers, so
+    /// this produces the URL the client actually used when running behind a proxy.
+    ///
+    /// # Parameters
+    /// - `req` - The request to link to
+    /// - `include_query` - Whether to include the query string of the request in the link
+    pub fn for_request(req: &HttpRequest, include_query: bool) -> Self {
+        let info = req.connection_info();
+        let mut href = format!("{}://{}{}", info.scheme(), info.host(), req.path());
+
+        let query = req.query_string();
+        if include_query && !query.is_empty() {
+            href.push('?');
+            href.push_str(query);
+        }
+
+        href.into()
+    }
+
+    /// Build a link to a named route of the application, so that the link always matches the routing table.
+    ///
+    /// # Parameters
+    /// - `req` - The request being responded to, which gives access to the routes of the application
+    /// - `name` - The name of the route, as given to `Resource::name`
+    /// - `elements` - The values of the dynamic segments of the route, in order
+    ///
+    /// # Errors
+    /// If there is no route with that name, or the elements don't match its dynamic segments
+    pub fn for_route<U, I>(req: &HttpRequest, name: &str, elements: U) -> Result<Self, UrlGenerationError>
+    where
+        U: IntoIterator<Item = I>,
+        I: AsRef<str>,
+    {
+        req.url_for(name, elements).map(|url| url.to_string().into())
+    }
+
+    /// Build a link to a parsed URI, using its normalized string form as the href.
+    ///
+    /// # Parameters
+    /// - `uri` - The URI to link to
+    pub fn from_uri(uri: &Uri) -> Self {
+        uri.to_string().into()
+    }
+
+    /// Build a link to a parsed URL, using its serialized form as the href.
+    ///
+    /// `Link` also implements `From<Url>`, because the `url` crate converts URLs into strings, so a `Url` can be
+    /// passed directly to `with_link` as well.
+    ///
+    /// # Parameters
+    /// - `url` - The URL to link to
+    #[cfg(feature = "url")]
+    pub fn from_url(url: &url::Url) -> Self {
+        url.as_str().into()
+    }
+
+    /// Build a link to a single resource within a base URL, e.g. `/users/42` from a base of `/users` and an ID of
+    /// `42`. The ID is percent-encoded so that it is always a single path segment.
+    ///
+    /// # Parameters
+    /// - `base` - The base URL of the resources, with or without a trailing `/`
+    /// - `id` - The ID of the resource
+    pub fn to_resource<I>(base: &str, id: I) -> Self
+    where
+        I: Display,
+    {
+        format!("{}/{}", base.trim_end_matches('/'), template::encode(&id.to_string(), false)).into()
+    }
+
+    /// Append query parameters to the href of the link, e.g. `/search?q=foo&page=2`.
+    ///
+    /// Every name and value is percent-encoded, so they can contain any characters. Parameters that are already
+    /// in the href are kept, and the new ones are added after them, before any fragment.
+    ///
+    /// # Parameters
+    /// - `params` - The names and values of the query parameters, in order
+    pub fn with_query(mut self, params: &[(&str, &str)]) -> Self {
+        if params.is_empty() {
+            return self;
+        }
+
+        let query = params
+            .iter()
+            .map(|(name, value)| format!("{}={}", template::encode(name, false), template::encode(value, false)))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let fragment = self.href.find('#').map(|index| self.href.split_off(index));
+        match self.href.find('?') {
+            None => self.href.push('?'),
+            Some(_) if self.href.ends_with('?') || self.href.ends_with('&') => {},
+            Some(_) => self.href.push('&'),
+        }
+        self.href.push_str(&query);
+        if let Some(fragment) = fragment {
+            self.href.push_str(&fragment);
+        }
+
+        self
+    }
+
+    /// Build a link from a URI Template, as defined by RFC 6570.
+    ///
+    /// # Parameters
+    /// - `href` - The URI Template
+    pub fn templated<S>(href: S) -> Self
+    where
+        S: Into<String>,
+    {
+        Self {
+            href: href.into(),
+            templated: true,
+            ..Link::default()
+        }
+    }
+
+    /// Add a custom numeric property to the link, e.g. the `count` of results behind a search link. The value is
+    /// always serialized as a JSON number, replacing any existing hint with the same key.
+    ///
+    /// Keys that clash with the properties defined by HAL, such as `href` or `name`, are rejected and the hint is
+    /// ignored.
+    ///
+    /// # Parameters
+    /// - `key` - The key of the property
+    /// - `value` - The value of the property
+    pub fn with_hint<S>(mut self, key: S, value: i64) -> Self
+    where
+        S: Into<String>,
+    {
+        let key = key.into();
+        if LINK_PROPERTIES.contains(&key.as_str()) {
+            tracing::error!(key = ?key, "Link hint clashes with a standard link property");
+        } else {
+            self.hints.insert(key, value);
+        }
+
+        self
+    }
+
+    /// Specify the context of the link when it is sent as a `Link` header, as the `anchor` parameter defined by
+    /// RFC 8288, e.g. for a link about an embedded resource rather than the resource being responded to. The
+    /// anchor only affects `Link` headers, since links in a HAL body already belong to the resource they are in.
+    ///
+    /// An anchor that isn't a well-formed URI-reference is rejected and ignored.
+    ///
+    /// # Parameters
+    /// - `anchor` - The URI-reference of the context of the link, e.g. `/users/1`
+    pub fn with_anchor<S>(mut self, anchor: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let anchor = anchor.into();
+        if is_uri_reference(&anchor) {
+            self.anchor = Some(anchor);
+        } else {
+            tracing::error!(anchor = ?anchor, "Link anchor is not a valid URI-reference");
+        }
+
+        self
+    }
+
+    /// Expand the URI Template of this link with the provided variables.
+    ///
+    /// Variables that aren't provided are left in the template, in which case the resulting link is still
+    /// templated. A link that isn't templated is returned unchanged.
+    ///
+    /// # Parameters
+    /// - `vars` - The variables to expand the template with
+    ///
+    /// # Errors
+    /// If the URI Template is malformed
+    pub fn expand(&self, vars: &BTreeMap<String, String>) -> Result<Link, TemplateError> {
+        if !self.templated {
+            return Ok(self.clone());
+        }
+
+        let (href, templated) = template::expand(&self.href, vars)?;
+        Ok(Self {
+            href,
+            templated,
+            ..self.clone()
+        })
+    }
+}
+
+/// The cardinality with which a link relation is serialized.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cardinality {
+    /// Always serialize the relation as an array, even with a single link.
+    Array,
+    /// Serialize the relation as an object whenever it has a single link.
+    Object,
 }
 
 impl Links {
     pub fn push(self, new: Link) -> Self {
         match self {
+            Links::Null => Self::Single(new),
+            Links::Multiple(previous) if previous.is_empty() => Self::Single(new),
             Links::Single(first) => Self::Multiple(vec![first, new]),
             Links::Multiple(mut previous) => {
                 previous.push(new);
@@ -26,6 +340,72 @@ impl Links {
             },
         }
     }
+
+    /// Get every link in this relation.
+    pub fn iter(&self) -> std::slice::Iter<'_, Link> {
+        match self {
+            Links::Single(link) => std::slice::from_ref(link).iter(),
+            Links::Multiple(links) => links.iter(),
+            Links::Null => [].iter(),
+        }
+    }
+
+    /// Get mutable access to every link in this relation.
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, Link> {
+        match self {
+            Links::Single(link) => std::slice::from_mut(link).iter_mut(),
+            Links::Multiple(links) => links.iter_mut(),
+            Links::Null => [].iter_mut(),
+        }
+    }
+
+    /// Sort these links into a stable order, by `href`, then by `name`, and then by their remaining fields.
+    pub fn sort(&mut self) {
+        if let Links::Multiple(links) = self {
+            links.sort();
+        }
+    }
+
+    /// Tidy up these links, e.g. after they have been assembled from several places.
+    ///
+    /// # Parameters
+    /// - `collapse` - Whether to collapse a `Multiple` with only one link into a `Single`
+    /// - `dedup` - Whether to remove links that are equal to an earlier one, keeping the order of the rest
+    pub fn normalize(self, collapse: bool, dedup: bool) -> Self {
+        let links = match self {
+            Links::Multiple(links) if dedup => {
+                let mut unique: Vec<Link> = Vec::with_capacity(links.len());
+                for link in links {
+                    if !unique.contains(&link) {
+                        unique.push(link);
+                    }
+                }
+                Links::Multiple(unique)
+            },
+            links => links,
+        };
+
+        if collapse {
+            links.with_cardinality(Cardinality::Object)
+        } else {
+            links
+        }
+    }
+
+    /// Convert these links to match the requested cardinality.
+    ///
+    /// A relation with more than one link is always an array, regardless of the cardinality requested, and an
+    /// explicitly empty relation keeps the form it was given.
+    ///
+    /// # Parameters
+    /// - `cardinality` - The cardinality to convert to
+    pub fn with_cardinality(self, cardinality: Cardinality) -> Self {
+        match (cardinality, self) {
+            (Cardinality::Array, Links::Single(link)) => Links::Multiple(vec![link]),
+            (Cardinality::Object, Links::Multiple(mut links)) if links.len() == 1 => Links::Single(links.remove(0)),
+            (_, links) => links,
+        }
+    }
 }
 
 impl<S> From<S> for Link
@@ -40,12 +420,71 @@ where
     }
 }
 
+/// Collect links into a single relation.
+///
+/// A single link produces `Links::Single`, and anything else produces `Links::Multiple`. This includes no links
+/// at all, which gives an empty `Links::Multiple` that serializes as an empty array.
+impl<L> FromIterator<L> for Links
+where
+    L: Into<Link>,
+{
+    fn from_iter<I>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = L>,
+    {
+        let mut links: Vec<Link> = iter.into_iter().map(Into::into).collect();
+        if links.len() == 1 {
+            Links::Single(links.remove(0))
+        } else {
+            Links::Multiple(links)
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use actix_web::test::TestRequest;
     use assert2::{check, let_assert};
 
     use super::*;
 
+    #[test]
+    fn query_parameters() {
+        let search = |href: &str| Link::from(href).with_query(&[("q", "fish & chips"), ("page", "2")]).href;
+
+        check!(search("/search") == "/search?q=fish%20%26%20chips&page=2");
+        check!(search("/search?sort=name") == "/search?sort=name&q=fish%20%26%20chips&page=2");
+        check!(search("/search?") == "/search?q=fish%20%26%20chips&page=2");
+        check!(search("/search#results") == "/search?q=fish%20%26%20chips&page=2#results");
+        check!(Link::from("/search").with_query(&[]).href == "/search");
+    }
+
+    #[test]
+    fn trailing_slashes() {
+        let strip = |href| TrailingSlash::AlwaysStrip.normalize(href);
+        let add = |href| TrailingSlash::AlwaysAdd.normalize(href);
+
+        check!(strip("/users/") == "/users");
+        check!(strip("/users//?page=2#top") == "/users?page=2#top");
+        check!(strip("/users/{?page}") == "/users{?page}");
+        check!(strip("/") == "/");
+        check!(strip("https://example.com/users/") == "https://example.com/users");
+        check!(strip("https://example.com/") == "https://example.com/");
+        check!(add("/users?page=2") == "/users/?page=2");
+        check!(add("/users/") == "/users/");
+        check!(add("https://example.com") == "https://example.com");
+        check!(TrailingSlash::Leave.normalize("/users/") == "/users/");
+    }
+
+    #[test]
+    fn anchor() {
+        let anchor = "/posts/1?full=true#author";
+        check!(Link::from("/a").with_anchor(anchor).anchor == Some(anchor.to_owned()));
+        check!(Link::from("/a").with_anchor("/posts/%2F1").anchor.is_some());
+        check!(Link::from("/a").with_anchor("/posts/ 1").anchor == None);
+        check!(Link::from("/a").with_anchor("/posts/%1").anchor == None);
+    }
+
     #[test]
     fn append_to_single() {
         let first = Links::Single("/first".into());
@@ -68,4 +507,137 @@ mod tests {
         check!(links[1].href == "/second");
         check!(links[2].href == "/third");
     }
+
+    #[test]
+    fn link_for_request() {
+        let req = TestRequest::with_uri("/users/123?page=2").to_http_request();
+
+        check!(Link::for_request(&req, true).href == "http://localhost:8080/users/123?page=2");
+        check!(Link::for_request(&req, false).href == "http://localhost:8080/users/123");
+    }
+
+    #[test]
+    fn link_for_proxied_request() {
+        let req = TestRequest::with_uri("/users/123")
+            .insert_header(("x-forwarded-proto", "https"))
+            .insert_header(("x-forwarded-host", "api.example.com"))
+            .to_http_request();
+
+        check!(Link::for_request(&req, true).href == "https://api.example.com/users/123");
+    }
+
+    #[test]
+    fn expand_templated_link() {
+        let mut vars = BTreeMap::new();
+        vars.insert("id".to_owned(), "42".to_owned());
+
+        let link = Link::templated("/users/{id}").expand(&vars).unwrap();
+        check!(link.href == "/users/42");
+        check!(!link.templated);
+
+        let link = Link::templated("/users/{id}{?page}").expand(&vars).unwrap();
+        check!(link.href == "/users/42{?page}");
+        check!(link.templated);
+        check!(serde_json::to_value(link).unwrap()["templated"] == true);
+    }
+
+    #[test]
+    fn single_as_array() {
+        let links = Links::Single("/first".into()).with_cardinality(Cardinality::Array);
+
+        let_assert!(Links::Multiple(links) = links);
+        check!(links.len() == 1);
+        check!(links[0].href == "/first");
+    }
+
+    #[test]
+    fn multiple_of_one_as_object() {
+        let links = Links::Multiple(vec!["/first".into()]).with_cardinality(Cardinality::Object);
+
+        let_assert!(Links::Single(link) = links);
+        check!(link.href == "/first");
+    }
+
+    #[test]
+    fn multiple_as_object() {
+        let links = Links::Multiple(vec!["/first".into(), "/second".into()]).with_cardinality(Cardinality::Object);
+
+        let_assert!(Links::Multiple(links) = links);
+        check!(links.len() == 2);
+    }
+
+    #[test]
+    fn collect_links() {
+        let_assert!(Links::Single(link) = vec!["/first"].into_iter().collect::<Links>());
+        check!(link.href == "/first");
+
+        let_assert!(Links::Multiple(links) = vec!["/first", "/second"].into_iter().collect::<Links>());
+        check!(links.len() == 2);
+
+        let_assert!(Links::Multiple(links) = Vec::<Link>::new().into_iter().collect::<Links>());
+        check!(links.is_empty());
+    }
+
+    #[test]
+    fn link_for_unknown_route() {
+        let req = TestRequest::with_uri("/users/123").to_http_request();
+
+        check!(Link::for_route(&req, "user", ["123"]).is_err());
+    }
+
+    #[test]
+    fn link_to_resource() {
+        check!(Link::to_resource("/users", 42).href == "/users/42");
+        check!(Link::to_resource("/users/", 42).href == "/users/42");
+        check!(Link::to_resource("/files", "a b/c").href == "/files/a%20b%2Fc");
+    }
+
+    #[test]
+    fn normalize_links() {
+        let duplicated = || Links::Multiple(vec!["/first".into(), "/first".into()]);
+
+        let_assert!(Links::Single(link) = duplicated().normalize(true, true));
+        check!(link.href == "/first");
+
+        let_assert!(Links::Multiple(links) = duplicated().normalize(false, true));
+        check!(links.len() == 1);
+
+        let_assert!(Links::Multiple(links) = duplicated().normalize(true, false));
+        check!(links.len() == 2);
+    }
+
+    #[test]
+    fn push_to_empty() {
+        let_assert!(Links::Single(link) = Links::Null.push("/first".into()));
+        check!(link.href == "/first");
+
+        let_assert!(Links::Single(link) = Links::Multiple(vec![]).push("/first".into()));
+        check!(link.href == "/first");
+    }
+
+    #[test]
+    fn link_from_uri() {
+        let uri: Uri = "https://example.com/users/1?full=true".parse().unwrap();
+
+        check!(Link::from_uri(&uri).href == "https://example.com/users/1?full=true");
+    }
+
+    #[cfg(feature = "url")]
+    #[test]
+    fn link_from_url() {
+        let url = url::Url::parse("HTTPS://Example.com/users/../teams/1").unwrap();
+
+        check!(Link::from_url(&url).href == "https://example.com/teams/1");
+        check!(Link::from(url).href == "https://example.com/teams/1");
+    }
+
+    #[test]
+    fn numeric_hints() {
+        let link = Link::templated("/search{?q}").with_hint("count", 42).with_hint("max", 100).with_hint("href", 1);
+
+        check!(
+            serde_json::to_value(link).unwrap()
+                == serde_json::json!({"href": "/search{?q}", "templated": true, "count": 42, "max": 100})
+        );
+    }
 }
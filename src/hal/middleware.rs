@@ -0,0 +1,408 @@
+use std::{
+    fmt,
+    future::{ready, Future, Ready},
+    pin::Pin,
+    rc::Rc,
+    sync::Arc,
+};
+
+use actix_http::http::{header, HeaderMap, HeaderName, HeaderValue};
+use actix_service::{forward_ready, Service, Transform};
+use actix_web::{
+    body::AnyBody,
+    dev::{ServiceRequest, ServiceResponse},
+    web::Bytes,
+    Error, HttpRequest,
+};
+use serde::{
+    de::{MapAccess, Visitor},
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_json::value::{to_raw_value, RawValue};
+
+use super::Link;
+
+/// A function producing the link for a relation from the request being responded to.
+type LinkFn = Arc<dyn Fn(&HttpRequest) -> Option<Link> + Send + Sync>;
+
+/// Middleware that adds links from a registry to the `_links` of every HAL response, e.g. to give every resource
+/// a `home` or `search` link without each handler having to add it.
+///
+/// A response is treated as HAL if its `Content-Type` is `application/hal+json`. Any other response is passed
+/// through untouched, as is a HAL response whose body is streamed rather than buffered, e.g. from
+/// `HalStreamingRespondable`, or that isn't a JSON object. Links are only added for relations that the response
+/// doesn't already have, so a handler can always override the registry for its own resources.
+///
+/// Only the `_links` object is rewritten. Every other field, including nested objects and `_embedded`, is copied
+/// byte for byte, so the order of keys and relations is kept exactly as the handler serialized it. Since the body
+/// changes, a `Content-Digest` header is recomputed for the new body, or removed if it can't be, and a strong
+/// `ETag` is made weak, as the representation is still semantically equivalent to the one it was computed for.
+///
+/// The registry is `Send` and `Sync`, so it can be built once and cloned into the `HttpServer::new` factory.
+#[derive(Clone, Default)]
+pub struct HalLinksMiddleware {
+    links: Vec<(String, LinkFn)>,
+}
+
+impl HalLinksMiddleware {
+    /// Create a new instance of the `HalLinksMiddleware` struct with no links registered.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a link to add to every HAL response.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the link relation
+    /// - `link` - The link to add
+    pub fn with_link<S, L>(self, name: S, link: L) -> Self
+    where
+        S: Into<String>,
+        L: Into<Link>,
+    {
+        let link = link.into();
+
+        self.with_link_fn(name, move |_| Some(link.clone()))
+    }
+
+    /// Register a link to add to every HAL response, produced from the request being responded to. If the
+    /// function returns `None` then no link is added for that request.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the link relation
+    /// - `link` - The function to produce the link
+    pub fn with_link_fn<S, F>(mut self, name: S, link: F) -> Self
+    where
+        S: Into<String>,
+        F: Fn(&HttpRequest) -> Option<Link> + Send + Sync + 'static,
+    {
+        self.links.push((name.into(), Arc::new(link)));
+
+        self
+    }
+}
+
+impl<S> Transform<S, ServiceRequest> for HalLinksMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<AnyBody>, Error = Error> + 'static,
+{
+    type Response = ServiceResponse<AnyBody>;
+    type Error = Error;
+    type Transform = HalLinksService<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(HalLinksService {
+            service: Rc::new(service),
+            links:   Rc::new(self.links.clone()),
+        }))
+    }
+}
+
+/// Service created by `HalLinksMiddleware` to add the registered links to the responses of the wrapped service.
+pub struct HalLinksService<S> {
+    service: Rc<S>,
+    links:   Rc<Vec<(String, LinkFn)>>,
+}
+
+impl<S> Service<ServiceRequest> for HalLinksService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<AnyBody>, Error = Error> + 'static,
+{
+    type Response = ServiceResponse<AnyBody>;
+    type Error = Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>>>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+        let links = Rc::clone(&self.links);
+
+        Box::pin(async move {
+            let response = service.call(req).await?;
+
+            let is_hal = response
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .is_some_and(|value| value.starts_with("application/hal+json"));
+            if !is_hal {
+                return Ok(response);
+            }
+
+            let request = response.request().clone();
+            Ok(response.map_body(|head, body| match body {
+                AnyBody::Bytes(bytes) => match add_links(&bytes, &links, &request) {
+                    Some(updated) => {
+                        update_validators(head.headers_mut(), &updated);
+                        AnyBody::Bytes(Bytes::from(updated))
+                    },
+                    None => AnyBody::Bytes(bytes),
+                },
+                body => body,
+            }))
+        })
+    }
+}
+
+/// The fields of a JSON object, in the order they were serialized, with their values kept exactly as serialized.
+struct RawObject(Vec<(String, Box<RawValue>)>);
+
+impl Serialize for RawObject {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.collect_map(self.0.iter().map(|(name, value)| (name, value)))
+    }
+}
+
+impl<'de> Deserialize<'de> for RawObject {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(RawObjectVisitor)
+    }
+}
+
+/// Visitor to deserialize the fields of a JSON object while retaining their order and serialization.
+struct RawObjectVisitor;
+
+impl<'de> Visitor<'de> for RawObjectVisitor {
+    type Value = RawObject;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a JSON object")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut fields = Vec::new();
+        while let Some(field) = map.next_entry()? {
+            fields.push(field);
+        }
+
+        Ok(RawObject(fields))
+    }
+}
+
+/// Add the registered links to a serialized HAL document, for any relations that it doesn't already have.
+///
+/// # Parameters
+/// - `body` - The serialized HAL document
+/// - `links` - The registered links
+/// - `req` - The request being responded to
+///
+/// # Returns
+/// The updated document, or `None` if it should be sent unchanged
+fn add_links(body: &[u8], links: &[(String, LinkFn)], req: &HttpRequest) -> Option<Vec<u8>> {
+    let RawObject(mut fields) = serde_json::from_slice(body).ok()?;
+    let index = fields.iter().position(|(name, _)| name == "_links");
+    let RawObject(mut relations) = match index {
+        Some(index) => serde_json::from_str(fields[index].1.get()).ok()?,
+        None => RawObject(vec![]),
+    };
+
+    let mut added = false;
+    for (name, link) in links {
+        if relations.iter().any(|(relation, _)| relation == name) {
+            continue;
+        }
+        let link = match link(req).map(|link| to_raw_value(&link)) {
+            Some(Ok(link)) => link,
+            Some(Err(e)) => {
+                tracing::error!(e = ?e, name = ?name, "Failed to serialize registered link");
+                continue;
+            },
+            None => continue,
+        };
+        relations.push((name.clone(), link));
+        added = true;
+    }
+
+    if !added {
+        return None;
+    }
+
+    let relations = to_raw_value(&RawObject(relations)).ok()?;
+    match index {
+        Some(index) => fields[index].1 = relations,
+        None => fields.insert(0, ("_links".to_owned(), relations)),
+    }
+
+    serde_json::to_vec(&RawObject(fields)).ok()
+}
+
+/// Update the headers that validate the body of a response after the body has changed.
+///
+/// # Parameters
+/// - `headers` - The headers of the response
+/// - `body` - The new body
+#[cfg_attr(not(feature = "content-digest"), allow(unused_variables))]
+fn update_validators(headers: &mut HeaderMap, body: &[u8]) {
+    let content_digest = HeaderName::from_static("content-digest");
+    #[cfg(feature = "content-digest")]
+    let digest = headers.get(&content_digest).and_then(|original| crate::response::digest::recompute(original, body));
+    #[cfg(not(feature = "content-digest"))]
+    let digest = None;
+    match digest {
+        Some(digest) => headers.insert(content_digest, digest),
+        None => headers.remove(content_digest),
+    };
+
+    let weak = headers
+        .get(header::ETAG)
+        .and_then(|etag| etag.to_str().ok())
+        .filter(|etag| etag.starts_with('"'))
+        .and_then(|etag| HeaderValue::from_str(&format!("W/{}", etag)).ok());
+    if let Some(weak) = weak {
+        headers.insert(header::ETAG, weak);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        rt::System,
+        test::{call_service, init_service, read_body, TestRequest},
+        web, App,
+    };
+    use assert2::check;
+    use serde_json::{json, Value};
+
+    use super::*;
+    #[cfg(feature = "content-digest")]
+    use crate::response::DigestAlgorithm;
+    use crate::{hal::HalRespondable, response::Response};
+
+    fn respond(path: &str) -> Value {
+        System::new().block_on(async {
+            let middleware = HalLinksMiddleware::new()
+                .with_link("home", "/")
+                .with_link_fn("current", |req| Some(Link::from(req.path())))
+                .with_link_fn("never", |_| None);
+            let app = init_service(
+                App::new()
+                    .wrap(middleware)
+                    .route("/hal", web::get().to(|| async { Response::new(HalRespondable::new(json!({"id": 1}))) }))
+                    .route(
+                        "/overridden",
+                        web::get().to(|| async {
+                            Response::new(HalRespondable::new(json!({})).with_link("home", Link::from("/me")))
+                        }),
+                    )
+                    .route("/json", web::get().to(|| async { web::Json(json!({"id": 1})) })),
+            )
+            .await;
+
+            let req = TestRequest::get().uri(path).to_request();
+            let body = read_body(call_service(&app, req).await).await;
+            serde_json::from_slice(&body).unwrap()
+        })
+    }
+
+    #[test]
+    fn links_added() {
+        check!(
+            respond("/hal")
+                == json!({
+                    "id": 1,
+                    "_links": {"home": {"href": "/"}, "current": {"href": "/hal"}}
+                })
+        );
+    }
+
+    #[test]
+    fn existing_relations_kept() {
+        check!(
+            respond("/overridden")
+                == json!({
+                    "_links": {"home": {"href": "/me"}, "current": {"href": "/overridden"}}
+                })
+        );
+    }
+
+    #[test]
+    fn other_responses_untouched() {
+        check!(respond("/json") == json!({"id": 1}));
+    }
+
+    #[test]
+    fn field_order_kept() {
+        let links: Vec<(String, LinkFn)> = vec![("home".to_owned(), Arc::new(|_| Some(Link::from("/"))))];
+        let req = TestRequest::default().to_http_request();
+        let updated = add_links(
+            br#"{"b":{"z":1,"a":[{"y":1,"x":2}]},"_links":{"self":{"href":"/x"},"author":{"href":"/a"}},"a":2}"#,
+            &links,
+            &req,
+        )
+        .unwrap();
+
+        check!(
+            std::str::from_utf8(&updated).unwrap()
+                == concat!(
+                    r#"{"b":{"z":1,"a":[{"y":1,"x":2}]},"#,
+                    r#""_links":{"self":{"href":"/x"},"author":{"href":"/a"},"home":{"href":"/"}},"a":2}"#
+                )
+        );
+    }
+
+    #[test]
+    fn canonical_order_kept() {
+        let links: Vec<(String, LinkFn)> = vec![("home".to_owned(), Arc::new(|_| Some(Link::from("/"))))];
+        let req = TestRequest::default().to_http_request();
+        let body = concat!(
+            r#"{"_links":{"self":{"href":"/x"},"curies":[{"name":"acme","href":"/rels/{rel}","templated":true}],"#,
+            r#""author":{"href":"/a"}},"_embedded":{"self":{"b":1,"a":2}},"a":2}"#
+        );
+        let updated = add_links(body.as_bytes(), &links, &req).unwrap();
+
+        check!(
+            std::str::from_utf8(&updated).unwrap()
+                == body.replacen(r#""author":{"href":"/a"}}"#, r#""author":{"href":"/a"},"home":{"href":"/"}}"#, 1)
+        );
+    }
+
+    #[test]
+    fn validators_updated() {
+        System::new().block_on(async {
+            let app = init_service(App::new().wrap(HalLinksMiddleware::new().with_link("home", "/")).route(
+                "/",
+                web::get().to(|| async {
+                    let respondable = HalRespondable::new(json!({"id": 1})).with_header_value(header::ETAG, "\"1\"");
+                    #[cfg(feature = "content-digest")]
+                    let response = Response::new(respondable).with_content_digest(DigestAlgorithm::Sha256);
+                    #[cfg(not(feature = "content-digest"))]
+                    let response = Response::new(
+                        respondable.with_header_value(HeaderName::from_static("content-digest"), "sha-256=:AAAA:"),
+                    );
+                    response
+                }),
+            ))
+            .await;
+
+            let res = call_service(&app, TestRequest::get().to_request()).await;
+            check!(res.headers().get(header::ETAG).unwrap() == "W/\"1\"");
+            let digest = res.headers().get("content-digest").cloned();
+            let body = read_body(res).await;
+            check!(body.as_ref() != br#"{"id":1}"#);
+
+            #[cfg(feature = "content-digest")]
+            check!(digest == crate::response::digest::header(&[DigestAlgorithm::Sha256], &body));
+            #[cfg(not(feature = "content-digest"))]
+            check!(digest.is_none());
+        });
+    }
+
+    #[test]
+    fn registry_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<HalLinksMiddleware>();
+    }
+}
@@ -1,16 +1,173 @@
 use std::{
-    collections::BTreeMap,
+    collections::{BTreeMap, BTreeSet, HashMap},
+    fmt::Display,
     ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        RwLock,
+    },
+    time::{Duration, SystemTime},
 };
 
 use actix_http::http::{
-    header::{self, Header, IntoHeaderValue},
+    header::{self, Header, HttpDate, IntoHeaderValue},
     HeaderMap, HeaderName, HeaderValue, StatusCode,
 };
+use actix_web::{web, HttpRequest, HttpResponse};
 use serde::Serialize;
 
-use super::{HalResponse, Link, Links};
-use crate::response::Respondable;
+use super::{
+    Cardinality, CollectionMeta, CollectionResponse, Embedded, EmbeddedMeta, EmbeddedResource, EmptyForm, Expansion,
+    HalResponse, Link, Links, TrailingSlash,
+};
+use crate::response::{conditional, json, NegotiationMode, Respondable, Response};
+
+/// The profile added to the content type of every HAL response that doesn't specify its own.
+static HAL_PROFILE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Specify a profile to add to the content type of every HAL response, e.g. to identify the version of the API
+/// as `application/hal+json; profile="https://acme.com/profiles/v3"`.
+///
+/// Responses that specify their own profile with `HalRespondable::with_profile` use that instead.
+///
+/// # Parameters
+/// - `profile` - The profile URI, or `None` to not add a profile
+pub fn set_hal_profile<S>(profile: Option<S>)
+where
+    S: Into<String>,
+{
+    *HAL_PROFILE.write().unwrap_or_else(|e| e.into_inner()) = profile.map(Into::into);
+}
+
+/// Whether the relations of `_links` and `_embedded` are serialized in canonical order.
+static CANONICAL_RELATION_ORDER: AtomicBool = AtomicBool::new(false);
+
+/// Specify whether the relations of `_links` and `_embedded` are serialized in canonical order - `self`, then
+/// `curies`, then every other relation alphabetically. Defaults to `false`, in which case they are serialized
+/// alphabetically.
+///
+/// This gives stable, readable output for contract and snapshot tests. The document itself is then serialized
+/// with `_links` and `_embedded` first, followed by every other field alphabetically, including for documents
+/// that are restricted to sparse fieldsets, wrapped in an envelope or have their nulls omitted. Relations of
+/// embedded resources are always ordered alphabetically.
+///
+/// # Parameters
+/// - `canonical` - Whether to serialize relations in canonical order
+pub fn canonical_relation_order(canonical: bool) {
+    CANONICAL_RELATION_ORDER.store(canonical, Ordering::Relaxed);
+}
+
+/// A serialized HAL response, or part of one, written with its relations in canonical order.
+enum Canonical<'a> {
+    /// An envelope, with the HAL document under the provided key
+    Envelope(&'a serde_json::Value, &'a str),
+    /// A HAL document, written with `_links` and then `_embedded` first
+    Document(&'a serde_json::Value),
+    /// The relations of `_links` or `_embedded`, written with `self` and then `curies` first
+    Relations(&'a serde_json::Map<String, serde_json::Value>),
+    /// Any other value, written as it is
+    Value(&'a serde_json::Value),
+}
+
+impl Serialize for Canonical<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match *self {
+            Canonical::Envelope(serde_json::Value::Object(map), key) => {
+                serializer.collect_map(map.iter().map(|(name, value)| {
+                    if name == key {
+                        (name, Canonical::Document(value))
+                    } else {
+                        (name, Canonical::Value(value))
+                    }
+                }))
+            },
+            Canonical::Document(serde_json::Value::Object(map)) => {
+                let entries = leading(map, &["_links", "_embedded"]);
+                serializer.collect_map(entries.map(|(name, value)| match value {
+                    serde_json::Value::Object(relations) if name == "_links" || name == "_embedded" => {
+                        (name, Canonical::Relations(relations))
+                    },
+                    value => (name, Canonical::Value(value)),
+                }))
+            },
+            Canonical::Relations(relations) => serializer.collect_map(leading(relations, &["self", "curies"])),
+            Canonical::Envelope(value, _) | Canonical::Document(value) | Canonical::Value(value) => {
+                value.serialize(serializer)
+            },
+        }
+    }
+}
+
+/// Iterate over the entries of a JSON object with the provided keys first, in that order, followed by every other
+/// entry in the order of the object.
+///
+/// # Parameters
+/// - `map` - The JSON object
+/// - `first` - The keys to put first
+fn leading<'a>(
+    map: &'a serde_json::Map<String, serde_json::Value>,
+    first: &'static [&'static str],
+) -> impl Iterator<Item = (&'a String, &'a serde_json::Value)> {
+    let leading = first.iter().filter_map(move |key| map.get_key_value(*key));
+
+    leading.chain(map.iter().filter(move |(key, _)| !first.contains(&key.as_str())))
+}
+
+/// The process-wide settings that apply to every HAL response, read once as each response is built.
+#[derive(Debug, Clone, Default)]
+struct Settings {
+    profile:         Option<String>,
+    canonical_order: bool,
+    sort_links:      bool,
+    trailing_slash:  TrailingSlash,
+}
+
+impl Settings {
+    /// Read the settings that are currently configured.
+    fn global() -> Self {
+        Self {
+            profile:         HAL_PROFILE.read().unwrap_or_else(|e| e.into_inner()).clone(),
+            canonical_order: CANONICAL_RELATION_ORDER.load(Ordering::Relaxed),
+            sort_links:      SORT_MULTIPLE_LINKS.load(Ordering::Relaxed),
+            trailing_slash:  *TRAILING_SLASH.read().unwrap_or_else(|e| e.into_inner()),
+        }
+    }
+}
+
+/// Whether the links of every relation with multiple links are sorted.
+static SORT_MULTIPLE_LINKS: AtomicBool = AtomicBool::new(false);
+
+/// Specify whether the links of every relation with multiple links are sorted before serialization, so that
+/// they are serialized in a stable order however they were assembled. Defaults to `false`, in which case links
+/// are serialized in the order they were added.
+///
+/// Links are sorted by `href`, then by `name`, and then by their other fields. Individual relations can be
+/// sorted instead with `HalRespondable::with_sorted_links`.
+///
+/// # Parameters
+/// - `sort` - Whether to sort multiple links
+pub fn sort_multiple_links(sort: bool) {
+    SORT_MULTIPLE_LINKS.store(sort, Ordering::Relaxed);
+}
+
+/// The policy for normalizing trailing slashes on the hrefs of every link.
+static TRAILING_SLASH: RwLock<TrailingSlash> = RwLock::new(TrailingSlash::Leave);
+
+/// Specify how trailing slashes are normalized on the hrefs of every link of a HAL response, both in the body
+/// and in `Link` headers, so that links are consistent with routing that is strict about them. Defaults to
+/// `TrailingSlash::Leave`, in which case hrefs are used exactly as they were given.
+///
+/// This is applied with `HalRespondable::visit_links` to the links of the response as it is built, and in the same
+/// way to the links of every resource embedded in it, at any depth, as well as to its `Link` headers.
+///
+/// # Parameters
+/// - `policy` - The policy for trailing slashes
+pub fn trailing_slash_policy(policy: TrailingSlash) {
+    *TRAILING_SLASH.write().unwrap_or_else(|e| e.into_inner()) = policy;
+}
 
 /// Respondable to represent a HAL resource.
 #[derive(Debug)]
@@ -18,10 +175,51 @@ pub struct HalRespondable<T>
 where
     T: Serialize,
 {
-    payload:     T,
-    status_code: StatusCode,
-    headers:     Headers,
-    links:       BTreeMap<String, Links>,
+    payload:              T,
+    status_code:          StatusCode,
+    headers:              Headers,
+    links:                BTreeMap<String, Links>,
+    embedded:             BTreeMap<String, Embedded>,
+    cardinality:          BTreeMap<String, Cardinality>,
+    embedded_cardinality: BTreeMap<String, Cardinality>,
+    negotiation:          NegotiationMode,
+    meta:                 BTreeMap<String, CollectionMeta>,
+    embedded_meta:        BTreeMap<String, EmbeddedMeta>,
+    embedded_meta_key:    String,
+    omit_nulls:           bool,
+    has_body:             bool,
+    fields:               Option<BTreeSet<String>>,
+    envelope:             Option<Envelope>,
+    link_header:          bool,
+    curies:               Vec<(String, String)>,
+    profile:              Option<String>,
+    sorted_links:         BTreeSet<String>,
+    embedded_failures:    EmbeddedFailureMode,
+    dynamic_headers:      DynamicHeaders,
+}
+
+/// Closure to compute headers of a response from the request it is in response to.
+type DynamicHeader = Box<dyn FnOnce(&HttpRequest, &mut Headers) + Send>;
+
+/// The closures to compute headers of a response from the request, run in the order they were added.
+#[derive(Default)]
+struct DynamicHeaders(Vec<DynamicHeader>);
+
+impl std::fmt::Debug for DynamicHeaders {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "DynamicHeaders({})", self.0.len())
+    }
+}
+
+/// How to handle embedded resources that failed to serialize.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum EmbeddedFailureMode {
+    /// Fail the entire response, as a `500 Internal Server Error`.
+    #[default]
+    FailFast,
+    /// Leave the failed resources out, logging each one, and serialize the rest. The number left out of each
+    /// relation is recorded as `skipped` in its embedded metadata.
+    Skip,
 }
 
 /// The actual JSON payload of a HAL resource.
@@ -31,9 +229,34 @@ where
     T: Serialize,
 {
     #[serde(rename = "_links")]
-    pub links:   BTreeMap<String, Links>,
+    pub links:         BTreeMap<String, Links>,
+    #[serde(rename = "_embedded", skip_serializing_if = "BTreeMap::is_empty")]
+    pub embedded:      BTreeMap<String, Embedded>,
+    #[serde(flatten)]
+    pub meta:          BTreeMap<String, CollectionMeta>,
+    /// The metadata of the embedded relations, nested under the configured key
     #[serde(flatten)]
-    pub payload: T,
+    pub embedded_meta: BTreeMap<String, BTreeMap<String, EmbeddedMeta>>,
+    #[serde(flatten)]
+    pub payload:       T,
+    /// The top-level fields to restrict the serialized payload to, if any. `_links` is always kept.
+    #[serde(skip)]
+    pub fields:        Option<BTreeSet<String>>,
+    /// The envelope to wrap the serialized payload in, if any.
+    #[serde(skip)]
+    pub envelope:      Option<Envelope>,
+    /// Whether the relations are serialized in canonical order.
+    #[serde(skip)]
+    pub canonical_order: bool,
+}
+
+/// Envelope to wrap a serialized HAL document in, for clients that expect the document under a top-level key.
+#[derive(Debug, Clone, Default)]
+pub struct Envelope {
+    /// The key to nest the HAL document under
+    pub key:    String,
+    /// Any other top-level fields to include alongside the HAL document
+    pub fields: BTreeMap<String, serde_json::Value>,
 }
 
 impl<T> HalRespondable<T>
@@ -53,6 +276,79 @@ where
             status_code: StatusCode::OK,
             headers,
             links: BTreeMap::new(),
+            embedded: BTreeMap::new(),
+            cardinality: BTreeMap::new(),
+            embedded_cardinality: BTreeMap::new(),
+            negotiation: NegotiationMode::default(),
+            meta: BTreeMap::new(),
+            embedded_meta: BTreeMap::new(),
+            embedded_meta_key: "_meta".to_owned(),
+            omit_nulls: false,
+            has_body: true,
+            fields: None,
+            envelope: None,
+            link_header: false,
+            curies: vec![],
+            profile: None,
+            sorted_links: BTreeSet::new(),
+            embedded_failures: EmbeddedFailureMode::default(),
+            dynamic_headers: DynamicHeaders::default(),
+        }
+    }
+
+    /// Create a new `202 Accepted` HAL Respondable for a request that will be processed asynchronously.
+    ///
+    /// The `Location` header and the `status` link both point to the resource that can be polled for the status
+    /// of the processing. A `Retry-After` header can be added with `with_retry_after` to say when to poll.
+    ///
+    /// # Parameters
+    /// - `payload` - The payload of the response
+    /// - `status` - The link to the status resource
+    pub fn accepted<L>(payload: T, status: L) -> Self
+    where
+        L: Into<Link>,
+    {
+        let status = status.into();
+
+        Self::new(payload)
+            .with_status_code(StatusCode::ACCEPTED)
+            .with_header_value(header::LOCATION, status.href.as_str())
+            .with_link("status", status)
+    }
+
+    /// Turn this into a `201 Created` response for a newly created resource.
+    ///
+    /// The `Location` header and the `self` link both point to the new resource, replacing any that were already
+    /// set, and so does the `self` link header if `Link` headers are enabled with `with_link_header`.
+    ///
+    /// # Parameters
+    /// - `location` - The link to the new resource
+    pub fn created<L>(mut self, location: L) -> Self
+    where
+        L: Into<Link>,
+    {
+        let location = location.into();
+
+        self.headers.remove(header::LOCATION);
+        self.links.insert("self".to_owned(), Links::Single(location.clone()));
+        self.with_status_code(StatusCode::CREATED)
+            .with_header_value(header::LOCATION, location.href.as_str())
+    }
+
+    /// Create a new `200 OK` HAL Respondable for the result of updating a resource, e.g. with `PATCH`.
+    ///
+    /// The response carries an `ETag` for the new state of the resource, so that the client can make further
+    /// conditional updates. A `self` link can be added as normal.
+    ///
+    /// # Parameters
+    /// - `payload` - The full updated resource
+    /// - `etag` - The entity tag of the new state, or `None` to compute a weak one from the payload
+    pub fn updated(payload: T, etag: Option<&str>) -> Self {
+        let respondable = Self::new(payload);
+
+        match etag {
+            Some(etag) => respondable.with_etag(etag),
+            None => respondable.with_etag_excluding(&[]),
         }
     }
 
@@ -66,6 +362,71 @@ where
         self
     }
 
+    /// Remove the body from the response, so that it has only a status code and headers. This also removes the
+    /// `Content-Type` header, since there is no content for it to describe.
+    pub fn without_body(mut self) -> Self {
+        self.headers.remove(header::CONTENT_TYPE);
+        self.has_body = false;
+
+        self
+    }
+
+    /// Convert the payload of the response, keeping everything else unchanged.
+    ///
+    /// # Parameters
+    /// - `f` - Closure to convert the payload
+    pub fn map_payload<U, F>(self, f: F) -> HalRespondable<U>
+    where
+        U: Serialize,
+        F: FnOnce(T) -> U,
+    {
+        HalRespondable {
+            payload:              f(self.payload),
+            status_code:          self.status_code,
+            headers:              self.headers,
+            links:                self.links,
+            embedded:             self.embedded,
+            cardinality:          self.cardinality,
+            embedded_cardinality: self.embedded_cardinality,
+            negotiation:          self.negotiation,
+            meta:                 self.meta,
+            embedded_meta:        self.embedded_meta,
+            embedded_meta_key:    self.embedded_meta_key,
+            omit_nulls:           self.omit_nulls,
+            has_body:             self.has_body,
+            fields:               self.fields,
+            envelope:             self.envelope,
+            link_header:          self.link_header,
+            curies:               self.curies,
+            profile:              self.profile,
+            sorted_links:         self.sorted_links,
+            embedded_failures:    self.embedded_failures,
+            dynamic_headers:      self.dynamic_headers,
+        }
+    }
+
+    /// Specify how to respond when the client accepts neither `application/hal+json` nor `application/json`.
+    ///
+    /// # Parameters
+    /// - `negotiation` - The negotiation mode
+    pub fn with_negotiation_mode(mut self, negotiation: NegotiationMode) -> Self {
+        self.negotiation = negotiation;
+
+        self
+    }
+
+    /// Specify whether to remove all null-valued keys from the serialized payload.
+    ///
+    /// This applies to nested objects and embedded resources as well, but never to `_links`.
+    ///
+    /// # Parameters
+    /// - `omit_nulls` - Whether to remove null-valued keys
+    pub fn omit_nulls(mut self, omit_nulls: bool) -> Self {
+        self.omit_nulls = omit_nulls;
+
+        self
+    }
+
     /// Add a header to the response.
     ///
     /// # Parameters
@@ -92,140 +453,2595 @@ where
         self.with_header_value(H::name(), header)
     }
 
-    /// Add a link to the response.
+    /// Add a header to the response only if it doesn't already have a header with that name.
     ///
     /// # Parameters
-    /// - `name` - The name of the link
-    /// - `link` - The actual link
-    pub fn with_link<S, L>(mut self, name: S, link: L) -> Self
+    /// - `name` - The name of the header
+    /// - `value` - The value of the header to add if it is absent
+    pub fn with_default_header<N, V>(mut self, name: N, value: V) -> Self
     where
-        S: Into<String>,
-        L: Into<Link>,
+        N: Into<HeaderName>,
+        V: IntoHeaderValue,
     {
-        let name = name.into();
-        let links = match self.links.remove(&name) {
-            None => Links::Single(link.into()),
-            Some(links) => links.push(link.into()),
-        };
-        self.links.insert(name, links);
+        self.headers.entry_or_insert(name, value);
 
         self
     }
-}
 
-impl<T> Respondable for HalRespondable<T>
-where
-    T: Serialize,
-{
-    type Body = HalPayload<T>;
+    /// Configure the headers of the response in a single block.
+    ///
+    /// # Parameters
+    /// - `f` - Closure that is given the headers to modify in place
+    pub fn with_headers_with<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&mut Headers),
+    {
+        f(&mut self.headers);
 
-    fn body(self) -> Self::Body {
-        HalPayload {
-            payload: self.payload,
-            links:   self.links,
+        self
+    }
+
+    /// Configure the headers of the response from the request it is in response to, for headers that can't be
+    /// known when the respondable is built, e.g. `Vary` values or correlation IDs.
+    ///
+    /// The closure runs once the response is being sent, after every header set on the respondable, so it can
+    /// override them. It runs before the profile is added to the `Content-Type` and before the `Link` headers
+    /// from `with_link_header` are appended, so it can't remove or replace those, and a `Content-Type` it sets is
+    /// only given the profile if it is still `application/hal+json`. This only happens when responding through
+    /// `Responder::respond_to`. Responses built directly with `Response::into_http_response` have no request, so
+    /// the closure never runs.
+    ///
+    /// # Parameters
+    /// - `f` - Closure that is given the request and the headers to modify in place
+    pub fn with_dynamic_headers<F>(mut self, f: F) -> Self
+    where
+        F: FnOnce(&HttpRequest, &mut Headers) + Send + 'static,
+    {
+        self.dynamic_headers.0.push(Box::new(f));
+
+        self
+    }
+
+    /// Check that every header added to the response was valid, so that all of the failures can be handled at
+    /// once before responding instead of only being logged.
+    ///
+    /// See `Headers` for the builder methods whose failures are checked. The builder methods of the same names on
+    /// `HalRespondable`, `with_default_header`, which goes through `entry_or_insert`, and anything done to the
+    /// headers in `with_headers_with` are all checked too. Headers added by `with_dynamic_headers` are only added
+    /// when responding, so are not checked.
+    ///
+    /// # Errors
+    /// The errors from every header that couldn't be added, in the order they happened
+    pub fn build(self) -> Result<Self, Vec<HeaderError>> {
+        if self.headers.errors().is_empty() {
+            Ok(self)
+        } else {
+            Err(self.headers.1)
         }
     }
 
-    fn status_code(&self) -> StatusCode {
-        self.status_code
+    /// Copy the named headers from the request onto the response, e.g. to propagate correlation IDs.
+    ///
+    /// # Parameters
+    /// - `req` - The request being responded to
+    /// - `names` - The names of the headers to copy. Headers missing from the request are skipped
+    pub fn echo_headers(mut self, req: &HttpRequest, names: &[&str]) -> Self {
+        self.headers.echo_headers(req, names);
+
+        self
     }
 
-    fn headers(&self) -> HeaderMap {
-        self.headers.clone()
+    /// Copy the `Idempotency-Key` header from the request onto the response, if present.
+    ///
+    /// This only handles the headers of idempotent requests - storing the responses is left to the application.
+    /// Typically a middleware looks up the key before calling the handler. On a miss the handler runs and builds
+    /// its response with this, and the middleware stores it under the key. On a hit the middleware serves the
+    /// stored response instead, after marking it with `with_idempotent_replay`.
+    ///
+    /// # Parameters
+    /// - `req` - The request being responded to
+    pub fn with_idempotency_key(mut self, req: &HttpRequest) -> Self {
+        self.headers.with_idempotency_key(req);
+
+        self
     }
-}
 
-/// Trait that model resources can implement to convert it into a HAL response.
-///
-/// # Types
-/// - `T` - The HAL payload type.
-pub trait IntoHal<T>: Sized
-where
-    T: Serialize,
-{
-    /// Convert this object into a `HalResponse` object.
-    fn into_hal(self) -> HalResponse<T> {
-        let mut headers = Headers::default();
-        self.headers(&mut headers);
+    /// Mark the response as a replay of the response to an earlier request with the same `Idempotency-Key`, by
+    /// adding an `Idempotent-Replayed: true` header.
+    pub fn with_idempotent_replay(mut self) -> Self {
+        self.headers.with_idempotent_replay();
 
-        let status_code = self.status_code();
-        let links = self.links();
-        let payload = self.payload();
+        self
+    }
 
-        let mut respondable = HalRespondable::new(payload).with_status_code(status_code);
+    /// Add a `Warning` header to the response.
+    ///
+    /// # Parameters
+    /// - `code` - The warning code. Must be in the range 100-299
+    /// - `agent` - The agent adding the warning, or `-` if unknown
+    /// - `text` - The warning text
+    pub fn with_warning(mut self, code: u16, agent: &str, text: &str) -> Self {
+        self.headers.with_warning(code, agent, text);
 
-        for (name, value) in headers.iter() {
-            respondable = respondable.with_header_value(name, value);
-        }
+        self
+    }
 
-        for (name, link) in links {
-            respondable = respondable.with_link(name, link);
+    /// Add an `ETag` header to the response, replacing any existing one.
+    ///
+    /// # Parameters
+    /// - `etag` - The entity tag, e.g. `"abc"` or `W/"abc"`. It is quoted as a strong tag if not already quoted
+    pub fn with_etag(mut self, etag: &str) -> Self {
+        self.headers.with_etag(etag);
+
+        self
+    }
+
+    /// Add a weak `ETag` header to the response, derived from the payload with the provided fields excluded.
+    ///
+    /// This allows the entity tag to remain stable when only volatile fields, such as timestamps, change.
+    /// If the payload can't be serialized then no `ETag` is added.
+    ///
+    /// # Parameters
+    /// - `excluded` - JSON Pointers to the fields of the payload to ignore
+    pub fn with_etag_excluding(mut self, excluded: &[&str]) -> Self {
+        let mut value = match serde_json::to_value(&self.payload) {
+            Ok(value) => value,
+            Err(e) => {
+                tracing::error!(e = ?e, "Failed to serialize payload for ETag");
+                return self;
+            },
+        };
+
+        for pointer in excluded {
+            json::remove_pointer(&mut value, pointer);
         }
 
-        respondable.into()
+        let etag = format!("W/\"{}\"", json::fingerprint(&value));
+        self.headers.with_header_value(header::ETAG, etag);
+
+        self
     }
 
-    /// Determine the status code to use for the response.
-    fn status_code(&self) -> StatusCode {
-        StatusCode::OK
+    /// Add a `Sunset` header to the response, as defined by RFC 8594, indicating when this resource is expected
+    /// to become unavailable.
+    ///
+    /// # Parameters
+    /// - `date` - The time at which the resource will become unavailable
+    pub fn with_sunset(mut self, date: SystemTime) -> Self {
+        self.headers.with_sunset(date);
+
+        self
     }
 
-    /// Determine any headers to use for the response.
-    fn headers(&self, _headers: &mut Headers) {}
+    /// Add a `Deprecation` header to the response, indicating that this resource is deprecated.
+    ///
+    /// This can be paired with a `deprecation` link to documentation about the deprecation.
+    ///
+    /// # Parameters
+    /// - `when` - When the resource was or will be deprecated
+    pub fn with_deprecation_header(mut self, when: DeprecationWhen) -> Self {
+        self.headers.with_deprecation_header(when);
 
-    /// Generate the links to include in the response.
-    fn links(&self) -> Vec<(String, Link)> {
-        vec![]
+        self
     }
 
-    /// Generate the payload to respond with.
-    fn payload(self) -> T;
-}
+    /// Add a `Retry-After` header to the response, indicating how long the client should wait before trying again.
+    ///
+    /// # Parameters
+    /// - `retry_after` - When the client should try again
+    pub fn with_retry_after(mut self, retry_after: RetryAfter) -> Self {
+        self.headers.with_retry_after(retry_after);
 
-/// Wrapper around the headers to make it easier to work with.
-#[derive(Debug, Default)]
-pub struct Headers(HeaderMap);
+        self
+    }
 
-impl Headers {
-    /// Add a header to the response.
+    /// Add a `Clear-Site-Data` header to the response, instructing the browser to clear data for the site, e.g. on
+    /// logout.
     ///
     /// # Parameters
-    /// - `name` - The name of the header
-    /// - `value` - The value of the header
-    pub fn with_header_value<N, V>(&mut self, name: N, value: V) -> &mut Self
-    where
-        N: Into<HeaderName>,
-        V: IntoHeaderValue,
-    {
-        let value: HeaderValue = value.try_into_value().ok().unwrap();
+    /// - `directives` - The types of data to clear
+    pub fn with_clear_site_data(mut self, directives: &[ClearSiteData]) -> Self {
+        self.headers.with_clear_site_data(directives);
+
+        self
+    }
 
-        self.0.append(name.into(), value);
+    /// Add an `Age` header to the response, for a response served from a cache.
+    ///
+    /// # Parameters
+    /// - `age` - How long ago the response was generated
+    pub fn with_age(mut self, age: Duration) -> Self {
+        self.headers.with_age(age);
 
         self
     }
 
-    /// Add a header to the response
+    /// Add an `Age` header to the response, computed from when a cached response was generated.
     ///
     /// # Parameters
-    /// - `header` - The header to add
-    pub fn with_header<H>(&mut self, header: H) -> &mut Self
-    where
-        H: Header + IntoHeaderValue,
-    {
-        self.with_header_value(H::name(), header)
+    /// - `generated` - When the response was generated
+    pub fn with_age_since(mut self, generated: SystemTime) -> Self {
+        self.headers.with_age_since(generated);
+
+        self
     }
-}
 
-impl Deref for Headers {
-    type Target = HeaderMap;
+    /// Add a `Content-Language` header to the response. A tag that isn't a well-formed BCP 47 tag is left out and
+    /// reported by `build`.
+    ///
+    /// # Parameters
+    /// - `lang` - The BCP 47 language tag of the response, e.g. `en-GB`
+    pub fn with_content_language(mut self, lang: &str) -> Self {
+        self.headers.with_content_language(lang);
 
-    fn deref(&self) -> &Self::Target {
-        &self.0
+        self
     }
-}
 
-impl DerefMut for Headers {
-    fn deref_mut(&mut self) -> &mut Self::Target {
+    /// Add an `Accept-Patch` header to the response, advertising the media types accepted for `PATCH` requests.
+    ///
+    /// # Parameters
+    /// - `media_types` - The accepted media types, e.g. `application/merge-patch+json`
+    pub fn with_accept_patch(mut self, media_types: &[&str]) -> Self {
+        self.headers.with_accept_patch(media_types);
+
+        self
+    }
+
+    /// Wrap the serialized HAL document in an envelope, nesting it under the provided top-level key. By default
+    /// the document is not wrapped.
+    ///
+    /// # Parameters
+    /// - `key` - The key to nest the HAL document under, e.g. `result`
+    pub fn with_envelope<S>(mut self, key: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.envelope.get_or_insert_with(Envelope::default).key = key.into();
+
+        self
+    }
+
+    /// Add a top-level field alongside the HAL document in the envelope, e.g. a request ID. This has no effect
+    /// unless `with_envelope` is also called. If the value fails to serialize then it is logged and ignored.
+    ///
+    /// # Parameters
+    /// - `key` - The key of the field
+    /// - `value` - The value of the field
+    pub fn with_envelope_field<S, V>(mut self, key: S, value: V) -> Self
+    where
+        S: Into<String>,
+        V: Serialize,
+    {
+        let key = key.into();
+        match serde_json::to_value(value) {
+            Ok(value) => {
+                self.envelope.get_or_insert_with(Envelope::default).fields.insert(key, value);
+            },
+            Err(e) => {
+                tracing::error!(e = ?e, key = ?key, "Failed to serialize envelope field");
+            },
+        };
+
+        self
+    }
+
+    /// Specify the profile to add to the content type of the response, overriding any set with
+    /// `set_hal_profile`.
+    ///
+    /// # Parameters
+    /// - `profile` - The profile URI, e.g. `https://acme.com/profiles/v3`
+    pub fn with_profile<S>(mut self, profile: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.profile = Some(profile.into());
+
+        self
+    }
+
+    /// Add the profile of the response to its `Content-Type` header, if it has one and the header is still the
+    /// plain HAL media type.
+    ///
+    /// # Parameters
+    /// - `headers` - The headers to update
+    /// - `global` - The profile of every HAL response that doesn't specify its own, if any
+    fn apply_profile(&self, headers: &mut HeaderMap, global: Option<&String>) {
+        if headers.get(header::CONTENT_TYPE).is_none_or(|value| value != "application/hal+json") {
+            return;
+        }
+
+        let profile = match self.profile.as_ref().or(global) {
+            Some(profile) => profile,
+            None => return,
+        };
+
+        match HeaderValue::from_str(&format!("application/hal+json; profile=\"{}\"", profile)) {
+            Ok(value) => {
+                headers.insert(header::CONTENT_TYPE, value);
+            },
+            Err(_) => {
+                tracing::error!(profile = ?profile, "Failed to process HAL profile");
+            },
+        };
+    }
+
+    /// Split the response into its status code, headers and payload with the provided settings.
+    ///
+    /// # Parameters
+    /// - `settings` - The settings to build the response with
+    fn parts(mut self, settings: &Settings) -> (StatusCode, HeaderMap, HalPayload<T>) {
+        let mut headers = std::mem::take(&mut self.headers.0);
+        self.apply_profile(&mut headers, settings.profile.as_ref());
+        if self.link_header {
+            append_link_headers(&self.links, settings.trailing_slash, &mut headers);
+        }
+        (self.status_code, headers, self.payload(settings))
+    }
+
+    /// Build the payload of the response with the provided settings.
+    ///
+    /// # Parameters
+    /// - `settings` - The settings to build the payload with
+    fn payload(mut self, settings: &Settings) -> HalPayload<T> {
+        let trailing_slash = settings.trailing_slash;
+        if trailing_slash != TrailingSlash::Leave {
+            self = self.visit_links(|_, link| link.href = trailing_slash.normalize(&link.href));
+            for resources in self.embedded.values_mut() {
+                resources.visit_hrefs(&mut |href| *href = trailing_slash.normalize(href));
+            }
+        }
+
+        let cardinality = self.cardinality;
+        let sort_all = settings.sort_links;
+        let sorted_links = self.sorted_links;
+        let links = self
+            .links
+            .into_iter()
+            .map(|(name, mut links)| {
+                if sort_all || sorted_links.contains(&name) {
+                    links.sort();
+                }
+                match cardinality.get(&name) {
+                    Some(c) => (name, links.with_cardinality(*c)),
+                    None => (name, links),
+                }
+            })
+            .collect();
+        let links = compact_relations(links, &self.curies);
+
+        let mut embedded = self.embedded;
+        let mut embedded_meta = self.embedded_meta;
+        if self.embedded_failures == EmbeddedFailureMode::Skip {
+            embedded = embedded
+                .into_iter()
+                .filter_map(|(name, resources)| {
+                    let total = resources.len() as u64;
+                    let (resources, skipped) = resources.without_failures(&name);
+                    if skipped > 0 {
+                        let meta = embedded_meta.entry(name.clone()).or_insert(EmbeddedMeta {
+                            count:     total,
+                            truncated: false,
+                            skipped:   0,
+                        });
+                        meta.truncated = true;
+                        meta.skipped = skipped;
+                    }
+                    resources.map(|resources| (name, resources))
+                })
+                .collect();
+        }
+
+        let embedded_cardinality = self.embedded_cardinality;
+        let embedded = embedded
+            .into_iter()
+            .map(|(name, embedded)| match embedded_cardinality.get(&name) {
+                Some(c) => (name, embedded.with_cardinality(*c)),
+                None => (name, embedded),
+            })
+            .collect();
+        let embedded = compact_relations(embedded, &self.curies);
+
+        let embedded_meta = if embedded_meta.is_empty() {
+            BTreeMap::new()
+        } else if self.meta.contains_key(&self.embedded_meta_key) {
+            tracing::error!(
+                key = ?self.embedded_meta_key,
+                "Embedded metadata key is already used by collection metadata, leaving it out"
+            );
+            BTreeMap::new()
+        } else {
+            std::iter::once((self.embedded_meta_key, embedded_meta)).collect()
+        };
+
+        HalPayload {
+            payload: self.payload,
+            links,
+            embedded,
+            meta: self.meta,
+            embedded_meta,
+            fields: self.fields,
+            envelope: self.envelope,
+            canonical_order: settings.canonical_order,
+        }
+    }
+
+    /// Sort the links of a relation before serialization, so that they are serialized in a stable order however
+    /// they were assembled. Links are sorted by `href`, then by `name`, and then by their other fields.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the relation to sort
+    pub fn with_sorted_links<S>(mut self, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.sorted_links.insert(name.into());
+
+        self
+    }
+
+    /// Specify whether the links of the response are also sent as `Link` headers, as defined by RFC 8288.
+    /// Defaults to `false`.
+    ///
+    /// Every link in the body is sent as its own `Link` header, with the same target and relation, and with its
+    /// `name`, `type` and `hreflang` as parameters of the same names. The cardinality of a relation only affects
+    /// the body, so the headers are the same whether or not it is serialized as an array. Templated links are
+    /// included with the template as the target and a `templated="true"` parameter, exactly as they are marked in
+    /// the body, so clients that don't understand templates must ignore them in both places.
+    ///
+    /// # Parameters
+    /// - `link_header` - Whether to send `Link` headers
+    pub fn with_link_header(mut self, link_header: bool) -> Self {
+        self.link_header = link_header;
+
+        self
+    }
+
+    /// Restrict the response to the top-level fields listed in the `fields` query parameter of the request, e.g.
+    /// `?fields=id,name`. `_links` is always kept, and any unknown field names are ignored.
+    ///
+    /// # Parameters
+    /// - `req` - The request to read the query parameter from
+    pub fn with_sparse_fields(self, req: &HttpRequest) -> Self {
+        self.with_sparse_fields_param(req, "fields")
+    }
+
+    /// Restrict the response to the top-level fields listed in the named query parameter of the request.
+    /// `_links` is always kept, and any unknown field names are ignored. If the parameter is absent then the
+    /// whole payload is returned.
+    ///
+    /// # Parameters
+    /// - `req` - The request to read the query parameter from
+    /// - `param` - The name of the query parameter
+    pub fn with_sparse_fields_param(mut self, req: &HttpRequest, param: &str) -> Self {
+        let query = match web::Query::<HashMap<String, String>>::from_query(req.query_string()) {
+            Ok(query) => query.into_inner(),
+            Err(e) => {
+                tracing::error!(e = ?e, "Failed to parse query string for sparse fields");
+                return self;
+            },
+        };
+
+        if let Some(fields) = query.get(param) {
+            self.fields = Some(
+                fields
+                    .split(',')
+                    .map(str::trim)
+                    .filter(|field| !field.is_empty())
+                    .map(ToOwned::to_owned)
+                    .collect(),
+            );
+        }
+
+        self
+    }
+
+    /// Turn this response into a `304 Not Modified` if the validators on the request match it.
+    ///
+    /// This compares the `If-None-Match` and `If-Modified-Since` headers of `GET` and `HEAD` requests against
+    /// the `ETag` and `Last-Modified` headers of this response, so must be called after those are set. The
+    /// `304` response has no body and keeps only the `Cache-Control`, `Content-Location`, `Date`, `ETag`,
+    /// `Expires` and `Vary` headers, so any of those must also be set before calling this.
+    ///
+    /// A request that forces revalidation with `Cache-Control: no-cache` or `max-age=0`, such as a forced refresh
+    /// in a browser, always gets the full response.
+    ///
+    /// # Parameters
+    /// - `req` - The request being responded to
+    pub fn conditional(mut self, req: &HttpRequest) -> Self {
+        if conditional::is_not_modified(req, &self.headers) {
+            let mut headers = Headers::default();
+            for name in conditional::NOT_MODIFIED_HEADERS {
+                for value in self.headers.get_all(name) {
+                    headers.append(name.clone(), value.clone());
+                }
+            }
+
+            self.status_code = StatusCode::NOT_MODIFIED;
+            self.headers = headers;
+            self = self.without_body();
+        }
+
+        self
+    }
+
+    /// Add a link to the response.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the link
+    /// - `link` - The actual link
+    pub fn with_link<S, L>(mut self, name: S, link: L) -> Self
+    where
+        S: Into<String>,
+        L: Into<Link>,
+    {
+        let name = name.into();
+        let links = match self.links.remove(&name) {
+            None => Links::Single(link.into()),
+            Some(links) => links.push(link.into()),
+        };
+        self.links.insert(name, links);
+
+        self
+    }
+
+    /// Add a single link to the response under several relations.
+    ///
+    /// In the body the link appears under each relation, and if `Link` headers are enabled it is sent as a single
+    /// header with all of the relations, e.g. `</users/1>; rel="self canonical"`.
+    ///
+    /// # Parameters
+    /// - `names` - The names of the relations
+    /// - `link` - The actual link
+    pub fn with_link_relations<L>(self, names: &[&str], link: L) -> Self
+    where
+        L: Into<Link>,
+    {
+        let link = link.into();
+        names.iter().fold(self, |respondable, name| respondable.with_link(*name, link.clone()))
+    }
+
+    /// Add a `describedby` link to the response, pointing to the documentation or JSON Schema that describes it.
+    ///
+    /// # Parameters
+    /// - `href` - The URI of the description
+    /// - `schema` - Whether the description is a JSON Schema, so the link has a type of `application/schema+json`
+    pub fn with_described_by<S>(self, href: S, schema: bool) -> Self
+    where
+        S: Into<String>,
+    {
+        let link = Link {
+            r#type: if schema { Some("application/schema+json".to_owned()) } else { None },
+            ..Link::from(href.into())
+        };
+        self.with_link("describedby", link)
+    }
+
+    /// Add a link to the response, computed from the payload.
+    ///
+    /// The closure is called exactly once, immediately, with a reference to the payload.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the link
+    /// - `link` - Closure to compute the link from the payload
+    pub fn with_link_fn<S, L, F>(self, name: S, link: F) -> Self
+    where
+        S: Into<String>,
+        L: Into<Link>,
+        F: FnOnce(&T) -> L,
+    {
+        let link = link(&self.payload);
+        self.with_link(name, link)
+    }
+
+    /// Add a link to the response only if the provided condition holds.
+    ///
+    /// # Parameters
+    /// - `cond` - Whether to add the link
+    /// - `name` - The name of the link
+    /// - `link` - The actual link
+    pub fn with_link_if<S, L>(self, cond: bool, name: S, link: L) -> Self
+    where
+        S: Into<String>,
+        L: Into<Link>,
+    {
+        if cond {
+            self.with_link(name, link)
+        } else {
+            self
+        }
+    }
+
+    /// Make a link relation always present in the response, even when it has no links, e.g. to match a schema
+    /// that requires it. Without this a relation with no links is left out entirely.
+    ///
+    /// Any links added to the relation are serialized as normal, so this only takes effect if none are.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the link relation
+    /// - `form` - How to serialize the relation when it has no links
+    pub fn with_empty_relation<S>(mut self, name: S, form: EmptyForm) -> Self
+    where
+        S: Into<String>,
+    {
+        self.links.entry(name.into()).or_insert(match form {
+            EmptyForm::Null => Links::Null,
+            EmptyForm::Array => Links::Multiple(vec![]),
+        });
+
+        self
+    }
+
+    /// Add a link to the response only if one is provided, e.g. for a `next` page that may not exist.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the link
+    /// - `link` - The actual link, or `None` to add nothing
+    pub fn with_optional_link<S, L>(self, name: S, link: Option<L>) -> Self
+    where
+        S: Into<String>,
+        L: Into<Link>,
+    {
+        match link {
+            Some(link) => self.with_link(name, link),
+            None => self,
+        }
+    }
+
+    /// Embed a resource in the response.
+    ///
+    /// Embedding a `HalRespondable` keeps its own `_links`, `_embedded` and collection metadata. Paginated
+    /// sub-collections can be embedded more simply with `with_embedded_collection`.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the relation to embed the resource under
+    /// - `resource` - The resource to embed. This can be any `Respondable`, including another `HalRespondable`
+    pub fn with_embedded<S, R>(mut self, name: S, resource: R) -> Self
+    where
+        S: Into<String>,
+        R: Respondable,
+    {
+        let name = name.into();
+        let resource = EmbeddedResource::new(resource.body());
+        let embedded = match self.embedded.remove(&name) {
+            None => Embedded::Single(resource),
+            Some(embedded) => embedded.push(resource),
+        };
+        self.embedded.insert(name, embedded);
+
+        self
+    }
+
+    /// Embed a page of a sub-collection in the response, with its items, its own pagination links relative to the
+    /// endpoint of the sub-collection, and its metadata.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the relation to embed the collection under
+    /// - `collection` - The page of the collection to embed
+    pub fn with_embedded_collection<S, U>(self, name: S, collection: CollectionResponse<U>) -> Self
+    where
+        S: Into<String>,
+        U: Serialize,
+    {
+        self.with_embedded(name, collection.into_respondable())
+    }
+
+    /// Embed a resource in the response only if the provided condition holds.
+    ///
+    /// # Parameters
+    /// - `cond` - Whether to embed the resource
+    /// - `name` - The name of the relation to embed the resource under
+    /// - `resource` - The resource to embed
+    pub fn with_embedded_if<S, R>(self, cond: bool, name: S, resource: R) -> Self
+    where
+        S: Into<String>,
+        R: Respondable,
+    {
+        if cond {
+            self.with_embedded(name, resource)
+        } else {
+            self
+        }
+    }
+
+    /// Add a relation that is linked by default, but embedded instead if the request asked for it to be expanded.
+    ///
+    /// The provider is only called, and awaited, when the relation is expanded, so the embedded resource is only
+    /// fetched when it is actually needed.
+    ///
+    /// # Parameters
+    /// - `expansion` - The relations that the request asked to expand
+    /// - `name` - The name of the relation
+    /// - `link` - The link to add if the relation isn't expanded
+    /// - `provider` - Function to fetch the resource to embed if the relation is expanded
+    pub async fn with_expandable<S, L, F, Fut, R>(
+        self,
+        expansion: &Expansion,
+        name: S,
+        link: L,
+        provider: F,
+    ) -> Self
+    where
+        S: Into<String>,
+        L: Into<Link>,
+        F: FnOnce() -> Fut,
+        Fut: std::future::Future<Output = R>,
+        R: Respondable,
+    {
+        let name = name.into();
+        if expansion.is_requested(&name) {
+            self.with_embedded(name, provider().await)
+        } else {
+            self.with_link(name, link)
+        }
+    }
+
+    /// Add a `self` link to the response pointing to the URL of the provided request.
+    ///
+    /// # Parameters
+    /// - `req` - The request being responded to
+    /// - `include_query` - Whether to include the query string of the request in the link
+    pub fn with_self_link_from_request(self, req: &HttpRequest, include_query: bool) -> Self {
+        self.with_link("self", Link::for_request(req, include_query))
+    }
+
+    /// Add a `self` link to the response pointing to a single resource within a base URL, as built by
+    /// `Link::to_resource`.
+    ///
+    /// # Parameters
+    /// - `base` - The base URL of the resources
+    /// - `id` - The ID of this resource
+    pub fn with_self_link_to_resource<I>(self, base: &str, id: I) -> Self
+    where
+        I: Display,
+    {
+        self.with_link("self", Link::to_resource(base, id))
+    }
+
+    /// Add a `self` link to the response pointing to a named route of the application, keeping the link in sync
+    /// with the routing table.
+    ///
+    /// If the route can't be resolved, e.g. because it isn't registered with the application, then the error is
+    /// logged and the fallback link is used instead.
+    ///
+    /// # Parameters
+    /// - `req` - The request being responded to
+    /// - `name` - The name of the route, as given to `Resource::name`
+    /// - `elements` - The values of the dynamic segments of the route, in order
+    /// - `fallback` - The link to use if the route can't be resolved
+    pub fn with_self_link_for_route<U, I, L>(self, req: &HttpRequest, name: &str, elements: U, fallback: L) -> Self
+    where
+        U: IntoIterator<Item = I>,
+        I: AsRef<str>,
+        L: Into<Link>,
+    {
+        let link = match Link::for_route(req, name, elements) {
+            Ok(link) => link,
+            Err(e) => {
+                tracing::error!(e = ?e, name = name, "Failed to build link for route");
+                fallback.into()
+            },
+        };
+
+        self.with_link("self", link)
+    }
+
+    /// Mark this response as a collection of the provided items.
+    ///
+    /// Every item is added as a link under the given relation, which is always serialized as an array.
+    ///
+    /// # Parameters
+    /// - `item_rel` - The name of the link relation for the items, typically `item`
+    /// - `item_hrefs` - The links to the items in the collection
+    pub fn as_collection_of<S, I, L>(mut self, item_rel: S, item_hrefs: I) -> Self
+    where
+        S: Into<String>,
+        I: IntoIterator<Item = L>,
+        L: Into<Link>,
+    {
+        let item_rel = item_rel.into();
+        for item in item_hrefs {
+            self = self.with_link(item_rel.clone(), item);
+        }
+
+        self.array_relation(item_rel)
+    }
+
+    /// Mark this response as being a member of the provided collection.
+    ///
+    /// # Parameters
+    /// - `collection_href` - The link to the collection
+    pub fn member_of<L>(self, collection_href: L) -> Self
+    where
+        L: Into<Link>,
+    {
+        self.with_link("collection", collection_href)
+    }
+
+    /// Tidy up every link relation on the response, as done by `Links::normalize`. Relations that have a
+    /// cardinality set still serialize with that cardinality.
+    ///
+    /// # Parameters
+    /// - `collapse` - Whether to collapse relations with only one link into a single object
+    /// - `dedup` - Whether to remove duplicate links within each relation
+    pub fn normalize_links(mut self, collapse: bool, dedup: bool) -> Self {
+        self.links = self
+            .links
+            .into_iter()
+            .map(|(rel, links)| (rel, links.normalize(collapse, dedup)))
+            .collect();
+
+        self
+    }
+
+    /// Apply a closure to every link on the response, allowing them to be inspected or rewritten in place, e.g. to
+    /// rewrite the host of upstream links or to sign them.
+    ///
+    /// This visits only the links that have already been added, so should be called once the response is
+    /// otherwise complete. Links of embedded resources are not visited.
+    ///
+    /// # Parameters
+    /// - `visitor` - The closure to call with the relation and link for every link
+    pub fn visit_links<F>(mut self, mut visitor: F) -> Self
+    where
+        F: FnMut(&str, &mut Link),
+    {
+        for (rel, links) in &mut self.links {
+            for link in links.iter_mut() {
+                visitor(rel, link);
+            }
+        }
+
+        self
+    }
+
+    /// Add metadata describing the page of the collection that this response represents.
+    ///
+    /// The key must not be the one that the metadata of the embedded relations is serialized under, `_meta` by
+    /// default. If it is, and there is also embedded metadata, then the embedded metadata is logged and left out.
+    ///
+    /// # Parameters
+    /// - `key` - The key to serialize the metadata under, e.g. `page`
+    /// - `meta` - The collection metadata
+    pub fn with_collection_meta<S>(mut self, key: S, meta: CollectionMeta) -> Self
+    where
+        S: Into<String>,
+    {
+        self.meta.insert(key.into(), meta);
+
+        self
+    }
+
+    /// Add metadata describing the resources embedded under a relation, e.g. so that clients know whether they
+    /// need to follow a link to get the full list.
+    ///
+    /// By convention the metadata of every relation is serialized in a top-level `_meta` object, keyed by the
+    /// name of the relation, as `"_meta": {"items": {"count": 25, "truncated": true}}`. The key of the object
+    /// can be changed with `with_embedded_meta_key`.
+    ///
+    /// # Parameters
+    /// - `rel` - The name of the embedded relation
+    /// - `count` - The number of resources in the full list
+    /// - `truncated` - Whether only some of the resources in the list were embedded
+    pub fn with_embedded_meta<S>(mut self, rel: S, count: u64, truncated: bool) -> Self
+    where
+        S: Into<String>,
+    {
+        self.embedded_meta.insert(rel.into(), EmbeddedMeta {
+            count,
+            truncated,
+            skipped: 0,
+        });
+
+        self
+    }
+
+    /// Specify how to handle embedded resources that failed to serialize. Defaults to failing the entire response.
+    ///
+    /// # Parameters
+    /// - `mode` - How to handle embedded resources that failed to serialize
+    pub fn with_embedded_failure_mode(mut self, mode: EmbeddedFailureMode) -> Self {
+        self.embedded_failures = mode;
+
+        self
+    }
+
+    /// Specify the top-level key that the metadata of the embedded relations is serialized under, to match the
+    /// profile that clients expect. Defaults to `_meta`.
+    ///
+    /// # Parameters
+    /// - `key` - The key to serialize the metadata under
+    pub fn with_embedded_meta_key<S>(mut self, key: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.embedded_meta_key = key.into();
+
+        self
+    }
+
+    /// Register a CURIE, adding it to the `curies` links and compacting every link and embedded relation that it
+    /// matches when serializing the response.
+    ///
+    /// The template must contain `{rel}`, e.g. `https://acme.com/rels/{rel}` for the name `acme`, which compacts
+    /// the relation `https://acme.com/rels/widget` into `acme:widget`. Relations that no CURIE matches are left
+    /// unchanged. If several CURIEs match a relation then the one with the longest template wins, with ties going
+    /// to the one registered first. A relation is also left unchanged if its compact form is already in use.
+    /// `Link` headers always use the full relation, since CURIEs are only meaningful within HAL documents.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the CURIE, e.g. `acme`
+    /// - `template` - The URI Template of the relations documented by the CURIE
+    pub fn with_curie<N, S>(mut self, name: N, template: S) -> Self
+    where
+        N: Into<String>,
+        S: Into<String>,
+    {
+        let name = name.into();
+        let template = template.into();
+        if !template.contains("{rel}") {
+            tracing::error!(name = ?name, template = ?template, "CURIE template is missing {{rel}}");
+            return self;
+        }
+
+        self.curies.push((name.clone(), template.clone()));
+        self.with_link("curies", Link {
+            name: Some(name),
+            ..Link::templated(template)
+        })
+        .array_relation("curies")
+    }
+
+    /// Indicate that a link relation should always be serialized as an array, even with only one link.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the link relation
+    pub fn array_relation<S>(mut self, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.cardinality.insert(name.into(), Cardinality::Array);
+
+        self
+    }
+
+    /// Indicate that a link relation should be serialized as an object whenever it has only one link.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the link relation
+    pub fn object_relation<S>(mut self, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.cardinality.insert(name.into(), Cardinality::Object);
+
+        self
+    }
+
+    /// Indicate that an embedded relation should always be serialized as an array, even with only one resource.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the embedded relation
+    pub fn array_embedded<S>(mut self, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.embedded_cardinality.insert(name.into(), Cardinality::Array);
+
+        self
+    }
+
+    /// Indicate that an embedded relation should be serialized as an object whenever it has only one resource.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the embedded relation
+    pub fn object_embedded<S>(mut self, name: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.embedded_cardinality.insert(name.into(), Cardinality::Object);
+
+        self
+    }
+}
+
+impl HalRespondable<()> {
+    /// Create a new HAL Respondable with no payload, for resources that consist only of links.
+    ///
+    /// This serializes as an object containing only the `_links` of the resource.
+    pub fn empty() -> Self {
+        Self::new(())
+    }
+
+    /// Create a new `300 Multiple Choices` HAL Respondable, for the client to pick one of several variants of a
+    /// resource. Each variant is an `alternate` link with its media type and language, and the relation is
+    /// always serialized as an array.
+    ///
+    /// # Parameters
+    /// - `variants` - The variants of the resource
+    pub fn multiple_choices(variants: &[Variant]) -> Self {
+        variants
+            .iter()
+            .fold(Self::empty(), |respondable, variant| {
+                respondable.with_link("alternate", Link {
+                    r#type: Some(variant.media_type.clone()),
+                    hreflang: variant.language.clone(),
+                    ..Link::from(variant.href.as_str())
+                })
+            })
+            .with_status_code(StatusCode::MULTIPLE_CHOICES)
+            .array_relation("alternate")
+    }
+}
+
+/// A single variant of a resource, for a `300 Multiple Choices` response.
+#[derive(Debug, Clone)]
+pub struct Variant {
+    /// The link to the variant
+    pub href:       String,
+    /// The media type of the variant
+    pub media_type: String,
+    /// The language of the variant, if it has one
+    pub language:   Option<String>,
+}
+
+impl<T> From<HalRespondable<T>> for HttpResponse
+where
+    T: Serialize,
+{
+    /// Serialize the HAL resource straight into an HTTP response, without needing the request.
+    ///
+    /// Anything that depends on the request is skipped - the response is always `application/hal+json`, and no
+    /// content negotiation is performed. Conditional requests still work if `conditional` was called first.
+    fn from(respondable: HalRespondable<T>) -> Self {
+        Response::new(respondable).into_http_response()
+    }
+}
+
+impl<T> Default for HalRespondable<T>
+where
+    T: Serialize + Default,
+{
+    fn default() -> Self {
+        Self::new(T::default())
+    }
+}
+
+impl<T> Respondable for HalRespondable<T>
+where
+    T: Serialize,
+{
+    type Body = HalPayload<T>;
+
+    fn body(self) -> Self::Body {
+        self.payload(&Settings::global())
+    }
+
+    fn status_code(&self) -> StatusCode {
+        self.status_code
+    }
+
+    fn headers(&self) -> HeaderMap {
+        let settings = Settings::global();
+        let mut headers = self.headers.clone();
+        self.apply_profile(&mut headers, settings.profile.as_ref());
+        if self.link_header {
+            append_link_headers(&self.links, settings.trailing_slash, &mut headers);
+        }
+        headers
+    }
+
+    fn into_parts(self) -> (StatusCode, HeaderMap, Self::Body) {
+        self.parts(&Settings::global())
+    }
+
+    fn media_types(&self) -> Vec<&'static str> {
+        vec!["application/hal+json", "application/json"]
+    }
+
+    fn negotiation_mode(&self) -> NegotiationMode {
+        self.negotiation
+    }
+
+    fn has_body(&self) -> bool {
+        self.has_body
+    }
+
+    fn omits_nulls(&self) -> bool {
+        self.omit_nulls
+    }
+
+    fn prepare(&mut self, req: &HttpRequest) {
+        for f in std::mem::take(&mut self.dynamic_headers.0) {
+            f(req, &mut self.headers);
+        }
+    }
+
+    fn serialize_body(mut body: Self::Body, omit_nulls: bool) -> serde_json::Result<Vec<u8>> {
+        let envelope = body.envelope.take().filter(|envelope| !envelope.key.is_empty());
+        if body.fields.is_none() && envelope.is_none() && !omit_nulls && !body.canonical_order {
+            return json::to_vec(&body);
+        }
+
+        let mut value = json::to_value(&body)?;
+        if let (Some(fields), serde_json::Value::Object(map)) = (&body.fields, &mut value) {
+            map.retain(|key, _| key == "_links" || fields.contains(key));
+        }
+        if omit_nulls {
+            json::strip_nulls(&mut value);
+        }
+        let key = envelope.as_ref().map(|envelope| envelope.key.clone());
+        if let Some(envelope) = envelope {
+            let mut wrapped: serde_json::Map<_, _> = envelope.fields.into_iter().collect();
+            wrapped.insert(envelope.key, value);
+            value = serde_json::Value::Object(wrapped);
+        }
+        match (body.canonical_order, &key) {
+            (false, _) => json::to_vec(&value),
+            (true, Some(key)) => json::to_vec(&Canonical::Envelope(&value, key)),
+            (true, None) => json::to_vec(&Canonical::Document(&value)),
+        }
+    }
+}
+
+/// Trait that model resources can implement to convert it into a HAL response.
+///
+/// # Types
+/// - `T` - The HAL payload type.
+pub trait IntoHal<T>: Sized
+where
+    T: Serialize,
+{
+    /// Convert this object into a `HalResponse` object.
+    fn into_hal(self) -> HalResponse<T> {
+        let mut headers = Headers::default();
+        self.headers(&mut headers);
+
+        let status_code = self.status_code();
+        let links = self.links();
+        let payload = self.payload();
+
+        let mut respondable = HalRespondable::new(payload).with_status_code(status_code);
+
+        // Move the headers across wholesale, so that repeated headers keep their order and multiplicity.
+        if !headers.contains_key(header::CONTENT_TYPE) {
+            if let Some(content_type) = respondable.headers.get(header::CONTENT_TYPE) {
+                headers.insert(header::CONTENT_TYPE, content_type.clone());
+            }
+        }
+        respondable.headers = headers;
+
+        for (name, link) in links {
+            respondable = respondable.with_link(name, link);
+        }
+
+        respondable.into()
+    }
+
+    /// Determine the status code to use for the response.
+    fn status_code(&self) -> StatusCode {
+        StatusCode::OK
+    }
+
+    /// Determine any headers to use for the response.
+    fn headers(&self, _headers: &mut Headers) {}
+
+    /// Generate the links to include in the response.
+    fn links(&self) -> Vec<(String, Link)> {
+        vec![]
+    }
+
+    /// Generate the payload to respond with.
+    fn payload(self) -> T;
+}
+
+/// When a resource was or will be deprecated, for the `Deprecation` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeprecationWhen {
+    /// The resource is already deprecated.
+    Now,
+    /// The resource was or will be deprecated at the provided time.
+    At(SystemTime),
+}
+
+/// The types of data that the `Clear-Site-Data` header can instruct the browser to clear.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClearSiteData {
+    /// Clear any locally cached data.
+    Cache,
+    /// Clear all cookies.
+    Cookies,
+    /// Clear all DOM storage, e.g. `localStorage` and `IndexedDB`.
+    Storage,
+    /// Reload all browsing contexts for the site.
+    ExecutionContexts,
+    /// Clear all types of data.
+    All,
+}
+
+impl ClearSiteData {
+    /// The quoted form of this directive, as used in the header.
+    fn as_header_value(self) -> &'static str {
+        match self {
+            ClearSiteData::Cache => "\"cache\"",
+            ClearSiteData::Cookies => "\"cookies\"",
+            ClearSiteData::Storage => "\"storage\"",
+            ClearSiteData::ExecutionContexts => "\"executionContexts\"",
+            ClearSiteData::All => "\"*\"",
+        }
+    }
+}
+
+/// The largest value that can be sent in an `Age` header, as given by RFC 9111.
+const MAX_AGE: u64 = 2_147_483_648;
+
+/// When the client should try a request again, for the `Retry-After` header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RetryAfter {
+    /// The client should wait for the provided duration, which is sent rounded down to whole seconds.
+    Delay(Duration),
+    /// The client should wait until the provided time.
+    At(SystemTime),
+}
+
+/// Error from a header that couldn't be added to a response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HeaderError {
+    /// The name of the header, as it was provided, since it may not be a valid header name
+    pub name:    String,
+    /// Why the header couldn't be added
+    pub message: String,
+}
+
+impl Display for HeaderError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Failed to process header {}: {}", self.name, self.message)
+    }
+}
+
+impl std::error::Error for HeaderError {}
+
+/// Wrapper around the headers to make it easier to work with.
+///
+/// Headers that can't be added, e.g. because their value isn't a valid header value, are logged and left out, and
+/// the error is also recorded so that every failure can be checked at once with `HalRespondable::build`. Errors
+/// are recorded by:
+/// - `with_header_value` and `with_header`
+/// - `entry_or_insert`
+/// - `echo_headers` and `with_idempotency_key`, for header names that aren't valid
+/// - `with_warning`, for codes outside of the range 100-299
+/// - `with_sunset`, `with_deprecation_header`, `with_etag`, `with_retry_after`, `with_clear_site_data`,
+///   `with_age` and `with_age_since`
+/// - `with_content_language`, for tags that aren't well-formed BCP 47 tags
+/// - `with_accept_patch`, for media types that aren't valid
+#[derive(Debug, Default)]
+pub struct Headers(HeaderMap, Vec<HeaderError>);
+
+impl Headers {
+    /// Add a header to the response.
+    ///
+    /// If the value isn't a valid header value then the header is left out, and the error is recorded.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the header
+    /// - `value` - The value of the header
+    pub fn with_header_value<N, V>(&mut self, name: N, value: V) -> &mut Self
+    where
+        N: Into<HeaderName>,
+        V: IntoHeaderValue,
+    {
+        let name = name.into();
+        match value.try_into_value() {
+            Ok(value) => {
+                self.0.append(name, value);
+            },
+            Err(e) => self.record_error(name, e.into().to_string()),
+        };
+
+        self
+    }
+
+    /// The errors from every header that couldn't be added, in the order they happened.
+    pub fn errors(&self) -> &[HeaderError] {
+        &self.1
+    }
+
+    /// Log and record an error from a header that couldn't be added.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the header
+    /// - `message` - Why the header couldn't be added
+    fn record_error<N, S>(&mut self, name: N, message: S)
+    where
+        N: AsRef<str>,
+        S: Into<String>,
+    {
+        let name = name.as_ref().to_owned();
+        let message = message.into();
+        tracing::error!(name = ?name, message = ?message, "Failed to process header");
+        self.1.push(HeaderError { name, message });
+    }
+
+    /// Add a header to the response only if it doesn't already have a header with that name, e.g. to layer a
+    /// default `Cache-Control` under whatever the handler set explicitly.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the header
+    /// - `default` - The value of the header to add if it is absent
+    ///
+    /// # Returns
+    /// True if the header was absent, and so the default value was added
+    pub fn entry_or_insert<N, V>(&mut self, name: N, default: V) -> bool
+    where
+        N: Into<HeaderName>,
+        V: IntoHeaderValue,
+    {
+        let name = name.into();
+        if self.0.contains_key(&name) {
+            return false;
+        }
+
+        match default.try_into_value() {
+            Ok(value) => {
+                self.0.insert(name, value);
+                true
+            },
+            Err(e) => {
+                self.record_error(name, e.into().to_string());
+                false
+            },
+        }
+    }
+
+    /// Add a header to the response
+    ///
+    /// # Parameters
+    /// - `header` - The header to add
+    pub fn with_header<H>(&mut self, header: H) -> &mut Self
+    where
+        H: Header + IntoHeaderValue,
+    {
+        self.with_header_value(H::name(), header)
+    }
+
+    /// Copy the named headers from the request onto the response, e.g. to propagate correlation IDs.
+    ///
+    /// Every value of each header is copied. Headers missing from the request are skipped.
+    ///
+    /// # Parameters
+    /// - `req` - The request being responded to
+    /// - `names` - The names of the headers to copy
+    pub fn echo_headers(&mut self, req: &HttpRequest, names: &[&str]) -> &mut Self {
+        for name in names {
+            match HeaderName::from_bytes(name.as_bytes()) {
+                Ok(name) => {
+                    for value in req.headers().get_all(&name) {
+                        self.0.append(name.clone(), value.clone());
+                    }
+                },
+                Err(_) => self.record_error(name, "Invalid header name"),
+            };
+        }
+
+        self
+    }
+
+    /// Add a `Warning` header to the response.
+    ///
+    /// Multiple warnings are emitted as repeated header lines. Warnings with a code outside of the
+    /// range 100-299 are left out, and the error is recorded.
+    ///
+    /// # Parameters
+    /// - `code` - The warning code
+    /// - `agent` - The agent adding the warning, or `-` if unknown
+    /// - `text` - The warning text
+    pub fn with_warning(&mut self, code: u16, agent: &str, text: &str) -> &mut Self {
+        if !(100..=299).contains(&code) {
+            self.record_error(header::WARNING, format!("Invalid warning code {}", code));
+            return self;
+        }
+
+        let text = text.replace('\\', "\\\\").replace('"', "\\\"");
+        self.with_header_value(header::WARNING, format!("{} {} \"{}\"", code, agent, text))
+    }
+
+    /// Add a `Sunset` header to the response, as defined by RFC 8594, indicating when this resource is expected
+    /// to become unavailable.
+    ///
+    /// # Parameters
+    /// - `date` - The time at which the resource will become unavailable
+    pub fn with_sunset(&mut self, date: SystemTime) -> &mut Self {
+        self.with_header_value(HeaderName::from_static("sunset"), HttpDate::from(date))
+    }
+
+    /// Add a `Deprecation` header to the response, indicating that this resource is deprecated.
+    ///
+    /// This is `true` for a resource that is already deprecated, or otherwise the HTTP-date at which it was or
+    /// will be deprecated.
+    ///
+    /// # Parameters
+    /// - `when` - When the resource was or will be deprecated
+    pub fn with_deprecation_header(&mut self, when: DeprecationWhen) -> &mut Self {
+        let name = HeaderName::from_static("deprecation");
+        match when {
+            DeprecationWhen::Now => self.with_header_value(name, "true"),
+            DeprecationWhen::At(date) => self.with_header_value(name, HttpDate::from(date)),
+        }
+    }
+
+    /// Add an `ETag` header to the response, replacing any existing one.
+    ///
+    /// # Parameters
+    /// - `etag` - The entity tag, e.g. `"abc"` or `W/"abc"`. It is quoted as a strong tag if not already quoted
+    pub fn with_etag(&mut self, etag: &str) -> &mut Self {
+        self.0.remove(header::ETAG);
+        if etag.starts_with('"') || etag.starts_with("W/\"") {
+            self.with_header_value(header::ETAG, etag)
+        } else {
+            self.with_header_value(header::ETAG, format!("\"{}\"", etag))
+        }
+    }
+
+    /// Add a `Retry-After` header to the response, replacing any existing one.
+    ///
+    /// This is the number of seconds to wait for a delay, or otherwise the HTTP-date to wait until.
+    ///
+    /// # Parameters
+    /// - `retry_after` - When the client should try again
+    pub fn with_retry_after(&mut self, retry_after: RetryAfter) -> &mut Self {
+        self.0.remove(header::RETRY_AFTER);
+        match retry_after {
+            RetryAfter::Delay(delay) => self.with_header_value(header::RETRY_AFTER, delay.as_secs()),
+            RetryAfter::At(date) => self.with_header_value(header::RETRY_AFTER, HttpDate::from(date)),
+        }
+    }
+
+    /// Add a `Clear-Site-Data` header to the response.
+    ///
+    /// Each directive is only included once, and if there are no directives then no header is added.
+    ///
+    /// # Parameters
+    /// - `directives` - The types of data to clear
+    pub fn with_clear_site_data(&mut self, directives: &[ClearSiteData]) -> &mut Self {
+        let mut values: Vec<&str> = vec![];
+        for directive in directives {
+            let value = directive.as_header_value();
+            if !values.contains(&value) {
+                values.push(value);
+            }
+        }
+
+        if values.is_empty() {
+            return self;
+        }
+
+        self.with_header_value(HeaderName::from_static("clear-site-data"), values.join(", "))
+    }
+
+    /// Add an `Age` header to the response, replacing any existing one.
+    ///
+    /// The age is sent in whole seconds, rounded down, and is capped at 2147483648 seconds as required by RFC 9111.
+    ///
+    /// # Parameters
+    /// - `age` - How long ago the response was generated
+    pub fn with_age(&mut self, age: Duration) -> &mut Self {
+        self.0.remove(header::AGE);
+        self.with_header_value(header::AGE, age.as_secs().min(MAX_AGE))
+    }
+
+    /// Add an `Age` header to the response, computed from when a cached response was generated, replacing any
+    /// existing one. A generation time in the future, e.g. due to clock skew, gives an age of `0`.
+    ///
+    /// # Parameters
+    /// - `generated` - When the response was generated
+    pub fn with_age_since(&mut self, generated: SystemTime) -> &mut Self {
+        // Ages are relative to the wall clock that the generation time came from.
+        let age = SystemTime::now().duration_since(generated).unwrap_or_default();
+        self.with_age(age)
+    }
+
+    /// Add a `Content-Language` header to the response.
+    ///
+    /// Language tags that aren't well-formed BCP 47 tags are rejected, so the header is left out and the error is
+    /// recorded.
+    ///
+    /// # Parameters
+    /// - `lang` - The BCP 47 language tag of the response, e.g. `en-GB`
+    pub fn with_content_language(&mut self, lang: &str) -> &mut Self {
+        if !is_language_tag(lang) {
+            self.record_error(header::CONTENT_LANGUAGE, format!("Invalid language tag {:?}", lang));
+            return self;
+        }
+
+        self.with_header_value(header::CONTENT_LANGUAGE, lang.to_owned())
+    }
+
+    /// Add an `Accept-Patch` header to the response, advertising the media types accepted for `PATCH` requests.
+    ///
+    /// Duplicate media types are only included once. Invalid media types are left out, and the error is recorded.
+    ///
+    /// # Parameters
+    /// - `media_types` - The accepted media types, e.g. `application/merge-patch+json`
+    pub fn with_accept_patch(&mut self, media_types: &[&str]) -> &mut Self {
+        let mut accepted: Vec<&str> = vec![];
+        for media_type in media_types.iter().map(|media_type| media_type.trim()) {
+            if !is_media_type(media_type) {
+                let name = HeaderName::from_static("accept-patch");
+                self.record_error(name, format!("Invalid media type {:?}", media_type));
+            } else if !accepted.iter().any(|m| m.eq_ignore_ascii_case(media_type)) {
+                accepted.push(media_type);
+            }
+        }
+
+        if accepted.is_empty() {
+            return self;
+        }
+
+        self.with_header_value(HeaderName::from_static("accept-patch"), accepted.join(", "))
+    }
+
+    /// Copy the `Idempotency-Key` header from the request onto the response, if present.
+    ///
+    /// # Parameters
+    /// - `req` - The request being responded to
+    pub fn with_idempotency_key(&mut self, req: &HttpRequest) -> &mut Self {
+        self.echo_headers(req, &["idempotency-key"])
+    }
+
+    /// Add an `Idempotent-Replayed: true` header to the response, marking it as a replay of the response to an
+    /// earlier request with the same `Idempotency-Key`.
+    pub fn with_idempotent_replay(&mut self) -> &mut Self {
+        self.0
+            .insert(HeaderName::from_static("idempotent-replayed"), HeaderValue::from_static("true"));
+
+        self
+    }
+}
+
+/// Compact the relations of a set of links or embedded resources using the registered CURIEs.
+///
+/// # Parameters
+/// - `relations` - The relations to compact
+/// - `curies` - The registered CURIEs, as pairs of name and template
+fn compact_relations<V>(relations: BTreeMap<String, V>, curies: &[(String, String)]) -> BTreeMap<String, V> {
+    if curies.is_empty() {
+        return relations;
+    }
+
+    let mut result = BTreeMap::new();
+    let mut compacted = vec![];
+    for (rel, value) in relations {
+        match compact_relation(&rel, curies) {
+            Some(compact) => compacted.push((rel, compact, value)),
+            None => {
+                result.insert(rel, value);
+            },
+        }
+    }
+
+    for (rel, compact, value) in compacted {
+        if result.contains_key(&compact) {
+            result.insert(rel, value);
+        } else {
+            result.insert(compact, value);
+        }
+    }
+
+    result
+}
+
+/// Compact a single relation using the best matching CURIE.
+///
+/// # Parameters
+/// - `rel` - The relation to compact
+/// - `curies` - The registered CURIEs, as pairs of name and template
+///
+/// # Returns
+/// The compact form of the relation, or `None` if no CURIE matches it
+fn compact_relation(rel: &str, curies: &[(String, String)]) -> Option<String> {
+    let mut best: Option<(usize, String)> = None;
+
+    for (name, template) in curies {
+        let (prefix, suffix) = template.split_once("{rel}").unwrap_or((template, ""));
+        let reference = rel.strip_prefix(prefix).and_then(|rest| rest.strip_suffix(suffix));
+
+        if let Some(reference) = reference.filter(|reference| !reference.is_empty()) {
+            if best.as_ref().is_none_or(|(len, _)| template.len() > *len) {
+                best = Some((template.len(), format!("{}:{}", name, reference)));
+            }
+        }
+    }
+
+    best.map(|(_, compact)| compact)
+}
+
+/// Append a `Link` header for every one of the provided links.
+///
+/// # Parameters
+/// - `links` - The links to append headers for
+/// - `trailing_slash` - The policy for trailing slashes on the hrefs
+/// - `headers` - The headers to append to
+fn append_link_headers(links: &BTreeMap<String, Links>, trailing_slash: TrailingSlash, headers: &mut HeaderMap) {
+    let quote = |value: &str| format!("\"{}\"", value.replace('\\', "\\\\").replace('"', "\\\""));
+
+    // A link registered under several relations is a single link with several relation types, so it's emitted
+    // as one header with a space-separated `rel`. Links are compared by equality, so only identical links merge.
+    let mut consolidated: Vec<(&Link, Vec<&str>)> = vec![];
+    for (rel, links) in links {
+        for link in links.iter() {
+            match consolidated.iter_mut().find(|(existing, _)| *existing == link) {
+                Some((_, rels)) => rels.push(rel),
+                None => consolidated.push((link, vec![rel])),
+            }
+        }
+    }
+
+    for (link, rels) in consolidated {
+        let href = trailing_slash.normalize(&link.href);
+        let mut value = format!("<{}>; rel={}", href, quote(&rels.join(" ")));
+        if let Some(name) = &link.name {
+            value.push_str(&format!("; name={}", quote(name)));
+        }
+        if link.templated {
+            value.push_str("; templated=\"true\"");
+        }
+        if let Some(r#type) = &link.r#type {
+            value.push_str(&format!("; type={}", quote(r#type)));
+        }
+        if let Some(hreflang) = &link.hreflang {
+            value.push_str(&format!("; hreflang={}", quote(hreflang)));
+        }
+        if let Some(anchor) = &link.anchor {
+            value.push_str(&format!("; anchor={}", quote(anchor)));
+        }
+
+        match HeaderValue::from_str(&value) {
+            Ok(value) => {
+                headers.append(header::LINK, value);
+            },
+            Err(_) => {
+                tracing::error!(rels = ?rels, href = ?link.href, "Failed to process link header");
+            },
+        };
+    }
+}
+
+/// Determine if the provided string is a valid media type, optionally with parameters.
+fn is_media_type(media_type: &str) -> bool {
+    let is_token = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_alphanumeric() || "!#$&-^_.+".contains(c));
+
+    let essence = media_type.split(';').next().unwrap_or_default().trim();
+    match essence.split_once('/') {
+        Some((r#type, subtype)) => is_token(r#type) && is_token(subtype),
+        None => false,
+    }
+}
+
+/// Determine if the provided string is well-formed as a BCP 47 language tag.
+///
+/// This checks the shape of the tag - a primary language subtag of letters followed by any number of
+/// alphanumeric subtags - but not that the subtags are registered.
+fn is_language_tag(lang: &str) -> bool {
+    let mut subtags = lang.split('-');
+
+    let primary = subtags.next().unwrap_or_default();
+    let primary_valid =
+        (primary.len() >= 2 || primary.eq_ignore_ascii_case("x") || primary.eq_ignore_ascii_case("i"))
+            && primary.len() <= 8
+            && primary.chars().all(|c| c.is_ascii_alphabetic());
+
+    primary_valid
+        && subtags.all(|subtag| (1..=8).contains(&subtag.len()) && subtag.chars().all(|c| c.is_ascii_alphanumeric()))
+}
+
+impl Deref for Headers {
+    type Target = HeaderMap;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for Headers {
+    fn deref_mut(&mut self) -> &mut Self::Target {
         &mut self.0
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        future::Future,
+        pin::pin,
+        task::{Context, Poll, Waker},
+    };
+
+    use actix_web::{test::TestRequest, Responder};
+    use assert2::{check, let_assert};
+
+    use super::*;
+
+    #[test]
+    fn warnings_are_repeated() {
+        let mut headers = Headers::default();
+        headers
+            .with_warning(110, "-", "Response is Stale")
+            .with_warning(199, "cache.example.com", "Said \"hello\"");
+
+        let values: Vec<_> = headers.get_all(header::WARNING).collect();
+        check!(values.len() == 2);
+        check!(values[0] == "110 - \"Response is Stale\"");
+        check!(values[1] == "199 cache.example.com \"Said \\\"hello\\\"\"");
+    }
+
+    #[test]
+    fn invalid_warning_code_is_ignored() {
+        let mut headers = Headers::default();
+        headers.with_warning(404, "-", "Not a warning");
+
+        check!(headers.get(header::WARNING).is_none());
+    }
+
+    #[test]
+    fn echo_request_headers() {
+        let req = TestRequest::default()
+            .insert_header(("x-request-id", "abc123"))
+            .insert_header(("x-other", "ignored"))
+            .to_http_request();
+        let mut headers = Headers::default();
+        headers.echo_headers(&req, &["x-request-id", "traceparent"]);
+
+        check!(headers.len() == 1);
+        check!(headers.get("x-request-id").unwrap() == "abc123");
+    }
+
+    #[test]
+    fn idempotency_headers() {
+        let req = TestRequest::default()
+            .insert_header(("idempotency-key", "8e03978e-40d5-43e8-bc93-6894a57f9324"))
+            .to_http_request();
+        let mut headers = Headers::default();
+        headers.with_idempotency_key(&req).with_idempotent_replay();
+
+        check!(headers.get("idempotency-key").unwrap() == "8e03978e-40d5-43e8-bc93-6894a57f9324");
+        check!(headers.get("idempotent-replayed").unwrap() == "true");
+    }
+
+    #[test]
+    fn retry_after() {
+        let mut headers = Headers::default();
+        headers.with_retry_after(RetryAfter::Delay(Duration::from_millis(120_500)));
+        check!(headers.get(header::RETRY_AFTER).unwrap() == "120");
+
+        let date = SystemTime::UNIX_EPOCH + Duration::from_secs(784_887_151);
+        headers.with_retry_after(RetryAfter::At(date));
+        check!(headers.get_all(header::RETRY_AFTER).collect::<Vec<_>>() == vec!["Tue, 15 Nov 1994 08:12:31 GMT"]);
+    }
+
+    #[test]
+    fn clear_site_data() {
+        let mut headers = Headers::default();
+        headers.with_clear_site_data(&[]);
+        check!(headers.is_empty());
+
+        headers.with_clear_site_data(&[ClearSiteData::Cookies, ClearSiteData::Storage, ClearSiteData::Cookies]);
+        check!(headers.get("clear-site-data").unwrap() == "\"cookies\", \"storage\"");
+    }
+
+    #[test]
+    fn age() {
+        let mut headers = Headers::default();
+        headers.with_age(Duration::from_millis(30_900));
+        check!(headers.get_all(header::AGE).collect::<Vec<_>>() == vec!["30"]);
+
+        headers.with_age(Duration::from_secs(u64::MAX));
+        check!(headers.get(header::AGE).unwrap() == "2147483648");
+
+        headers.with_age_since(SystemTime::now() + Duration::from_secs(60));
+        check!(headers.get(header::AGE).unwrap() == "0");
+
+        headers.with_age_since(SystemTime::now() - Duration::from_secs(120));
+        let age: u64 = headers.get(header::AGE).unwrap().to_str().unwrap().parse().unwrap();
+        check!((120..=121).contains(&age));
+    }
+
+    #[test]
+    fn sunset_and_deprecation() {
+        let date = SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(784_887_151);
+        let mut headers = Headers::default();
+        headers
+            .with_sunset(date)
+            .with_deprecation_header(DeprecationWhen::Now)
+            .with_deprecation_header(DeprecationWhen::At(date));
+
+        check!(headers.get("sunset").unwrap() == "Tue, 15 Nov 1994 08:12:31 GMT");
+        let deprecation: Vec<_> = headers.get_all("deprecation").collect();
+        check!(deprecation == vec!["true", "Tue, 15 Nov 1994 08:12:31 GMT"]);
+    }
+
+    #[test]
+    fn content_language() {
+        let mut headers = Headers::default();
+        headers.with_content_language("en-GB").with_content_language("zh-Hant-TW");
+
+        let values: Vec<_> = headers.get_all(header::CONTENT_LANGUAGE).collect();
+        check!(values == vec!["en-GB", "zh-Hant-TW"]);
+    }
+
+    #[test]
+    fn invalid_content_language() {
+        let mut headers = Headers::default();
+        headers
+            .with_content_language("")
+            .with_content_language("e")
+            .with_content_language("en_GB")
+            .with_content_language("en--GB")
+            .with_content_language("englishlanguage");
+
+        check!(headers.get(header::CONTENT_LANGUAGE).is_none());
+        check!(headers.errors().len() == 5);
+
+        let_assert!(Err(errors) = HalRespondable::new(()).with_content_language("en_GB").build());
+        check!(errors[0].name == "content-language");
+    }
+
+    #[test]
+    fn accept_patch() {
+        let mut headers = Headers::default();
+        headers.with_accept_patch(&[
+            "application/merge-patch+json",
+            "application/json-patch+json",
+            "application/Merge-Patch+json",
+            "not a media type",
+            "text/plain; charset=utf-8",
+        ]);
+
+        check!(
+            headers.get("accept-patch").unwrap()
+                == "application/merge-patch+json, application/json-patch+json, text/plain; charset=utf-8"
+        );
+    }
+
+    #[test]
+    fn configure_headers_with_closure() {
+        let respondable = HalRespondable::empty().with_headers_with(|headers| {
+            headers
+                .with_header_value(header::CACHE_CONTROL, "no-cache")
+                .with_header_value(header::VARY, "Accept");
+        });
+
+        let headers = respondable.headers();
+        check!(headers.get(header::CACHE_CONTROL).unwrap() == "no-cache");
+        check!(headers.get(header::VARY).unwrap() == "Accept");
+        check!(headers.get(header::CONTENT_TYPE).unwrap() == "application/hal+json");
+    }
+
+    #[test]
+    fn relation_cardinality() {
+        let body = HalRespondable::new(())
+            .with_link("self", "/self")
+            .with_link("item", "/item")
+            .with_link("other", "/other")
+            .array_relation("item")
+            .body();
+
+        let json = serde_json::to_value(body.links).unwrap();
+        check!(json["self"]["href"] == "/self");
+        check!(json["item"][0]["href"] == "/item");
+        check!(json["other"]["href"] == "/other");
+    }
+
+    #[test]
+    fn empty_resource() {
+        let body = HalRespondable::empty().with_link("self", "/").body();
+
+        let json = serde_json::to_value(body).unwrap();
+        check!(json == serde_json::json!({"_links": {"self": {"href": "/"}}}));
+    }
+
+    #[test]
+    fn collection_meta() {
+        let body = HalRespondable::empty()
+            .with_collection_meta("page", CollectionMeta::new(25, 1, 10))
+            .body();
+
+        let json = serde_json::to_value(body).unwrap();
+        check!(json["page"] == serde_json::json!({"total": 25, "page": 1, "size": 10, "pages": 3}));
+    }
+
+    #[test]
+    fn etag_ignores_excluded_fields() {
+        let first = HalRespondable::new(serde_json::json!({"id": 1, "generated_at": "first"}))
+            .with_etag_excluding(&["/generated_at"]);
+        let second = HalRespondable::new(serde_json::json!({"id": 1, "generated_at": "second"}))
+            .with_etag_excluding(&["/generated_at"]);
+        let third = HalRespondable::new(serde_json::json!({"id": 2, "generated_at": "first"}))
+            .with_etag_excluding(&["/generated_at"]);
+
+        let etag = first.headers().get(header::ETAG).cloned().unwrap();
+        check!(etag.to_str().unwrap().starts_with("W/\""));
+        check!(Some(&etag) == second.headers().get(header::ETAG));
+        check!(Some(&etag) != third.headers().get(header::ETAG));
+    }
+
+    #[test]
+    fn conditional_not_modified() {
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "\"abc\""))
+            .to_http_request();
+        let response = HalRespondable::new(serde_json::json!({"id": 1}))
+            .with_header_value(header::ETAG, "\"abc\"")
+            .with_header_value(header::CACHE_CONTROL, "max-age=60")
+            .with_header_value(header::LOCATION, "/other")
+            .conditional(&req);
+        let response = Response::new(response).respond_to(&req);
+
+        check!(response.status() == StatusCode::NOT_MODIFIED);
+        check!(response.headers().get(header::ETAG).unwrap() == "\"abc\"");
+        check!(response.headers().get(header::CACHE_CONTROL).unwrap() == "max-age=60");
+        check!(response.headers().get(header::LOCATION).is_none());
+        check!(response.headers().get(header::CONTENT_TYPE).is_none());
+    }
+
+    #[test]
+    fn conditional_not_modified_header_set() {
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "\"abc\""))
+            .to_http_request();
+        let response = HalRespondable::new(serde_json::json!({"id": 1}))
+            .with_header_value(header::ETAG, "\"abc\"")
+            .with_header_value(header::CACHE_CONTROL, "max-age=60")
+            .with_header_value(header::CONTENT_LOCATION, "/resource")
+            .with_header_value(header::DATE, "Tue, 15 Nov 1994 08:12:31 GMT")
+            .with_header_value(header::EXPIRES, "Tue, 15 Nov 1994 08:13:31 GMT")
+            .with_header_value(header::VARY, "Accept")
+            .with_header_value(header::VARY, "Accept-Language")
+            .with_header_value(header::LAST_MODIFIED, "Tue, 15 Nov 1994 08:00:00 GMT")
+            .with_header_value(header::CONTENT_LANGUAGE, "en")
+            .with_header_value(header::LOCATION, "/other")
+            .conditional(&req);
+        let response = Response::new(response).respond_to(&req);
+
+        let mut names: Vec<_> = response.headers().keys().map(|name| name.as_str()).collect();
+        names.sort_unstable();
+        names.dedup();
+        check!(names == vec!["cache-control", "content-location", "date", "etag", "expires", "vary"]);
+        check!(response.headers().get_all(header::VARY).count() == 2);
+    }
+
+    #[test]
+    fn conditional_modified() {
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "\"def\""))
+            .to_http_request();
+        let response = HalRespondable::new(serde_json::json!({"id": 1}))
+            .with_header_value(header::ETAG, "\"abc\"")
+            .conditional(&req);
+        let response = Response::new(response).respond_to(&req);
+
+        check!(response.status() == StatusCode::OK);
+        check!(response.headers().get(header::CONTENT_TYPE).unwrap() == "application/hal+json");
+    }
+
+    #[test]
+    fn link_from_payload() {
+        let body = HalRespondable::new(serde_json::json!({"id": 42}))
+            .with_link_fn("self", |payload| format!("/items/{}", payload["id"]))
+            .body();
+
+        check!(serde_json::to_value(body.links).unwrap()["self"]["href"] == "/items/42");
+    }
+
+    #[test]
+    fn conditional_links() {
+        let body = HalRespondable::empty()
+            .with_link_if(true, "edit", "/edit")
+            .with_link_if(false, "delete", "/delete")
+            .body();
+
+        check!(body.links.contains_key("edit"));
+        check!(!body.links.contains_key("delete"));
+    }
+
+    #[test]
+    fn embedded_resources() {
+        let body = HalRespondable::empty()
+            .with_embedded(
+                "owner",
+                HalRespondable::new(serde_json::json!({"name": "Graham"})).with_link("self", "/owner"),
+            )
+            .with_embedded_if(true, "item", 1)
+            .with_embedded_if(true, "item", 2)
+            .with_embedded_if(false, "hidden", 3)
+            .body();
+
+        let json = serde_json::to_value(body).unwrap();
+        check!(json["_embedded"]["owner"]["name"] == "Graham");
+        check!(json["_embedded"]["owner"]["_links"]["self"]["href"] == "/owner");
+        check!(json["_embedded"]["item"] == serde_json::json!([1, 2]));
+        check!(json["_embedded"].get("hidden").is_none());
+    }
+
+    #[test]
+    fn embedded_cardinality() {
+        let body = HalRespondable::empty()
+            .with_embedded("item", 1)
+            .with_embedded("owner", 2)
+            .array_embedded("item")
+            .body();
+
+        let json = serde_json::to_value(body).unwrap();
+        check!(json["_embedded"] == serde_json::json!({"item": [1], "owner": 2}));
+    }
+
+    #[test]
+    fn embedded_collection() {
+        let orders = vec![serde_json::json!({"id": 1}), serde_json::json!({"id": 2})];
+        let orders = CollectionResponse::new("/customers/1/orders", orders, CollectionMeta::new(4, 0, 2));
+        let body = HalRespondable::new(serde_json::json!({"name": "Graham"}))
+            .with_link("self", "/customers/1")
+            .with_embedded_collection("orders", orders)
+            .body();
+
+        let json = serde_json::to_value(body).unwrap();
+        let orders = &json["_embedded"]["orders"];
+        check!(orders["_links"]["self"]["href"] == "/customers/1/orders?page=0");
+        check!(orders["_links"]["next"]["href"] == "/customers/1/orders?page=1");
+        check!(orders["_embedded"]["item"][1]["id"] == 2);
+        check!(orders["page"]["pages"] == 2);
+    }
+
+    #[test]
+    fn collection_of_one_item() {
+        let body = HalRespondable::new(()).as_collection_of("item", vec!["/items/1"]).body();
+
+        let json = serde_json::to_value(body.links).unwrap();
+        check!(json["item"][0]["href"] == "/items/1");
+    }
+
+    #[test]
+    fn member_of_collection() {
+        let body = HalRespondable::new(()).member_of("/items").body();
+
+        let json = serde_json::to_value(body.links).unwrap();
+        check!(json["collection"]["href"] == "/items");
+    }
+
+    #[test]
+    fn sparse_fields() {
+        let req = TestRequest::with_uri("/users/1?fields=id,%20unknown").to_http_request();
+        let response = HalRespondable::new(serde_json::json!({"id": 1, "name": "Graham"}))
+            .with_link("self", "/users/1")
+            .with_sparse_fields(&req);
+        let bytes = HalRespondable::serialize_body(response.body(), false).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        check!(json == serde_json::json!({"id": 1, "_links": {"self": {"href": "/users/1"}}}));
+    }
+
+    #[test]
+    fn sparse_fields_custom_param() {
+        let req = TestRequest::with_uri("/users/1?fields=id&only=name").to_http_request();
+        let response = HalRespondable::new(serde_json::json!({"id": 1, "name": "Graham"}))
+            .with_sparse_fields_param(&req, "only");
+        let bytes = HalRespondable::serialize_body(response.body(), false).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        check!(json == serde_json::json!({"name": "Graham", "_links": {}}));
+    }
+
+    #[test]
+    fn into_http_response() {
+        let response: HttpResponse = HalRespondable::new(serde_json::json!({"id": 1}))
+            .with_status_code(StatusCode::CREATED)
+            .into();
+
+        check!(response.status() == StatusCode::CREATED);
+        check!(response.headers().get(header::CONTENT_TYPE).unwrap() == "application/hal+json");
+        check!(response.headers().contains_key(header::DATE));
+    }
+
+    #[test]
+    fn visit_links() {
+        let mut visited = vec![];
+        let body = HalRespondable::new(())
+            .with_link("self", "http://upstream/users/1")
+            .with_link("item", "http://upstream/items/1")
+            .with_link("item", "http://upstream/items/2")
+            .visit_links(|rel, link| {
+                visited.push(rel.to_owned());
+                link.href = link.href.replace("http://upstream", "https://api.example.com");
+            })
+            .body();
+
+        check!(visited == vec!["item", "item", "self"]);
+        let json = serde_json::to_value(body.links).unwrap();
+        check!(json["self"]["href"] == "https://api.example.com/users/1");
+        check!(json["item"][1]["href"] == "https://api.example.com/items/2");
+    }
+
+    #[test]
+    fn self_link_for_unknown_route() {
+        let req = TestRequest::with_uri("/users/123").to_http_request();
+        let body = HalRespondable::new(())
+            .with_self_link_for_route(&req, "user", ["123"], "/users/123")
+            .body();
+
+        let json = serde_json::to_value(body.links).unwrap();
+        check!(json["self"]["href"] == "/users/123");
+    }
+
+    #[test]
+    fn accepted() {
+        let respondable = HalRespondable::accepted(serde_json::json!({"state": "queued"}), "/jobs/1/status")
+            .with_retry_after(RetryAfter::Delay(Duration::from_secs(5)));
+
+        check!(respondable.status_code() == StatusCode::ACCEPTED);
+        let headers = respondable.headers();
+        check!(headers.get(header::LOCATION).unwrap() == "/jobs/1/status");
+        check!(headers.get(header::RETRY_AFTER).unwrap() == "5");
+        let json = serde_json::to_value(respondable.body().links).unwrap();
+        check!(json["status"]["href"] == "/jobs/1/status");
+    }
+
+    #[test]
+    fn enveloped() {
+        let response = HalRespondable::new(serde_json::json!({"id": 1}))
+            .with_link("self", "/users/1")
+            .with_envelope("result")
+            .with_envelope_field("requestId", "abc123");
+        let bytes = HalRespondable::serialize_body(response.body(), false).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        check!(
+            json == serde_json::json!({
+                "result": {"id": 1, "_links": {"self": {"href": "/users/1"}}},
+                "requestId": "abc123",
+            })
+        );
+    }
+
+    #[test]
+    fn link_headers_match_body() {
+        let respondable = HalRespondable::new(())
+            .with_link("self", "/users/1")
+            .with_link("item", Link {
+                name: Some("first".to_owned()),
+                ..Link::from("/items/1")
+            })
+            .with_link("search", Link::templated("/users{?q}"))
+            .array_relation("self")
+            .with_link_header(true);
+
+        let headers: Vec<_> = respondable.headers().get_all(header::LINK).cloned().collect();
+        check!(
+            headers
+                == vec![
+                    "</items/1>; rel=\"item\"; name=\"first\"",
+                    "</users{?q}>; rel=\"search\"; templated=\"true\"",
+                    "</users/1>; rel=\"self\"",
+                ]
+        );
+
+        let json = serde_json::to_value(respondable.body().links).unwrap();
+        check!(json["self"][0]["href"] == "/users/1");
+        check!(json["item"]["name"] == "first");
+        check!(json["search"]["templated"] == true);
+    }
+
+    #[test]
+    fn multiple_choices() {
+        let respondable = HalRespondable::multiple_choices(&[
+            Variant {
+                href:       "/docs/en".to_owned(),
+                media_type: "text/html".to_owned(),
+                language:   Some("en".to_owned()),
+            },
+            Variant {
+                href:       "/docs.pdf".to_owned(),
+                media_type: "application/pdf".to_owned(),
+                language:   None,
+            },
+        ]);
+
+        check!(respondable.status_code() == StatusCode::MULTIPLE_CHOICES);
+        let json = serde_json::to_value(respondable.body().links).unwrap();
+        check!(
+            json["alternate"]
+                == serde_json::json!([
+                    {"href": "/docs/en", "type": "text/html", "hreflang": "en"},
+                    {"href": "/docs.pdf", "type": "application/pdf"},
+                ])
+        );
+    }
+
+    #[test]
+    fn optional_links() {
+        let body = HalRespondable::new(())
+            .with_optional_link("prev", None::<&str>)
+            .with_optional_link("next", Some("/items?page=2"))
+            .body();
+
+        let json = serde_json::to_value(body.links).unwrap();
+        check!(json == serde_json::json!({"next": {"href": "/items?page=2"}}));
+    }
+
+    #[test]
+    fn into_hal_keeps_repeated_headers() {
+        struct Session;
+
+        impl IntoHal<()> for Session {
+            fn headers(&self, headers: &mut Headers) {
+                headers
+                    .with_header_value(header::SET_COOKIE, "a=1")
+                    .with_header_value(header::LINK, "</a>; rel=\"a\"")
+                    .with_header_value(header::SET_COOKIE, "b=2")
+                    .with_header_value(header::SET_COOKIE, "c=3");
+            }
+
+            fn payload(self) {}
+        }
+
+        let headers = Session.into_hal().into_inner().headers();
+        check!(headers.get_all(header::SET_COOKIE).collect::<Vec<_>>() == vec!["a=1", "b=2", "c=3"]);
+        check!(headers.get_all(header::LINK).collect::<Vec<_>>() == vec!["</a>; rel=\"a\""]);
+        check!(headers.get_all(header::CONTENT_TYPE).collect::<Vec<_>>() == vec!["application/hal+json"]);
+    }
+
+    #[test]
+    fn embedded_meta() {
+        let body = HalRespondable::new(())
+            .with_embedded("items", serde_json::json!({"id": 1}))
+            .with_embedded_meta("items", 25, true)
+            .body();
+        let json = serde_json::to_value(body).unwrap();
+        check!(json["_meta"] == serde_json::json!({"items": {"count": 25, "truncated": true}}));
+
+        let body = HalRespondable::new(())
+            .with_embedded_meta("items", 1, false)
+            .with_embedded_meta_key("_embeddedMeta")
+            .body();
+        let json = serde_json::to_value(body).unwrap();
+        check!(json["_embeddedMeta"]["items"]["count"] == 1);
+
+        let json = serde_json::to_value(HalRespondable::new(()).body()).unwrap();
+        check!(json.get("_meta").is_none());
+    }
+
+    #[test]
+    fn colliding_meta_keys() {
+        let body = HalRespondable::new(())
+            .with_collection_meta("_meta", CollectionMeta::new(10, 0, 5))
+            .with_embedded_meta("items", 10, true)
+            .body();
+        let serialized = serde_json::to_string(&body).unwrap();
+
+        check!(serialized.matches("\"_meta\"").count() == 1);
+        check!(serialized.contains("\"total\":10"));
+    }
+
+    #[test]
+    fn curie_compaction() {
+        let body = HalRespondable::new(())
+            .with_curie("acme", "https://acme.com/rels/{rel}")
+            .with_curie("widgets", "https://acme.com/rels/widgets/{rel}")
+            .with_link("https://acme.com/rels/order", "/orders/1")
+            .with_link("https://acme.com/rels/widgets/gear", "/widgets/1")
+            .with_link("https://other.com/rels/order", "/other/1")
+            .with_embedded("https://acme.com/rels/customer", serde_json::json!({"id": 1}))
+            .body();
+
+        let json = serde_json::to_value(body).unwrap();
+        check!(json["_links"]["acme:order"]["href"] == "/orders/1");
+        check!(json["_links"]["widgets:gear"]["href"] == "/widgets/1");
+        check!(json["_links"]["https://other.com/rels/order"]["href"] == "/other/1");
+        check!(
+            json["_links"]["curies"][0]
+                == serde_json::json!({"href": "https://acme.com/rels/{rel}", "name": "acme", "templated": true})
+        );
+        check!(json["_embedded"]["acme:customer"]["id"] == 1);
+    }
+
+    #[test]
+    fn updated() {
+        let respondable =
+            HalRespondable::updated(serde_json::json!({"id": 1}), Some("v2")).with_link("self", "/users/1");
+        check!(respondable.status_code() == StatusCode::OK);
+        check!(respondable.headers().get_all(header::ETAG).collect::<Vec<_>>() == vec!["\"v2\""]);
+
+        let respondable = HalRespondable::updated(serde_json::json!({"id": 1}), Some("W/\"v2\""));
+        check!(respondable.headers().get(header::ETAG).unwrap() == "W/\"v2\"");
+
+        let respondable = HalRespondable::updated(serde_json::json!({"id": 1}), None);
+        check!(respondable.headers().get(header::ETAG).unwrap().to_str().unwrap().starts_with("W/\""));
+    }
+
+    #[test]
+    fn empty_relations() {
+        let body = HalRespondable::new(())
+            .with_empty_relation("next", EmptyForm::Null)
+            .with_empty_relation("item", EmptyForm::Array)
+            .with_empty_relation("self", EmptyForm::Null)
+            .with_link("self", "/items")
+            .body();
+
+        let json = serde_json::to_value(body.links).unwrap();
+        check!(json == serde_json::json!({"next": null, "item": [], "self": {"href": "/items"}}));
+    }
+
+    #[test]
+    fn profiles() {
+        let respondable = HalRespondable::new(()).with_profile("https://acme.com/profiles/v4");
+        check!(
+            respondable.headers().get_all(header::CONTENT_TYPE).collect::<Vec<_>>()
+                == vec!["application/hal+json; profile=\"https://acme.com/profiles/v4\""]
+        );
+
+        let settings = Settings {
+            profile: Some("https://acme.com/profiles/v3".to_owned()),
+            ..Settings::default()
+        };
+        let (_, global, _) = HalRespondable::new(()).parts(&settings);
+        let (_, overridden, _) = HalRespondable::new(()).with_profile("https://acme.com/profiles/v4").parts(&settings);
+
+        let v3 = "application/hal+json; profile=\"https://acme.com/profiles/v3\"";
+        let v4 = "application/hal+json; profile=\"https://acme.com/profiles/v4\"";
+        check!(global.get(header::CONTENT_TYPE).unwrap() == v3);
+        check!(overridden.get(header::CONTENT_TYPE).unwrap() == v4);
+    }
+
+    #[test]
+    fn trailing_slash_normalization() {
+        let respondable = || {
+            HalRespondable::new(())
+                .with_link("self", "/users/1/")
+                .with_link("next", "/users/?page=2")
+                .with_link_header(true)
+        };
+
+        let settings = Settings {
+            trailing_slash: TrailingSlash::AlwaysStrip,
+            ..Settings::default()
+        };
+        let (_, headers, body) = respondable().parts(&settings);
+
+        let json = serde_json::to_value(body.links).unwrap();
+        check!(json["self"]["href"] == "/users/1");
+        check!(json["next"]["href"] == "/users?page=2");
+        let headers: Vec<_> = headers.get_all(header::LINK).cloned().collect();
+        check!(headers == vec!["</users?page=2>; rel=\"next\"", "</users/1>; rel=\"self\""]);
+
+        let author = HalRespondable::new(())
+            .with_link("self", "/users/2/")
+            .with_embedded("avatar", HalRespondable::new(()).with_link("self", "/avatars/2/"));
+        let (_, _, body) = respondable().with_embedded("author", author).parts(&settings);
+
+        let json = serde_json::to_value(body.embedded).unwrap();
+        check!(json["author"]["_links"]["self"]["href"] == "/users/2");
+        check!(json["author"]["_embedded"]["avatar"]["_links"]["self"]["href"] == "/avatars/2");
+    }
+
+    #[test]
+    fn canonical_relation_ordering() {
+        let settings = Settings {
+            canonical_order: true,
+            ..Settings::default()
+        };
+        let respondable = || {
+            HalRespondable::new(serde_json::json!({"id": 1, "name": null}))
+                .with_link("author", "/users/1")
+                .with_link("self", "/posts/1")
+                .with_curie("acme", "https://acme.com/rels/{rel}")
+                .with_embedded("self", serde_json::json!({"id": 1}))
+                .with_embedded("author", serde_json::json!({"id": 2}))
+        };
+        let serialize = |body, omit_nulls| {
+            String::from_utf8(HalRespondable::serialize_body(body, omit_nulls).unwrap()).unwrap()
+        };
+        let in_order = |serialized: &str, keys: &[&str]| {
+            let positions: Vec<_> = keys.iter().map(|key| serialized.find(key).unwrap()).collect();
+            positions.windows(2).all(|pair| pair[0] < pair[1])
+        };
+
+        let direct = serialize(respondable().payload(&settings), false);
+        check!(in_order(&direct, &["\"_links\"", "\"self\"", "\"curies\"", "\"author\"", "\"_embedded\""]));
+        check!(in_order(&direct, &["\"_embedded\":{\"self\"", "\"author\":{\"id\":2}", "\"id\":1,\"name\""]));
+
+        let omitted = serialize(respondable().payload(&settings), true);
+        check!(in_order(&omitted, &["\"_links\"", "\"self\"", "\"curies\"", "\"author\"", "\"_embedded\""]));
+        check!(!omitted.contains("null"));
+
+        let enveloped = serialize(respondable().with_envelope("data").payload(&settings), false);
+        check!(in_order(&enveloped, &["\"data\":{\"_links\":{\"self\"", "\"curies\"", "\"author\""]));
+    }
+
+    #[test]
+    fn sorted_links() {
+        let body = HalRespondable::new(())
+            .with_link("item", "/items/2")
+            .with_link("item", Link {
+                name: Some("b".to_owned()),
+                ..Link::from("/items/1")
+            })
+            .with_link("item", Link {
+                name: Some("a".to_owned()),
+                ..Link::from("/items/1")
+            })
+            .with_link("other", "/other/2")
+            .with_link("other", "/other/1")
+            .with_sorted_links("item")
+            .body();
+
+        let json = serde_json::to_value(&body.links).unwrap();
+        check!(
+            json == serde_json::json!({
+                "item": [
+                    {"href": "/items/1", "name": "a"},
+                    {"href": "/items/1", "name": "b"},
+                    {"href": "/items/2"}
+                ],
+                "other": [{"href": "/other/2"}, {"href": "/other/1"}]
+            })
+        );
+    }
+
+    #[test]
+    fn expandable_relations() {
+        let req = TestRequest::with_uri("/users/1?embed=orders").to_http_request();
+        let expansion = Expansion::from_request(&req, &["orders", "address"]);
+
+        let future = HalRespondable::new(())
+            .with_expandable(&expansion, "orders", "/users/1/orders", || async { serde_json::json!([{"id": 1}]) });
+        let_assert!(Poll::Ready(respondable) = pin!(future).poll(&mut Context::from_waker(Waker::noop())));
+        let future = respondable.with_expandable(&expansion, "address", "/users/1/address", || async {
+            serde_json::json!({"street": "Main Street"})
+        });
+        let_assert!(Poll::Ready(respondable) = pin!(future).poll(&mut Context::from_waker(Waker::noop())));
+
+        let body = serde_json::to_value(respondable.body()).unwrap();
+        check!(body["_embedded"]["orders"] == serde_json::json!([{"id": 1}]));
+        check!(body["_links"]["address"] == serde_json::json!({"href": "/users/1/address"}));
+        check!(body["_links"].get("orders").is_none());
+        check!(body["_embedded"].get("address").is_none());
+    }
+
+    #[test]
+    fn link_headers_consolidate_relations() {
+        let respondable = HalRespondable::new(())
+            .with_link_relations(&["self", "canonical"], "/users/1")
+            .with_link("item", "/users/1/items")
+            .with_link_header(true);
+
+        let headers: Vec<_> = respondable.headers().get_all(header::LINK).cloned().collect();
+        check!(headers == vec!["</users/1>; rel=\"canonical self\"", "</users/1/items>; rel=\"item\""]);
+
+        let json = serde_json::to_value(respondable.body().links).unwrap();
+        check!(json["self"]["href"] == "/users/1");
+        check!(json["canonical"]["href"] == "/users/1");
+    }
+
+    #[test]
+    fn link_header_anchor() {
+        let respondable = HalRespondable::new(())
+            .with_link("author", Link::from("/users/2").with_anchor("/posts/1"))
+            .with_link_header(true);
+
+        let headers: Vec<_> = respondable.headers().get_all(header::LINK).cloned().collect();
+        check!(headers == vec!["</users/2>; rel=\"author\"; anchor=\"/posts/1\""]);
+
+        let json = serde_json::to_value(respondable.body().links).unwrap();
+        check!(json["author"] == serde_json::json!({"href": "/users/2"}));
+    }
+
+    #[test]
+    fn skip_embedded_failures() {
+        let mut broken = HashMap::new();
+        broken.insert((1, 2), "Not a string key");
+
+        let respondable = HalRespondable::new(())
+            .with_embedded("items", serde_json::json!({"id": 1}))
+            .with_embedded("items", broken.clone())
+            .with_embedded("owner", broken)
+            .with_embedded_failure_mode(EmbeddedFailureMode::Skip);
+        let bytes = HalRespondable::serialize_body(respondable.body(), false).unwrap();
+
+        let json: serde_json::Value = serde_json::from_slice(&bytes).unwrap();
+        check!(json["_embedded"] == serde_json::json!({"items": [{"id": 1}]}));
+        check!(
+            json["_meta"]
+                == serde_json::json!({
+                    "items": {"count": 2, "truncated": true, "skipped": 1},
+                    "owner": {"count": 1, "truncated": true, "skipped": 1}
+                })
+        );
+    }
+
+    #[test]
+    fn created_resource() {
+        let respondable = HalRespondable::new(())
+            .with_link("self", "/users")
+            .with_link_header(true)
+            .created("/users/1");
+
+        check!(respondable.status_code() == StatusCode::CREATED);
+        let headers = respondable.headers();
+        check!(headers.get_all(header::LOCATION).collect::<Vec<_>>() == vec!["/users/1"]);
+        check!(headers.get_all(header::LINK).collect::<Vec<_>>() == vec!["</users/1>; rel=\"self\""]);
+        check!(serde_json::to_value(respondable.body().links).unwrap()["self"]["href"] == "/users/1");
+    }
+
+    #[test]
+    fn default_headers() {
+        let mut headers = Headers::default();
+        headers.with_header_value(header::CACHE_CONTROL, "no-store");
+
+        check!(!headers.entry_or_insert(header::CACHE_CONTROL, "max-age=60"));
+        check!(headers.entry_or_insert(header::VARY, "Accept"));
+        check!(headers.get_all(header::CACHE_CONTROL).collect::<Vec<_>>() == vec!["no-store"]);
+        check!(headers.get(header::VARY).unwrap() == "Accept");
+
+        let respondable = HalRespondable::new(()).with_default_header(header::CACHE_CONTROL, "max-age=60");
+        check!(respondable.headers().get(header::CACHE_CONTROL).unwrap() == "max-age=60");
+    }
+
+    #[test]
+    fn described_by() {
+        let respondable = HalRespondable::new(())
+            .with_described_by("/schemas/user.json", true)
+            .with_described_by("/docs/users", false);
+        let_assert!(Links::Multiple(links) = respondable.links.get("describedby").unwrap());
+
+        check!(links[0].href == "/schemas/user.json");
+        check!(links[0].r#type == Some("application/schema+json".to_owned()));
+        check!(links[1].href == "/docs/users");
+        check!(links[1].r#type == None);
+    }
+
+    #[test]
+    fn dynamic_headers() {
+        let req = TestRequest::default().insert_header(("X-Request-ID", "abc")).to_http_request();
+        let respondable = || {
+            HalRespondable::new(())
+                .with_header_value(header::VARY, "Accept")
+                .with_dynamic_headers(|req, headers| {
+                    if let Some(id) = req.headers().get("X-Request-ID") {
+                        headers.with_header_value(HeaderName::from_static("x-request-id"), id.clone());
+                    }
+                    headers.insert(header::VARY, HeaderValue::from_static("Accept, Accept-Language"));
+                })
+        };
+
+        let response = Response::new(respondable()).respond_to(&req);
+        check!(response.headers().get("X-Request-ID").unwrap() == "abc");
+        check!(response.headers().get(header::VARY).unwrap() == "Accept, Accept-Language");
+
+        let response = Response::new(respondable()).into_http_response();
+        check!(response.headers().get("X-Request-ID").is_none());
+        check!(response.headers().get(header::VARY).unwrap() == "Accept");
+    }
+
+    #[test]
+    fn header_errors() {
+        let valid = HalRespondable::new(()).with_header_value(header::VARY, "Accept").build();
+        check!(valid.is_ok());
+
+        let_assert!(
+            Err(errors) = HalRespondable::new(())
+                .with_header_value(header::VARY, "Accept\n")
+                .with_warning(999, "-", "Bad code")
+                .with_default_header(header::CACHE_CONTROL, "no-store\r")
+                .build()
+        );
+        let names: Vec<_> = errors.iter().map(|e| e.name.as_str()).collect();
+        check!(names == vec!["vary", "warning", "cache-control"]);
+
+        let req = TestRequest::default().to_http_request();
+        let_assert!(
+            Err(errors) = HalRespondable::new(())
+                .echo_headers(&req, &["x request id"])
+                .with_accept_patch(&["not a media type"])
+                .build()
+        );
+        let names: Vec<_> = errors.iter().map(|e| e.name.as_str()).collect();
+        check!(names == vec!["x request id", "accept-patch"]);
+    }
+}
@@ -0,0 +1,322 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_http::http::{header, HeaderValue};
+use actix_web::{web::Bytes, HttpRequest, HttpResponse, Responder};
+use futures_core::Stream;
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use super::HalRespondable;
+use crate::{
+    problem::{Problem, INTERNAL_SERVER_ERROR},
+    response::Respondable,
+};
+
+/// The number of bytes of serialized items to buffer before sending them to the client.
+const FLUSH_SIZE: usize = 8 * 1024;
+
+/// Response for a large HAL collection, where the envelope of the resource is buffered but the items embedded
+/// under one relation are streamed to the client as a JSON array.
+///
+/// The envelope - the payload, `_links`, any other `_embedded` resources and any metadata - is serialized up
+/// front, so a failure there is reported as a `500 Internal Server Error` problem as usual. The items are then
+/// serialized as they are streamed, in chunks of around 8 KiB, so the whole collection is never held in memory
+/// and the next chunk is only produced once the client has accepted the previous one.
+///
+/// The status code and headers have already been sent by the time the items are serialized, so a failure to
+/// serialize an item can't be reported to the client. Instead the error is logged and the connection is
+/// aborted, leaving the JSON document unterminated so that the client can tell the collection is incomplete.
+///
+/// This is created with `HalRespondable::with_streamed_embedded`.
+pub struct HalStreamingRespondable<T, I>
+where
+    T: Serialize,
+{
+    respondable: HalRespondable<T>,
+    name:        String,
+    items:       I,
+}
+
+impl<T> HalRespondable<T>
+where
+    T: Serialize,
+{
+    /// Stream the provided items as the embedded resources under the named relation, instead of buffering the
+    /// entire resource. Any resources already embedded under the same relation are replaced.
+    ///
+    /// # Parameters
+    /// - `name` - The name of the embedded relation
+    /// - `items` - The items to embed
+    pub fn with_streamed_embedded<S, C>(self, name: S, items: C) -> HalStreamingRespondable<T, C::IntoIter>
+    where
+        S: Into<String>,
+        C: IntoIterator,
+    {
+        HalStreamingRespondable {
+            respondable: self,
+            name:        name.into(),
+            items:       items.into_iter(),
+        }
+    }
+}
+
+/// Serialize everything in the response up to the start of the streamed array.
+///
+/// # Parameters
+/// - `name` - The name of the embedded relation being streamed
+/// - `respondable` - The respondable for the envelope of the resource
+///
+/// # Errors
+/// If the envelope fails to serialize, or isn't a JSON object
+fn prefix<T>(name: &str, respondable: HalRespondable<T>) -> serde_json::Result<Vec<u8>>
+where
+    T: Serialize,
+{
+    let omit_nulls = respondable.omits_nulls();
+    let envelope = HalRespondable::<T>::serialize_body(respondable.body(), omit_nulls)?;
+    let mut envelope: Map<String, Value> = serde_json::from_slice(&envelope)?;
+    let mut embedded = match envelope.remove("_embedded") {
+        Some(Value::Object(embedded)) => embedded,
+        _ => Map::new(),
+    };
+    embedded.remove(name);
+
+    let mut prefix = serialize_open(&envelope)?;
+    if !envelope.is_empty() {
+        prefix.push(b',');
+    }
+    prefix.extend_from_slice(br#""_embedded":"#);
+    prefix.extend(serialize_open(&embedded)?);
+    if !embedded.is_empty() {
+        prefix.push(b',');
+    }
+    serde_json::to_writer(&mut prefix, name)?;
+    prefix.extend_from_slice(b":[");
+
+    Ok(prefix)
+}
+
+/// Serialize a JSON object, leaving it open so that more entries can be added to it.
+///
+/// # Parameters
+/// - `object` - The object to serialize
+fn serialize_open(object: &Map<String, Value>) -> serde_json::Result<Vec<u8>> {
+    let mut serialized = serde_json::to_vec(object)?;
+    serialized.pop();
+
+    Ok(serialized)
+}
+
+impl<T, I> Responder for HalStreamingRespondable<T, I>
+where
+    T: Serialize,
+    I: Iterator + Unpin + 'static,
+    I::Item: Serialize,
+{
+    fn respond_to(mut self, req: &HttpRequest) -> HttpResponse {
+        self.respondable.prepare(req);
+        let media_type = self.respondable.media_types()[0];
+        let status_code = self.respondable.status_code();
+        let mut headers = self.respondable.headers();
+
+        let prefix = match prefix(&self.name, self.respondable) {
+            Ok(prefix) => prefix,
+            Err(e) => {
+                tracing::error!(e = ?e, "Failed to serialize HAL envelope");
+                return Problem::new(INTERNAL_SERVER_ERROR).into();
+            },
+        };
+
+        if !headers.contains_key(header::CONTENT_TYPE) {
+            headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(media_type));
+        }
+
+        let mut response = HttpResponse::build(status_code);
+
+        for (key, value) in headers {
+            response.append_header((key, value));
+        }
+
+        let response = response.streaming(EmbeddedStream {
+            prefix: Some(prefix),
+            items:  Some(self.items),
+            first:  true,
+            error:  None,
+        });
+
+        #[cfg(feature = "metrics")]
+        crate::response::metrics::record(&response);
+
+        response
+    }
+}
+
+/// Stream of the chunks of a `HalStreamingRespondable`.
+struct EmbeddedStream<I> {
+    /// The start of the document, before the first item, or `None` once it has been sent.
+    prefix: Option<Vec<u8>>,
+    /// The items still to serialize, or `None` once they are exhausted or have failed.
+    items:  Option<I>,
+    /// Whether the next item is the first one, and so isn't preceded by a comma.
+    first:  bool,
+    /// An error to report once the items before it have been sent.
+    error:  Option<serde_json::Error>,
+}
+
+impl<I> Stream for EmbeddedStream<I>
+where
+    I: Iterator + Unpin,
+    I::Item: Serialize,
+{
+    type Item = Result<Bytes, serde_json::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Some(e) = this.error.take() {
+            return Poll::Ready(Some(Err(e)));
+        }
+
+        let items = match &mut this.items {
+            Some(items) => items,
+            None => return Poll::Ready(None),
+        };
+
+        let mut chunk = this.prefix.take().unwrap_or_default();
+        while chunk.len() < FLUSH_SIZE {
+            let item = match items.next() {
+                Some(item) => item,
+                None => {
+                    chunk.extend_from_slice(b"]}}");
+                    this.items = None;
+                    break;
+                },
+            };
+
+            let item_start = chunk.len();
+            if !this.first {
+                chunk.push(b',');
+            }
+            if let Err(e) = serde_json::to_writer(&mut chunk, &item) {
+                tracing::error!(e = ?e, "Failed to serialize streamed HAL item, aborting response");
+                chunk.truncate(item_start);
+                this.items = None;
+                this.error = Some(e);
+                break;
+            }
+            this.first = false;
+        }
+
+        if chunk.is_empty() {
+            Poll::Ready(this.error.take().map(Err))
+        } else {
+            Poll::Ready(Some(Ok(Bytes::from(chunk))))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, task::Waker};
+
+    use assert2::{check, let_assert};
+    use serde_json::json;
+
+    use super::*;
+    use crate::hal::Link;
+
+    fn poll<I>(stream: &mut EmbeddedStream<I>) -> Option<Result<Bytes, serde_json::Error>>
+    where
+        I: Iterator + Unpin,
+        I::Item: Serialize,
+    {
+        let mut cx = Context::from_waker(Waker::noop());
+        let_assert!(Poll::Ready(item) = Pin::new(stream).poll_next(&mut cx));
+        item
+    }
+
+    fn stream<I>(respondable: HalStreamingRespondable<Value, I>) -> EmbeddedStream<I> {
+        EmbeddedStream {
+            prefix: Some(prefix(&respondable.name, respondable.respondable).unwrap()),
+            items:  Some(respondable.items),
+            first:  true,
+            error:  None,
+        }
+    }
+
+    fn collect<I>(mut stream: EmbeddedStream<I>) -> Vec<u8>
+    where
+        I: Iterator + Unpin,
+        I::Item: Serialize,
+    {
+        let mut body = vec![];
+        while let Some(chunk) = poll(&mut stream) {
+            body.extend_from_slice(&chunk.unwrap());
+        }
+        body
+    }
+
+    #[test]
+    fn streamed_items() {
+        let respondable = HalRespondable::new(json!({"total": 2}))
+            .with_link("self", Link::from("/users"))
+            .with_embedded("owner", json!({"id": 0}))
+            .with_streamed_embedded("items", vec![json!({"id": 1}), json!({"id": 2})]);
+        let body: Value = serde_json::from_slice(&collect(stream(respondable))).unwrap();
+
+        check!(
+            body == json!({
+                "total": 2,
+                "_links": {"self": {"href": "/users"}},
+                "_embedded": {
+                    "owner": {"id": 0},
+                    "items": [{"id": 1}, {"id": 2}]
+                }
+            })
+        );
+    }
+
+    #[test]
+    fn no_items() {
+        let respondable = HalRespondable::new(json!({})).with_streamed_embedded("items", Vec::<Value>::new());
+
+        check!(collect(stream(respondable)) == br#"{"_links":{},"_embedded":{"items":[]}}"#);
+    }
+
+    #[test]
+    fn chunked() {
+        let respondable =
+            HalRespondable::new(json!({})).with_streamed_embedded("items", (0..10_000).map(|id| json!({ "id": id })));
+        let mut stream = stream(respondable);
+
+        let mut chunks = 0;
+        let mut body = vec![];
+        while let Some(chunk) = poll(&mut stream) {
+            let chunk = chunk.unwrap();
+            check!(chunk.len() < FLUSH_SIZE + 64);
+            chunks += 1;
+            body.extend_from_slice(&chunk);
+        }
+
+        check!(chunks > 1);
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        check!(body["_embedded"]["items"].as_array().unwrap().len() == 10_000);
+    }
+
+    #[test]
+    fn failure_after_items() {
+        let good = HashMap::new();
+        let mut bad = HashMap::new();
+        bad.insert((1, 2), "Not a string key");
+
+        let respondable = HalRespondable::new(json!({})).with_streamed_embedded("items", vec![good, bad]);
+        let mut stream = stream(respondable);
+
+        let_assert!(Some(Ok(chunk)) = poll(&mut stream));
+        check!(chunk.as_ref() == br#"{"_links":{},"_embedded":{"items":[{}"#);
+        let_assert!(Some(Err(_)) = poll(&mut stream));
+        check!(poll(&mut stream).is_none());
+    }
+}
@@ -0,0 +1,221 @@
+use std::{
+    collections::BTreeMap,
+    fmt::{Display, Formatter},
+};
+
+/// Errors that can occur when expanding a URI Template.
+#[derive(Debug, PartialEq, Eq)]
+pub enum TemplateError {
+    /// An expression was opened with `{` but never closed.
+    UnclosedExpression,
+    /// An expression was not valid, e.g. it had an empty or malformed variable name.
+    InvalidExpression(String),
+}
+
+impl Display for TemplateError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TemplateError::UnclosedExpression => write!(f, "Unclosed expression in URI Template"),
+            TemplateError::InvalidExpression(expression) => {
+                write!(f, "Invalid expression in URI Template: {{{}}}", expression)
+            },
+        }
+    }
+}
+
+impl std::error::Error for TemplateError {}
+
+/// Expand a URI Template, as defined by RFC 6570, using the provided variables.
+///
+/// All of the operators are supported, along with the prefix (`:n`) and explode (`*`) modifiers. Variables
+/// that aren't provided are left in the template, so that it can be expanded further later:
+/// - For the `/`, `.`, `;`, `?` and `&` operators, the provided variables are expanded and the rest are kept
+///   in a new expression, so `{?a,b}` with only `a` becomes `?a=1{&b}`.
+/// - For the other operators, the expression is only expanded if every variable is provided. Otherwise it is
+///   kept unchanged.
+///
+/// # Parameters
+/// - `template` - The template to expand
+/// - `vars` - The variables to expand the template with
+///
+/// # Returns
+/// The expanded template, and whether it still contains any expressions
+pub(crate) fn expand(template: &str, vars: &BTreeMap<String, String>) -> Result<(String, bool), TemplateError> {
+    let mut result = String::with_capacity(template.len());
+    let mut templated = false;
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        result.push_str(&rest[..start]);
+
+        let end = rest[start..].find('}').ok_or(TemplateError::UnclosedExpression)? + start;
+        let (expanded, remaining) = expand_expression(&rest[start + 1..end], vars)?;
+        result.push_str(&expanded);
+        templated |= remaining;
+
+        rest = &rest[end + 1..];
+    }
+    result.push_str(rest);
+
+    Ok((result, templated))
+}
+
+/// Expand a single expression, without the surrounding braces.
+///
+/// # Returns
+/// The expansion, and whether it still contains an expression
+fn expand_expression(expression: &str, vars: &BTreeMap<String, String>) -> Result<(String, bool), TemplateError> {
+    let invalid = || TemplateError::InvalidExpression(expression.to_owned());
+
+    let (operator, specs) = match expression.chars().next() {
+        Some(c @ ('+' | '#' | '.' | '/' | ';' | '?' | '&')) => (Some(c), &expression[1..]),
+        Some(_) => (None, expression),
+        None => return Err(invalid()),
+    };
+
+    let mut defined = vec![];
+    let mut missing = vec![];
+    for spec in specs.split(',') {
+        let (name, prefix) = parse_spec(spec).ok_or_else(invalid)?;
+        match vars.get(name) {
+            Some(value) => defined.push((name, truncate(value, prefix))),
+            None => missing.push(spec),
+        }
+    }
+
+    let (first, separator, named, reserved) = match operator {
+        None => ("", ",", false, false),
+        Some('+') => ("", ",", false, true),
+        Some('#') => ("#", ",", false, true),
+        Some('.') => (".", ".", false, false),
+        Some('/') => ("/", "/", false, false),
+        Some(';') => (";", ";", true, false),
+        Some('?') => ("?", "&", true, false),
+        _ => ("&", "&", true, false),
+    };
+
+    let splittable = matches!(operator, Some('.' | '/' | ';' | '?' | '&'));
+    if !missing.is_empty() && (defined.is_empty() || !splittable) {
+        return Ok((format!("{{{}}}", expression), true));
+    }
+
+    let expanded: Vec<String> = defined
+        .into_iter()
+        .map(|(name, value)| {
+            let value = encode(value, reserved);
+            match (named, operator) {
+                (false, _) => value,
+                (true, Some(';')) if value.is_empty() => name.to_owned(),
+                (true, _) => format!("{}={}", name, value),
+            }
+        })
+        .collect();
+
+    let mut result = format!("{}{}", first, expanded.join(separator));
+    if missing.is_empty() {
+        return Ok((result, false));
+    }
+
+    let continuation = if operator == Some('?') { '&' } else { operator.unwrap_or_default() };
+    result.push_str(&format!("{{{}{}}}", continuation, missing.join(",")));
+    Ok((result, true))
+}
+
+/// Parse a variable specification into the variable name and the optional prefix length.
+fn parse_spec(spec: &str) -> Option<(&str, Option<usize>)> {
+    let (name, prefix) = match spec.split_once(':') {
+        Some((name, prefix)) => (name, Some(prefix.parse::<usize>().ok().filter(|p| *p > 0 && *p < 10000)?)),
+        None => (spec.strip_suffix('*').unwrap_or(spec), None),
+    };
+
+    let valid = !name.is_empty()
+        && name.split('.').all(|part| {
+            !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '%')
+        });
+
+    if valid {
+        Some((name, prefix))
+    } else {
+        None
+    }
+}
+
+/// Truncate a value to the provided number of characters, if any.
+fn truncate(value: &str, prefix: Option<usize>) -> &str {
+    match prefix.and_then(|prefix| value.char_indices().nth(prefix)) {
+        Some((index, _)) => &value[..index],
+        None => value,
+    }
+}
+
+/// Percent-encode a value for inclusion in an expanded URI Template.
+///
+/// # Parameters
+/// - `value` - The value to encode
+/// - `reserved` - Whether reserved characters are allowed through unencoded
+pub(crate) fn encode(value: &str, reserved: bool) -> String {
+    let mut result = String::with_capacity(value.len());
+
+    for byte in value.bytes() {
+        let c = byte as char;
+        if c.is_ascii_alphanumeric() || "-._~".contains(c) || (reserved && ":/?#[]@!$&'()*+,;=".contains(c)) {
+            result.push(c);
+        } else {
+            result.push_str(&format!("%{:02X}", byte));
+        }
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use assert2::check;
+
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> BTreeMap<String, String> {
+        pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()
+    }
+
+    #[test]
+    fn simple_expansion() {
+        let vars = vars(&[("id", "42"), ("name", "Hello World!")]);
+
+        check!(expand("/users/{id}", &vars) == Ok(("/users/42".to_owned(), false)));
+        check!(expand("/users/{name}", &vars) == Ok(("/users/Hello%20World%21".to_owned(), false)));
+        check!(expand("/{+name}", &vars) == Ok(("/Hello%20World!".to_owned(), false)));
+        check!(expand("/users/{name:5}", &vars) == Ok(("/users/Hello".to_owned(), false)));
+    }
+
+    #[test]
+    fn operator_expansion() {
+        let vars = vars(&[("id", "42"), ("page", "2"), ("size", "10"), ("empty", "")]);
+
+        check!(expand("/users{/id}", &vars) == Ok(("/users/42".to_owned(), false)));
+        check!(expand("/users{?page,size}", &vars) == Ok(("/users?page=2&size=10".to_owned(), false)));
+        check!(expand("/users?a=b{&page}", &vars) == Ok(("/users?a=b&page=2".to_owned(), false)));
+        check!(expand("/users{;id,empty}", &vars) == Ok(("/users;id=42;empty".to_owned(), false)));
+        check!(expand("/file{.size}", &vars) == Ok(("/file.10".to_owned(), false)));
+        check!(expand("/users{#id}", &vars) == Ok(("/users#42".to_owned(), false)));
+    }
+
+    #[test]
+    fn partial_expansion() {
+        let vars = vars(&[("page", "2"), ("id", "42")]);
+
+        check!(expand("/users{?page,size}", &vars) == Ok(("/users?page=2{&size}".to_owned(), true)));
+        check!(expand("/users{?size}", &vars) == Ok(("/users{?size}".to_owned(), true)));
+        check!(expand("/users{/id,sub}", &vars) == Ok(("/users/42{/sub}".to_owned(), true)));
+        check!(expand("/users/{id,other}", &vars) == Ok(("/users/{id,other}".to_owned(), true)));
+    }
+
+    #[test]
+    fn invalid_templates() {
+        let vars = vars(&[]);
+
+        check!(expand("/users/{id", &vars) == Err(TemplateError::UnclosedExpression));
+        check!(expand("/users/{}", &vars) == Err(TemplateError::InvalidExpression("".to_owned())));
+        check!(expand("/users/{a b}", &vars) == Err(TemplateError::InvalidExpression("a b".to_owned())));
+    }
+}
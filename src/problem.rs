@@ -1,6 +1,10 @@
+mod internal;
 mod model;
+mod payload;
 mod response;
 mod types;
 
+pub use internal::*;
 pub use model::*;
+pub use payload::*;
 pub use types::*;
@@ -0,0 +1,126 @@
+use std::{
+    backtrace::Backtrace,
+    error::Error,
+    fmt::{Debug, Display, Formatter},
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use actix_web::{error::ResponseError, http::StatusCode, HttpResponse};
+use uuid::Uuid;
+
+use super::{Problem, INTERNAL_SERVER_ERROR};
+
+/// Whether a backtrace is captured and logged for internal errors.
+static CAPTURE_BACKTRACES: AtomicBool = AtomicBool::new(false);
+
+/// Specify whether a backtrace is captured and logged when an internal error is converted into a problem.
+/// Defaults to `false`, since capturing a backtrace is expensive. The backtrace is only ever logged, and never
+/// included in the problem sent to the client.
+///
+/// # Parameters
+/// - `capture` - Whether to capture backtraces
+pub fn capture_error_backtraces(capture: bool) {
+    CAPTURE_BACKTRACES.store(capture, Ordering::Relaxed);
+}
+
+impl Problem {
+    /// Create a new `500 Internal Server Error` Problem instance for an unexpected error.
+    ///
+    /// A new error ID is generated, and the full error is logged along with it - and with a backtrace if
+    /// enabled with `capture_error_backtraces`. The problem carries only the error ID, in the `error_id`
+    /// member, so that it can be correlated with the logs without exposing any details of the error.
+    ///
+    /// # Parameters
+    /// - `error` - The error that occurred
+    ///
+    /// # Returns
+    /// The problem
+    pub fn internal_error<E>(error: &E) -> Self
+    where
+        E: Error,
+    {
+        let error_id = Uuid::new_v4().to_string();
+
+        if CAPTURE_BACKTRACES.load(Ordering::Relaxed) {
+            let backtrace = Backtrace::force_capture();
+            tracing::error!(error_id = %error_id, e = ?error, backtrace = %backtrace, "Internal error");
+        } else {
+            tracing::error!(error_id = %error_id, e = ?error, "Internal error");
+        }
+
+        Problem::new(INTERNAL_SERVER_ERROR).with_extra("error_id", error_id)
+    }
+}
+
+/// Wrapper around any error that responds with a `500 Internal Server Error` problem, as produced by
+/// `Problem::internal_error`. This allows handlers to return `Result<T, InternalError<E>>` and use `?` on any
+/// errors that are unexpected.
+///
+/// # Types
+/// - `E` - The type of error to wrap
+pub struct InternalError<E>(pub E);
+
+impl<E> From<E> for InternalError<E>
+where
+    E: Error,
+{
+    fn from(error: E) -> Self {
+        Self(error)
+    }
+}
+
+impl<E> Debug for InternalError<E>
+where
+    E: Debug,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Debug::fmt(&self.0, f)
+    }
+}
+
+impl<E> Display for InternalError<E>
+where
+    E: Display,
+{
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(&self.0, f)
+    }
+}
+
+impl<E> ResponseError for InternalError<E>
+where
+    E: Error,
+{
+    fn status_code(&self) -> StatusCode {
+        StatusCode::INTERNAL_SERVER_ERROR
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        Problem::internal_error(&self.0).into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert2::{check, let_assert};
+
+    use super::*;
+
+    #[test]
+    fn internal_error_has_id() {
+        let error = std::io::Error::other("Database exploded");
+        let problem = Problem::internal_error(&error);
+
+        check!(problem.status == StatusCode::INTERNAL_SERVER_ERROR);
+        check!(problem.detail == None);
+        let_assert!(Some(serde_json::Value::String(error_id)) = problem.extra.get("error_id"));
+        check!(Uuid::parse_str(error_id).is_ok());
+    }
+
+    #[test]
+    fn internal_error_response() {
+        let error: InternalError<std::io::Error> = std::io::Error::other("Database exploded").into();
+
+        check!(error.error_response().status() == StatusCode::INTERNAL_SERVER_ERROR);
+    }
+}
@@ -19,6 +19,53 @@ pub trait ProblemTypeStatus {
     fn status_code(&self) -> StatusCode;
 }
 
+/// Errors that can occur with the type URI of a problem.
+#[derive(Debug, PartialEq)]
+pub enum ProblemTypeError {
+    /// The type URI was empty.
+    Empty,
+    /// The type URI was not an absolute URI, e.g. it had no scheme or contained whitespace.
+    NotAbsolute(String),
+}
+
+impl Display for ProblemTypeError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProblemTypeError::Empty => write!(f, "Problem type URI is empty"),
+            ProblemTypeError::NotAbsolute(uri) => write!(f, "Problem type URI is not an absolute URI: {}", uri),
+        }
+    }
+}
+
+impl std::error::Error for ProblemTypeError {}
+
+/// Check that a problem type URI is an absolute URI, i.e. that it starts with a scheme and contains no
+/// whitespace.
+///
+/// # Parameters
+/// - `uri` - The type URI to check
+pub(crate) fn validate_type_uri(uri: &str) -> Result<(), ProblemTypeError> {
+    if uri.is_empty() {
+        return Err(ProblemTypeError::Empty);
+    }
+
+    let valid = match uri.split_once(':') {
+        Some((scheme, rest)) => {
+            scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+                && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c))
+                && !rest.is_empty()
+                && !uri.contains(char::is_whitespace)
+        },
+        None => false,
+    };
+
+    if valid {
+        Ok(())
+    } else {
+        Err(ProblemTypeError::NotAbsolute(uri.to_owned()))
+    }
+}
+
 /// Representation of an RFC-7807 Problem.
 #[derive(Debug)]
 pub struct Problem {
@@ -26,6 +73,8 @@ pub struct Problem {
     pub error:    Box<dyn ProblemType>,
     /// The HTTP Status code to use
     pub status:   StatusCode,
+    /// A type URI to use instead of the one from the error
+    pub type_uri: Option<String>,
     /// An additional detail message
     pub detail:   Option<String>,
     /// An additional instance subtype
@@ -56,6 +105,7 @@ impl Problem {
         Self {
             error: Box::new(error),
             status,
+            type_uri: None,
             detail: None,
             instance: None,
             extra: HashMap::new(),
@@ -107,6 +157,26 @@ impl Problem {
         }
     }
 
+    /// Set the Type URI of the Problem instance, overriding the one from the error
+    ///
+    /// # Parameters
+    /// - `type_uri` - The type URI to use. This must be an absolute URI, e.g. `https://example.com/problems/1`
+    ///
+    /// # Errors
+    /// If the type URI is empty or not an absolute URI
+    pub fn with_type_uri<S>(self, type_uri: S) -> Result<Self, ProblemTypeError>
+    where
+        S: Into<String>,
+    {
+        let type_uri = type_uri.into();
+        validate_type_uri(&type_uri)?;
+
+        Ok(Self {
+            type_uri: Some(type_uri),
+            ..self
+        })
+    }
+
     /// Set some extra data on the Problem instance
     ///
     /// # Parameters
@@ -188,7 +258,22 @@ mod tests {
         check!(Some("Some Detail".to_owned()) == problem.detail);
         check!(Some("Some Instance".to_owned()) == problem.instance);
         check!(2 == problem.extra.len());
-        check!(Some(&serde_json::to_value("Some Value").unwrap()) == problem.extra.get(&"some_key".to_owned()));
-        check!(Some(&serde_json::to_value(42).unwrap()) == problem.extra.get(&"other_key".to_owned()));
+        check!(Some(&serde_json::to_value("Some Value").unwrap()) == problem.extra.get("some_key"));
+        check!(Some(&serde_json::to_value(42).unwrap()) == problem.extra.get("other_key"));
+    }
+
+    #[test]
+    fn test_type_uri() {
+        let problem = Problem::new(ProblemDetails::SomeProblem)
+            .with_type_uri("https://example.com/problems/some")
+            .unwrap();
+        check!(Some("https://example.com/problems/some".to_owned()) == problem.type_uri);
+
+        check!(validate_type_uri("about:blank") == Ok(()));
+        check!(validate_type_uri("tag:mundus,2021:some/problem") == Ok(()));
+        check!(validate_type_uri("") == Err(ProblemTypeError::Empty));
+        check!(validate_type_uri("/problems/some") == Err(ProblemTypeError::NotAbsolute("/problems/some".to_owned())));
+        check!(validate_type_uri("1http:x").is_err());
+        check!(validate_type_uri("https://example.com/some problem").is_err());
     }
 }
@@ -0,0 +1,175 @@
+use actix_http::{error::PayloadError, http::header};
+use actix_web::{
+    error::{JsonPayloadError, UrlencodedError},
+    Error, HttpRequest,
+};
+
+use super::{Problem, EXPECTATION_FAILED, PAYLOAD_TOO_LARGE};
+
+impl Problem {
+    /// Create a new `413 Payload Too Large` Problem instance for a request body that was larger than allowed.
+    ///
+    /// # Parameters
+    /// - `limit` - The maximum size of the request body in bytes, if known. This is included in the detail
+    ///
+    /// # Returns
+    /// The problem
+    pub fn payload_too_large(limit: Option<usize>) -> Self {
+        let problem = Problem::new(PAYLOAD_TOO_LARGE);
+
+        match limit {
+            Some(limit) => problem
+                .with_detail(format!("The request body must be no larger than {} bytes", limit))
+                .with_extra("limit", limit),
+            None => problem.with_detail("The request body is larger than allowed"),
+        }
+    }
+
+    /// Create a new `417 Expectation Failed` Problem instance for a request with an `Expect` header that can't be
+    /// met.
+    ///
+    /// # Parameters
+    /// - `expectation` - The value of the `Expect` header. This is included in the detail
+    ///
+    /// # Returns
+    /// The problem
+    pub fn expectation_failed(expectation: &str) -> Self {
+        Problem::new(EXPECTATION_FAILED)
+            .with_detail(format!("The expectation {} is not supported", expectation))
+            .with_extra("expectation", expectation)
+    }
+}
+
+/// Determine whether an upload should be rejected before its body is read, based only on its headers, e.g. for a
+/// handler that takes a `web::Payload` and processes it as a stream.
+///
+/// An `Expect` header other than `100-continue` gives a `417 Expectation Failed` problem, and a `Content-Length`
+/// larger than the limit gives a `413 Payload Too Large` problem. Uploads without a `Content-Length` aren't
+/// rejected, so the limit must still be enforced while reading the body.
+///
+/// Actix acknowledges `Expect: 100-continue` itself, sending `100 Continue` as soon as the request headers have
+/// been received and before the handler runs, so a rejection from a handler doesn't stop the client from sending
+/// the body. Returning the problem without reading the payload does mean the body is never processed, and the
+/// connection is closed rather than reused. Rejecting before `100 Continue` is sent needs an `expect` service on
+/// the underlying `actix_http::HttpService`, which can return the problem as its error.
+///
+/// # Parameters
+/// - `req` - The upload request
+/// - `limit` - The maximum size of the request body in bytes
+///
+/// # Returns
+/// The problem to respond with if the upload is rejected, or `None` if it can be read
+pub fn upload_rejection(req: &HttpRequest, limit: usize) -> Option<Problem> {
+    if let Some(expect) = req.headers().get(header::EXPECT) {
+        if !expect.as_bytes().eq_ignore_ascii_case(b"100-continue") {
+            return Some(Problem::expectation_failed(expect.to_str().unwrap_or_default()));
+        }
+    }
+
+    let length = req
+        .headers()
+        .get(header::CONTENT_LENGTH)
+        .and_then(|length| length.to_str().ok())
+        .and_then(|length| length.parse::<usize>().ok());
+    match length {
+        Some(length) if length > limit => Some(Problem::payload_too_large(Some(limit))),
+        _ => None,
+    }
+}
+
+/// Error handler for `JsonConfig` that responds to request bodies that are too large with a `413 Payload Too
+/// Large` problem. Every other error is left for actix to handle as normal.
+///
+/// This is wired up as the error handler of the JSON extractor:
+///
+/// ```
+/// use actix_web::{web, App};
+/// use sazzer_actix_response::problem::json_payload_error_handler;
+///
+/// let app = App::new().app_data(web::JsonConfig::default().limit(4096).error_handler(json_payload_error_handler));
+/// ```
+///
+/// # Parameters
+/// - `err` - The error extracting the JSON body
+/// - `_req` - The request being handled
+pub fn json_payload_error_handler(err: JsonPayloadError, _req: &HttpRequest) -> Error {
+    match err {
+        JsonPayloadError::OverflowKnownLength { limit, .. } | JsonPayloadError::Overflow { limit } => {
+            Problem::payload_too_large(Some(limit)).into()
+        },
+        JsonPayloadError::Payload(PayloadError::Overflow) => Problem::payload_too_large(None).into(),
+        err => err.into(),
+    }
+}
+
+/// Error handler for `FormConfig` that responds to request bodies that are too large with a `413 Payload Too
+/// Large` problem. Every other error is left for actix to handle as normal.
+///
+/// This is wired up as the error handler of the form extractor:
+///
+/// ```
+/// use actix_web::{web, App};
+/// use sazzer_actix_response::problem::form_payload_error_handler;
+///
+/// let app = App::new().app_data(web::FormConfig::default().limit(4096).error_handler(form_payload_error_handler));
+/// ```
+///
+/// # Parameters
+/// - `err` - The error extracting the form body
+/// - `_req` - The request being handled
+pub fn form_payload_error_handler(err: UrlencodedError, _req: &HttpRequest) -> Error {
+    match err {
+        UrlencodedError::Overflow { limit, .. } => Problem::payload_too_large(Some(limit)).into(),
+        UrlencodedError::Payload(PayloadError::Overflow) => Problem::payload_too_large(None).into(),
+        err => err.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::http::{header, StatusCode};
+    use actix_web::test::TestRequest;
+    use assert2::check;
+
+    use super::*;
+
+    #[test]
+    fn json_payload_too_large() {
+        let req = TestRequest::default().to_http_request();
+        let error = json_payload_error_handler(JsonPayloadError::OverflowKnownLength { length: 20, limit: 10 }, &req);
+        let response = error.error_response();
+
+        check!(response.status() == StatusCode::PAYLOAD_TOO_LARGE);
+        check!(response.headers().get(header::CONTENT_TYPE).unwrap() == "application/problem+json");
+    }
+
+    #[test]
+    fn other_json_errors_untouched() {
+        let req = TestRequest::default().to_http_request();
+        let error = json_payload_error_handler(JsonPayloadError::ContentType, &req);
+
+        check!(error.error_response().status() == StatusCode::BAD_REQUEST);
+    }
+
+    #[test]
+    fn upload_checks() {
+        let upload = |expect: &str, length: &str| {
+            let req = TestRequest::post()
+                .insert_header((header::EXPECT, expect))
+                .insert_header((header::CONTENT_LENGTH, length))
+                .to_http_request();
+            upload_rejection(&req, 1024).map(|problem| problem.status)
+        };
+
+        check!(upload("100-continue", "1024") == None);
+        check!(upload("100-continue", "1025") == Some(StatusCode::PAYLOAD_TOO_LARGE));
+        check!(upload("200-ok", "10") == Some(StatusCode::EXPECTATION_FAILED));
+    }
+
+    #[test]
+    fn payload_too_large_detail() {
+        let problem = Problem::payload_too_large(Some(4096));
+
+        check!(problem.detail == Some("The request body must be no larger than 4096 bytes".to_owned()));
+    }
+}
@@ -2,17 +2,18 @@ use std::collections::HashMap;
 
 use actix_web::{
     error::ResponseError,
-    http::{header, StatusCode},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
     HttpRequest, HttpResponse, Responder,
 };
 use serde::Serialize;
 use serde_json::Value;
 
-use super::Problem;
+use super::{model::validate_type_uri, Problem};
+use crate::response::Respondable;
 
 /// HTTP representation of an RFC-7807 Problem response.
 #[derive(Serialize)]
-struct ProblemModel {
+pub struct ProblemModel {
     /// The type code for the problem
     pub r#type:   String,
     /// The title string for the problem
@@ -30,20 +31,41 @@ struct ProblemModel {
     pub extra:    HashMap<String, Value>,
 }
 
-impl From<&Problem> for HttpResponse {
+impl From<&Problem> for ProblemModel {
     fn from(problem: &Problem) -> Self {
-        let body = ProblemModel {
-            r#type:   problem.error.problem_type().to_owned(),
-            title:    problem.error.to_string(),
+        let mut problem_type = problem
+            .type_uri
+            .clone()
+            .unwrap_or_else(|| problem.error.problem_type().to_owned());
+        let mut title = problem.error.to_string();
+
+        if let Err(e) = validate_type_uri(&problem_type) {
+            tracing::error!(e = ?e, "Invalid problem type URI, using about:blank instead");
+            problem_type = "about:blank".to_owned();
+            title.clear();
+        }
+
+        // RFC 7807 says that problems of type about:blank should be titled with the reason phrase of the status.
+        if problem_type == "about:blank" && title.is_empty() {
+            title = problem.status.canonical_reason().unwrap_or_default().to_owned();
+        }
+
+        Self {
+            r#type:   problem_type,
+            title,
             status:   problem.status.as_u16(),
             detail:   problem.detail.clone(),
             instance: problem.instance.clone(),
             extra:    problem.extra.clone(),
-        };
+        }
+    }
+}
 
+impl From<&Problem> for HttpResponse {
+    fn from(problem: &Problem) -> Self {
         Self::build(problem.status)
             .append_header((header::CONTENT_TYPE, "application/problem+json"))
-            .json(body)
+            .json(ProblemModel::from(problem))
     }
 }
 
@@ -55,7 +77,34 @@ impl From<Problem> for HttpResponse {
 
 impl Responder for Problem {
     fn respond_to(self, _req: &HttpRequest) -> HttpResponse {
-        self.into()
+        let response = self.into();
+
+        #[cfg(feature = "metrics")]
+        crate::response::metrics::record(&response);
+
+        response
+    }
+}
+
+impl Respondable for Problem {
+    type Body = ProblemModel;
+
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn headers(&self) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/problem+json"));
+        headers
+    }
+
+    fn media_types(&self) -> Vec<&'static str> {
+        vec!["application/problem+json"]
+    }
+
+    fn body(self) -> Self::Body {
+        ProblemModel::from(&self)
     }
 }
 
@@ -65,6 +114,11 @@ impl ResponseError for Problem {
     }
 
     fn error_response(&self) -> HttpResponse {
-        self.into()
+        let response = self.into();
+
+        #[cfg(feature = "metrics")]
+        crate::response::metrics::record(&response);
+
+        response
     }
 }
@@ -1,9 +1,85 @@
-use std::fmt::{Display, Formatter};
+use std::{
+    fmt::{Display, Formatter},
+    sync::RwLock,
+};
 
 use actix_http::http::StatusCode;
 
 use super::{Problem, ProblemType, ProblemTypeStatus};
 
+/// The base URI for the type of problems created from a status code.
+static STATUS_TYPE_BASE: RwLock<Option<String>> = RwLock::new(None);
+
+/// Specify the base URI for the type of problems created by `Problem::for_status`.
+///
+/// The type of each problem is this base followed by the status code, e.g. a base of
+/// `https://example.com/problems/` gives a type of `https://example.com/problems/404`. With no base
+/// configured, the type of the problems is `about:blank`.
+///
+/// # Parameters
+/// - `base` - The base URI, or `None` to use `about:blank`
+pub fn set_status_problem_type_base<S>(base: Option<S>)
+where
+    S: Into<String>,
+{
+    *STATUS_TYPE_BASE.write().unwrap_or_else(|e| e.into_inner()) = base.map(Into::into);
+}
+
+/// Problem type derived entirely from an HTTP status code.
+#[derive(Debug)]
+pub struct StatusProblemType(pub StatusCode);
+
+impl ProblemType for StatusProblemType {
+    /// Determine the value to use for the problem type.
+    fn problem_type(&self) -> &'static str {
+        "about:blank"
+    }
+}
+
+impl ProblemTypeStatus for StatusProblemType {
+    /// Determine the status code for the problem.
+    fn status_code(&self) -> StatusCode {
+        self.0
+    }
+}
+
+impl Display for StatusProblemType {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0.canonical_reason().unwrap_or("Unknown Status"))
+    }
+}
+
+impl Problem {
+    /// Create a new Problem instance derived entirely from an HTTP status code.
+    ///
+    /// The title is the canonical reason phrase of the status code, and the type is built from the base
+    /// configured with `set_status_problem_type_base`.
+    ///
+    /// # Parameters
+    /// - `status` - The HTTP Status code
+    ///
+    /// # Returns
+    /// The problem
+    pub fn for_status(status: StatusCode) -> Self {
+        let base = STATUS_TYPE_BASE.read().unwrap_or_else(|e| e.into_inner());
+
+        Self::for_status_with_base(status, base.as_deref())
+    }
+
+    /// Create a new Problem instance derived entirely from an HTTP status code, with the type built from the
+    /// provided base instead of the configured one.
+    ///
+    /// # Parameters
+    /// - `status` - The HTTP Status code
+    /// - `base` - The base URI of the type, or `None` to use `about:blank`
+    fn for_status_with_base(status: StatusCode, base: Option<&str>) -> Self {
+        Self {
+            type_uri: base.map(|base| format!("{}{}", base, status.as_u16())),
+            ..Self::new(StatusProblemType(status))
+        }
+    }
+}
+
 /// A simple representation of a problem type.
 #[derive(Debug)]
 pub struct SimpleProblemType {
@@ -69,6 +145,27 @@ pub const BAD_REQUEST: SimpleProblemType = SimpleProblemType {
     status_code:   StatusCode::BAD_REQUEST,
 };
 
+/// Problem to indicate that none of the media types accepted by the client can be produced.
+pub const NOT_ACCEPTABLE: SimpleProblemType = SimpleProblemType {
+    problem_type:  "about:blank",
+    problem_title: "Not Acceptable",
+    status_code:   StatusCode::NOT_ACCEPTABLE,
+};
+
+/// Problem to indicate that the body of a request was larger than allowed.
+pub const PAYLOAD_TOO_LARGE: SimpleProblemType = SimpleProblemType {
+    problem_type:  "about:blank",
+    problem_title: "Payload Too Large",
+    status_code:   StatusCode::PAYLOAD_TOO_LARGE,
+};
+
+/// Problem to indicate that an expectation in the `Expect` header of a request can't be met.
+pub const EXPECTATION_FAILED: SimpleProblemType = SimpleProblemType {
+    problem_type:  "about:blank",
+    problem_title: "Expectation Failed",
+    status_code:   StatusCode::EXPECTATION_FAILED,
+};
+
 /// Problem to indicate that a request was a valid request but wasn't processable for this request.
 pub const UNPROCESSABLE_ENTITY: SimpleProblemType = SimpleProblemType {
     problem_type:  "about:blank",
@@ -82,3 +179,24 @@ pub const INTERNAL_SERVER_ERROR: SimpleProblemType = SimpleProblemType {
     problem_title: "Internal Server Error",
     status_code:   StatusCode::INTERNAL_SERVER_ERROR,
 };
+
+#[cfg(test)]
+mod tests {
+    use assert2::check;
+
+    use super::*;
+
+    #[test]
+    fn problem_for_status() {
+        let problem = Problem::for_status_with_base(StatusCode::NOT_FOUND, None);
+
+        check!(problem.status == StatusCode::NOT_FOUND);
+        check!(problem.error.to_string() == "Not Found");
+        check!(problem.error.problem_type() == "about:blank");
+        check!(problem.type_uri == None);
+
+        let problem = Problem::for_status_with_base(StatusCode::NOT_FOUND, Some("https://example.com/problems/"));
+
+        check!(problem.type_uri == Some("https://example.com/problems/404".to_owned()));
+    }
+}
@@ -1,19 +1,185 @@
+mod batch;
+mod bytes;
+mod cached;
+pub(crate) mod conditional;
+mod cors;
+mod csv;
+mod decimal;
+#[cfg(feature = "content-digest")]
+pub(crate) mod digest;
+mod empty;
+#[cfg(feature = "testing")]
+mod example;
+#[cfg(feature = "tokio")]
+mod file;
+mod html;
+pub(crate) mod json;
+#[cfg(feature = "metrics")]
+pub(crate) mod metrics;
+mod ndjson;
+mod negotiation;
+#[cfg(feature = "prost")]
+mod protobuf;
+mod raw;
+mod redacted;
 mod respondable;
+mod result;
+#[cfg(feature = "jsonschema")]
+mod schema;
 mod simple;
+#[cfg(feature = "serde_yaml")]
+mod yaml;
 
-use actix_web::{HttpRequest, HttpResponse, Responder};
+use std::{
+    sync::atomic::{AtomicBool, Ordering},
+    time::SystemTime,
+};
+
+use actix_http::http::{
+    header::{self, HttpDate, IntoHeaderValue},
+    HeaderValue, StatusCode,
+};
+use actix_web::{dev::Extensions, HttpRequest, HttpResponse, HttpResponseBuilder, Responder};
+pub use batch::*;
+pub use bytes::*;
+pub use cached::*;
+pub use cors::*;
+pub use csv::*;
+#[cfg(feature = "content-digest")]
+pub use digest::DigestAlgorithm;
+pub use empty::*;
+#[cfg(feature = "testing")]
+pub use example::*;
+#[cfg(feature = "tokio")]
+pub use file::*;
+pub use html::*;
+pub use json::reuse_serialization_buffers;
+#[cfg(feature = "metrics")]
+pub use metrics::RESPONSES_COUNTER;
+pub use ndjson::*;
+pub use negotiation::*;
+#[cfg(feature = "prost")]
+pub use protobuf::*;
+pub use raw::*;
+pub use redacted::*;
 pub use respondable::*;
+pub use result::*;
+#[cfg(feature = "jsonschema")]
+pub use schema::*;
 use serde::Serialize;
 pub use simple::*;
 
+use crate::problem::{Problem, BAD_REQUEST, INTERNAL_SERVER_ERROR, NOT_ACCEPTABLE};
+
+/// Hook that is able to customise the `HttpResponseBuilder` for a response.
+type BuilderHook = Box<dyn FnOnce(&mut HttpResponseBuilder)>;
+
 /// Wrapper for any HTTP Response, implementing the standard requirements.
 ///
 /// # Types
 /// - `R` - The exact type of `Respondable` to wrap.
-pub struct Response<R>(pub R)
+pub struct Response<R>
+where
+    R: Respondable,
+    R::Body: Serialize,
+{
+    respondable:  R,
+    builder_hook: Option<BuilderHook>,
+    extensions:   Extensions,
+    decimal:      bool,
+    #[cfg(feature = "content-digest")]
+    digests:      Vec<digest::DigestAlgorithm>,
+}
+
+impl<R> Response<R>
 where
     R: Respondable,
-    R::Body: Serialize;
+    R::Body: Serialize,
+{
+    /// Create a new response wrapping the provided respondable.
+    ///
+    /// # Parameters
+    /// - `respondable` - The respondable to wrap
+    pub fn new(respondable: R) -> Self {
+        Self {
+            respondable,
+            builder_hook: None,
+            extensions: Extensions::new(),
+            decimal: false,
+            #[cfg(feature = "content-digest")]
+            digests: vec![],
+        }
+    }
+
+    /// Specify a hook to customise the `HttpResponseBuilder` used to build the final response.
+    ///
+    /// The hook runs after the status code and all of the headers from the respondable have been set, so it
+    /// can override them, and immediately before the body is attached. It does not run if the response is
+    /// replaced by a problem, e.g. when content negotiation fails or the body fails to serialize.
+    ///
+    /// # Parameters
+    /// - `hook` - The hook to run
+    pub fn with_builder_hook<F>(mut self, hook: F) -> Self
+    where
+        F: FnOnce(&mut HttpResponseBuilder) + 'static,
+    {
+        self.builder_hook = Some(Box::new(hook));
+        self
+    }
+
+    /// Specify a value to store in the extensions of the final `HttpResponse`, e.g. for middleware to read.
+    ///
+    /// Values are keyed by their type, so values of different types all coexist and a later value replaces an
+    /// earlier one of the same type. Since extensions are type-erased, the value must be `'static`. The
+    /// extensions are stored even if the response is replaced by a problem.
+    ///
+    /// # Parameters
+    /// - `value` - The value to store
+    pub fn with_extension<V>(mut self, value: V) -> Self
+    where
+        V: 'static,
+    {
+        self.extensions.insert(value);
+        self
+    }
+
+    /// Specify whether floats in a JSON body are always written in decimal notation, e.g. `0.0000001`, rather than
+    /// in the scientific notation that is used by default for very large and very small numbers, e.g. `1e-7`.
+    /// Defaults to `false`.
+    ///
+    /// Numbers are rewritten after serialization as the shortest decimal that parses back to exactly the same
+    /// value, so nothing is rounded, but very large and very small magnitudes become very long. Floats that are
+    /// whole numbers keep a trailing `.0`. Bodies in media types other than JSON are untouched.
+    ///
+    /// # Parameters
+    /// - `decimal` - Whether to write floats in decimal notation
+    pub fn with_decimal_floats(mut self, decimal: bool) -> Self {
+        self.decimal = decimal;
+        self
+    }
+
+    /// Add a `Content-Digest` header to the response, as defined by RFC 9530, containing the digest of the
+    /// serialized body computed with the provided algorithm. Calling this again with other algorithms includes
+    /// the digest computed with each of them.
+    ///
+    /// The digest is of the body exactly as sent, so it reflects the negotiated media type. Responses without a
+    /// body don't get the header at all.
+    ///
+    /// # Parameters
+    /// - `algorithm` - The algorithm to compute the digest with
+    #[cfg(feature = "content-digest")]
+    pub fn with_content_digest(mut self, algorithm: DigestAlgorithm) -> Self {
+        if !self.digests.contains(&algorithm) {
+            self.digests.push(algorithm);
+        }
+        self
+    }
+
+    /// Get the respondable wrapped by this response.
+    pub fn into_inner(self) -> R {
+        self.respondable
+    }
+}
 
 impl<R> From<R> for Response<R>
 where
@@ -21,7 +187,7 @@ where
     R::Body: Serialize,
 {
     fn from(respondable: R) -> Self {
-        Self(respondable)
+        Self::new(respondable)
     }
 }
 
@@ -30,13 +196,320 @@ where
     R: Respondable,
     R::Body: Serialize,
 {
-    fn respond_to(self, _req: &HttpRequest) -> HttpResponse {
-        let mut response = HttpResponse::build(self.0.status_code());
+    fn respond_to(mut self, req: &HttpRequest) -> HttpResponse {
+        self.respondable.prepare(req);
+        let extensions = std::mem::replace(&mut self.extensions, Extensions::new());
+        let mut response = self.respond(req);
+        response.extensions_mut().extend(extensions);
+
+        #[cfg(feature = "metrics")]
+        metrics::record(&response);
+
+        response
+    }
+}
+
+impl<R> Response<R>
+where
+    R: Respondable,
+    R::Body: Serialize,
+{
+    /// Negotiate the media type to respond with for the request, and then build the HTTP response.
+    ///
+    /// # Parameters
+    /// - `req` - The request being responded to
+    fn respond(self, req: &HttpRequest) -> HttpResponse {
+        #[allow(unused_mut)]
+        let mut media_types = self.respondable.media_types();
+        #[cfg(feature = "serde_yaml")]
+        if yaml::is_available(&media_types) {
+            media_types.push(yaml::MEDIA_TYPE);
+        }
+
+        let format = negotiation::format_parameter();
+        let media_type = match negotiation::format_override(req, &media_types, format.as_ref()) {
+            Some(negotiation::FormatOverride::Selected(media_type)) => media_type,
+            Some(negotiation::FormatOverride::Unknown(format)) => {
+                return Problem::new(BAD_REQUEST)
+                    .with_detail(format!("Unknown format {}", format))
+                    .with_extra("available", media_types)
+                    .into();
+            },
+            None => match negotiate(req, &media_types) {
+                Some(media_type) => media_type,
+                None if self.respondable.negotiation_mode() == NegotiationMode::NotAcceptable => {
+                    return Problem::new(NOT_ACCEPTABLE).with_extra("available", media_types).into();
+                },
+                None => media_types[0],
+            },
+        };
+
+        self.build(media_type, media_types[0])
+    }
+
+    /// Build the HTTP response directly, without needing the request it is a response to.
+    ///
+    /// This always uses the default media type of the respondable, and skips anything else that depends on the
+    /// request, e.g. content negotiation and conditional requests. It is useful for code running outside of the
+    /// normal `Responder` flow, such as middleware and error handlers.
+    pub fn into_http_response(mut self) -> HttpResponse {
+        let extensions = std::mem::replace(&mut self.extensions, Extensions::new());
+        let media_type = self.respondable.media_types()[0];
+        let mut response = self.build(media_type, media_type);
+        response.extensions_mut().extend(extensions);
+
+        #[cfg(feature = "metrics")]
+        metrics::record(&response);
+
+        response
+    }
+
+    /// Build the HTTP response once the media type has been decided.
+    ///
+    /// # Parameters
+    /// - `media_type` - The media type to respond with
+    /// - `default_media_type` - The default media type of the respondable
+    fn build(self, media_type: &'static str, default_media_type: &'static str) -> HttpResponse {
+        let has_body = self.respondable.has_body();
+        let omit_nulls = self.respondable.omits_nulls();
+        let (status_code, mut headers, body) = self.respondable.into_parts();
+
+        let has_body = if has_body && is_bodyless(status_code) {
+            if STRICT_BODYLESS_STATUSES.load(Ordering::Relaxed) {
+                tracing::error!(status = ?status_code, "Response has a body but the status code does not allow one");
+                return Problem::new(INTERNAL_SERVER_ERROR)
+                    .with_detail(format!("Status code {} does not allow a body", status_code))
+                    .into();
+            }
+
+            tracing::warn!(status = ?status_code, "Stripping body from response with a bodyless status code");
+            headers.remove(header::CONTENT_TYPE);
+            false
+        } else {
+            has_body
+        };
+
+        let body = if has_body {
+            if media_type != default_media_type || !headers.contains_key(header::CONTENT_TYPE) {
+                headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(media_type));
+            }
+
+            let body = R::serialize_body(body, omit_nulls);
+            let body = match body {
+                Ok(body) if self.decimal && is_json(media_type) => Ok(decimal::normalize(&body)),
+                body => body,
+            };
+            #[cfg(feature = "serde_yaml")]
+            let body = match body {
+                Ok(body) if media_type == yaml::MEDIA_TYPE => yaml::transcode(&body),
+                body => body,
+            };
+
+            let body = match body {
+                Ok(body) => body,
+                Err(e) => return serialization_error(e),
+            };
+
+            #[cfg(feature = "content-digest")]
+            if !self.digests.is_empty() {
+                if let Some(value) = digest::header(&self.digests, &body) {
+                    headers.insert(digest::CONTENT_DIGEST, value);
+                }
+            }
+
+            Some(body)
+        } else {
+            None
+        };
+
+        // HTTP-dates are absolute, so this needs the wall clock rather than a monotonic one.
+        if !headers.contains_key(header::DATE) {
+            if let Ok(date) = HttpDate::from(SystemTime::now()).try_into_value() {
+                headers.insert(header::DATE, date);
+            }
+        }
+
+        let mut response = HttpResponse::build(status_code);
+
+        for (key, value) in headers {
+            response.append_header((key, value));
+        }
+
+        if let Some(hook) = self.builder_hook {
+            hook(&mut response);
+        }
 
-        for (key, value) in self.0.headers().iter() {
-            response.insert_header((key, value.clone()));
+        match body {
+            Some(body) => response.body(body),
+            None => response.finish(),
         }
+    }
+}
+
+/// Whether responses with a body and a status code that doesn't allow one are rejected.
+static STRICT_BODYLESS_STATUSES: AtomicBool = AtomicBool::new(false);
+
+/// Specify whether responses that have a body but a status code that doesn't allow one - `1xx`, `204 No
+/// Content` and `304 Not Modified` - are rejected. Defaults to `false`.
+///
+/// When strict, such responses are replaced with a `500 Internal Server Error` problem, so that the mistake is
+/// obvious during development. Otherwise a warning is logged and the body is stripped from the response.
+///
+/// # Parameters
+/// - `strict` - Whether to reject bodies on bodyless status codes
+pub fn strict_bodyless_statuses(strict: bool) {
+    STRICT_BODYLESS_STATUSES.store(strict, Ordering::Relaxed);
+}
+
+/// Determine whether a status code forbids the response from having a body.
+///
+/// # Parameters
+/// - `status_code` - The status code to check
+fn is_bodyless(status_code: StatusCode) -> bool {
+    status_code.is_informational() || status_code == StatusCode::NO_CONTENT || status_code == StatusCode::NOT_MODIFIED
+}
+
+/// Determine whether a media type is JSON, including structured syntax suffixes such as `application/hal+json`.
+///
+/// # Parameters
+/// - `media_type` - The media type to check
+fn is_json(media_type: &str) -> bool {
+    media_type == "application/json" || media_type.ends_with("+json")
+}
+
+/// Whether the message of a serialization error is exposed to the client.
+static EXPOSE_SERIALIZATION_ERRORS: AtomicBool = AtomicBool::new(false);
+
+/// Specify whether the details of a failure to serialize a response body are included in the `500 Internal
+/// Server Error` problem sent back to the client. Defaults to `false`.
+///
+/// # Parameters
+/// - `expose` - Whether to expose serialization errors
+pub fn expose_serialization_errors(expose: bool) {
+    EXPOSE_SERIALIZATION_ERRORS.store(expose, Ordering::Relaxed);
+}
+
+/// Build the response to send when the body of a response failed to serialize.
+///
+/// # Parameters
+/// - `e` - The serialization error
+fn serialization_error(e: serde_json::Error) -> HttpResponse {
+    tracing::error!(e = ?e, "Failed to serialize response body");
+
+    let problem = Problem::new(INTERNAL_SERVER_ERROR);
+    if EXPOSE_SERIALIZATION_ERRORS.load(Ordering::Relaxed) {
+        problem.with_detail(e.to_string()).into()
+    } else {
+        problem.into()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use actix_web::{
+        body::AnyBody,
+        http::header::{CacheControl, CacheDirective, Date},
+        test::TestRequest,
+    };
+    use assert2::{check, let_assert};
+
+    use super::*;
+
+    #[test]
+    fn serialization_failure() {
+        let mut body = HashMap::new();
+        body.insert((1, 2), "Not a string key");
+
+        let req = TestRequest::default().to_http_request();
+        let response = Response::new(body).respond_to(&req);
+
+        check!(response.status() == StatusCode::INTERNAL_SERVER_ERROR);
+        check!(response.headers().get(header::CONTENT_TYPE).unwrap() == "application/problem+json");
+    }
+
+    #[test]
+    fn builder_hook_overrides_headers() {
+        let req = TestRequest::default().to_http_request();
+        let response = Response::new(SimpleRespondable::new(42).with_header(CacheControl(vec![CacheDirective::Public])))
+            .with_builder_hook(|builder| {
+                builder.insert_header((header::CACHE_CONTROL, "no-store"));
+            })
+            .respond_to(&req);
+
+        check!(response.headers().get(header::CACHE_CONTROL).unwrap() == "no-store");
+    }
+
+    #[test]
+    fn decimal_floats() {
+        let req = TestRequest::default().to_http_request();
+        let response = Response::new(vec![1e-7]).with_decimal_floats(true).respond_to(&req);
+
+        let_assert!(AnyBody::Bytes(body) = response.body());
+        check!(body.as_ref() == b"[0.0000001]");
+    }
+
+    #[test]
+    fn date_added_if_absent() {
+        let req = TestRequest::default().to_http_request();
+        let response = Response::new(42).respond_to(&req);
+
+        let date = response.headers().get(header::DATE).unwrap().to_str().unwrap();
+        check!(date.parse::<HttpDate>().is_ok());
+    }
+
+    #[test]
+    fn explicit_date_retained() {
+        let req = TestRequest::default().to_http_request();
+        let date = "Tue, 15 Nov 1994 08:12:31 GMT";
+        let respondable = SimpleRespondable::new(42).with_header(Date(date.parse::<HttpDate>().unwrap()));
+        let response = Response::new(respondable).respond_to(&req);
+
+        check!(response.headers().get_all(header::DATE).collect::<Vec<_>>() == vec![date]);
+    }
+
+    #[test]
+    fn body_stripped_from_bodyless_status() {
+        let req = TestRequest::default().to_http_request();
+        let respondable = SimpleRespondable::new(42).with_status_code(StatusCode::NO_CONTENT);
+        let response = Response::new(respondable).respond_to(&req);
+
+        check!(response.status() == StatusCode::NO_CONTENT);
+        check!(response.headers().get(header::CONTENT_TYPE).is_none());
+    }
+
+    #[test]
+    fn extensions_stored() {
+        let req = TestRequest::default().to_http_request();
+        let response = Response::new(42).with_extension(7_u32).with_extension("audit").respond_to(&req);
+
+        check!(response.extensions().get::<u32>() == Some(&7));
+        check!(response.extensions().get::<&str>() == Some(&"audit"));
+    }
+
+    #[cfg(feature = "content-digest")]
+    #[test]
+    fn content_digest() {
+        let req = TestRequest::default().to_http_request();
+        let response = Response::new(42).with_content_digest(DigestAlgorithm::Sha256).respond_to(&req);
+
+        check!(
+            response.headers().get("content-digest").unwrap()
+                == "sha-256=:c0dctApWjo2ooEXO0RATfhWfiQrE2og7axfcZRs6gEk=:"
+        );
+    }
+
+    #[cfg(feature = "serde_yaml")]
+    #[test]
+    fn negotiate_yaml() {
+        let req = TestRequest::default()
+            .insert_header((header::ACCEPT, "application/yaml"))
+            .to_http_request();
+        let response = Response::new(SimpleRespondable::new(42)).respond_to(&req);
 
-        response.json(self.0.body())
+        check!(response.headers().get(header::CONTENT_TYPE).unwrap() == "application/yaml");
+        let_assert!(AnyBody::Bytes(body) = response.body());
+        check!(body.as_ref() == b"42\n");
     }
 }
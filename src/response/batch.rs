@@ -0,0 +1,175 @@
+use actix_http::http::{header::Header, HeaderMap, StatusCode};
+use serde::{Serialize, Serializer};
+
+use super::Respondable;
+
+/// How the overall status code of a batch response is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum BatchStatusPolicy {
+    /// Always respond with `200 OK`, leaving the outcome to the status of each result.
+    AlwaysOk,
+    /// Respond with `207 Multi-Status` if some results were successful and others weren't, and `200 OK`
+    /// otherwise.
+    #[default]
+    MultiStatusWhenMixed,
+}
+
+/// The result of a single operation within a batch.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+pub struct BatchResult {
+    /// The identifier of the operation
+    pub id:     String,
+    /// The status of the operation
+    #[serde(serialize_with = "serialize_status_code")]
+    pub status: StatusCode,
+    /// The body of the operation's result
+    pub body:   serde_json::Value,
+}
+
+/// Respondable for the outcome of a batch endpoint, summarizing the result of each operation in the batch.
+///
+/// The body is an array with an entry for each result, in the order they were added, e.g.
+/// `[{"id": "1", "status": 201, "body": {...}}]`.
+pub struct BatchRespondable {
+    headers: HeaderMap,
+    policy:  BatchStatusPolicy,
+    results: Vec<BatchResult>,
+}
+
+impl BatchRespondable {
+    /// Create a new instance of the `BatchRespondable` struct with no results.
+    pub fn new() -> Self {
+        Self {
+            headers: HeaderMap::new(),
+            policy:  BatchStatusPolicy::default(),
+            results: vec![],
+        }
+    }
+
+    /// Add the result of an operation within the batch.
+    ///
+    /// # Parameters
+    /// - `id` - The identifier of the operation
+    /// - `status` - The status of the operation
+    /// - `body` - The body of the operation's result
+    pub fn with_result<S>(mut self, id: S, status: StatusCode, body: serde_json::Value) -> Self
+    where
+        S: Into<String>,
+    {
+        self.results.push(BatchResult {
+            id: id.into(),
+            status,
+            body,
+        });
+
+        self
+    }
+
+    /// Specify how the overall status code of the response is chosen.
+    ///
+    /// # Parameters
+    /// - `policy` - The policy to use
+    pub fn with_status_policy(mut self, policy: BatchStatusPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Specify a header to include in the response.
+    ///
+    /// # Parameters
+    /// - `header` - The header to add to the response.
+    pub fn with_header<H>(mut self, header: H) -> Self
+    where
+        H: Header,
+    {
+        let name = H::name();
+        match header.try_into_value() {
+            Ok(value) => {
+                self.headers.append(name, value);
+            },
+            Err(_) => {
+                tracing::error!(name = ?name, "Failed to process header");
+            },
+        };
+
+        self
+    }
+}
+
+impl Default for BatchRespondable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Respondable for BatchRespondable {
+    type Body = Vec<BatchResult>;
+
+    fn status_code(&self) -> StatusCode {
+        let successful = self.results.iter().filter(|result| result.status.is_success()).count();
+        let mixed = successful != 0 && successful != self.results.len();
+
+        match self.policy {
+            BatchStatusPolicy::MultiStatusWhenMixed if mixed => StatusCode::MULTI_STATUS,
+            _ => StatusCode::OK,
+        }
+    }
+
+    fn headers(&self) -> HeaderMap {
+        self.headers.clone()
+    }
+
+    fn body(self) -> Self::Body {
+        self.results
+    }
+}
+
+/// Serialize a status code as its number.
+///
+/// # Parameters
+/// - `status_code` - The status code to serialize
+/// - `serializer` - The serializer to use
+fn serialize_status_code<S>(status_code: &StatusCode, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serializer.serialize_u16(status_code.as_u16())
+}
+
+#[cfg(test)]
+mod tests {
+    use assert2::check;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn mixed_results() {
+        let respondable = BatchRespondable::new()
+            .with_result("1", StatusCode::CREATED, json!({"id": 1}))
+            .with_result("2", StatusCode::CONFLICT, json!({"title": "Conflict"}));
+
+        check!(respondable.status_code() == StatusCode::MULTI_STATUS);
+        check!(
+            serde_json::to_value(respondable.body()).unwrap()
+                == json!([
+                    {"id": "1", "status": 201, "body": {"id": 1}},
+                    {"id": "2", "status": 409, "body": {"title": "Conflict"}}
+                ])
+        );
+    }
+
+    #[test]
+    fn status_policy() {
+        let successful = BatchRespondable::new()
+            .with_result("1", StatusCode::CREATED, json!(null))
+            .with_result("2", StatusCode::OK, json!(null));
+        let mixed = BatchRespondable::new()
+            .with_result("1", StatusCode::CREATED, json!(null))
+            .with_result("2", StatusCode::NOT_FOUND, json!(null))
+            .with_status_policy(BatchStatusPolicy::AlwaysOk);
+
+        check!(successful.status_code() == StatusCode::OK);
+        check!(mixed.status_code() == StatusCode::OK);
+    }
+}
@@ -0,0 +1,258 @@
+use actix_http::http::{
+    header::{self, Header},
+    HeaderMap, HeaderValue, Method, StatusCode,
+};
+use actix_web::{web::Bytes, HttpRequest};
+
+use super::{conditional, Respondable};
+
+/// Respondable for an arbitrary binary body, such as a download, served exactly as provided.
+///
+/// Clients can request part of the body with a `Range` header, which is honoured by `with_range`.
+pub struct BytesRespondable {
+    status_code: StatusCode,
+    headers:     HeaderMap,
+    media_type:  &'static str,
+    body:        Bytes,
+}
+
+impl BytesRespondable {
+    /// Create a new instance of the `BytesRespondable` struct serving the provided bytes.
+    ///
+    /// # Parameters
+    /// - `body` - The bytes to send back to the client
+    /// - `media_type` - The media type of the bytes
+    pub fn new<B>(body: B, media_type: &'static str) -> Self
+    where
+        B: Into<Bytes>,
+    {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(media_type));
+        headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+
+        Self {
+            status_code: StatusCode::OK,
+            headers,
+            media_type,
+            body: body.into(),
+        }
+    }
+
+    /// Specify the status code to use.
+    ///
+    /// # Parameters
+    /// - `status_code` - The status code to use
+    pub fn with_status_code(mut self, status_code: StatusCode) -> Self {
+        self.status_code = status_code;
+        self
+    }
+
+    /// Specify a header to include in the response.
+    ///
+    /// # Parameters
+    /// - `header` - The header to add to the response.
+    pub fn with_header<H>(mut self, header: H) -> Self
+    where
+        H: Header,
+    {
+        let name = H::name();
+        match header.try_into_value() {
+            Ok(value) => {
+                self.headers.append(name, value);
+            },
+            Err(_) => {
+                tracing::error!(name = ?name, "Failed to process header");
+            },
+        };
+
+        self
+    }
+
+    /// Serve only the part of the body requested by the `Range` header of the request, as a `206 Partial
+    /// Content`. A range that can't be satisfied gives a `416 Range Not Satisfiable` instead.
+    ///
+    /// The range is only served if the `If-Range` header of the request, if any, matches the `ETag` or
+    /// `Last-Modified` header of this response, so those must be set before calling this. Otherwise the resource
+    /// has changed since the client fetched the earlier part, so the whole body is served instead. The whole body
+    /// is also served for anything other than a `200 OK` response to a `GET` request, and for requests for
+    /// several ranges at once.
+    ///
+    /// # Parameters
+    /// - `req` - The request being responded to
+    pub fn with_range(mut self, req: &HttpRequest) -> Self {
+        if req.method() != Method::GET || self.status_code != StatusCode::OK {
+            return self;
+        }
+
+        let range = match req.headers().get(header::RANGE).and_then(|value| value.to_str().ok()) {
+            Some(range) => range,
+            None => return self,
+        };
+        if !conditional::if_range_matches(req, &self.headers) {
+            return self;
+        }
+
+        let length = self.body.len() as u64;
+        match parse_range(range, length) {
+            Some(Ok((start, end))) => {
+                self.set_content_range(&format!("bytes {}-{}/{}", start, end, length));
+                self.status_code = StatusCode::PARTIAL_CONTENT;
+                self.body = self.body.slice(start as usize..=end as usize);
+            },
+            Some(Err(())) => {
+                self.set_content_range(&format!("bytes */{}", length));
+                self.status_code = StatusCode::RANGE_NOT_SATISFIABLE;
+                self.headers.remove(header::CONTENT_TYPE);
+                self.body = Bytes::new();
+            },
+            None => {},
+        }
+
+        self
+    }
+
+    /// Set the `Content-Range` header of the response.
+    ///
+    /// # Parameters
+    /// - `content_range` - The value of the header
+    fn set_content_range(&mut self, content_range: &str) {
+        if let Ok(value) = HeaderValue::from_str(content_range) {
+            self.headers.insert(header::CONTENT_RANGE, value);
+        }
+    }
+}
+
+/// Parse the value of a `Range` header requesting a single range of bytes.
+///
+/// # Parameters
+/// - `range` - The value of the `Range` header, e.g. `bytes=0-499`, `bytes=500-` or `bytes=-500`
+/// - `length` - The length of the body
+///
+/// # Returns
+/// The first and last positions of the range, both inclusive, or `Err` if the range can't be satisfied. `None`
+/// if the header is malformed or requests several ranges, in which case it is ignored.
+pub(crate) fn parse_range(range: &str, length: u64) -> Option<Result<(u64, u64), ()>> {
+    let spec = range.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None;
+    }
+
+    let (start, end) = spec.trim().split_once('-')?;
+    let range = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix = suffix.parse::<u64>().ok()?;
+            (length.saturating_sub(suffix), length.checked_sub(1).filter(|_| suffix > 0))
+        },
+        (start, "") => (start.parse::<u64>().ok()?, length.checked_sub(1)),
+        (start, end) => {
+            let (start, end) = (start.parse::<u64>().ok()?, end.parse::<u64>().ok()?);
+            if end < start {
+                return None;
+            }
+            (start, length.checked_sub(1).map(|last| end.min(last)))
+        },
+    };
+
+    Some(match range {
+        (start, Some(end)) if start <= end => Ok((start, end)),
+        _ => Err(()),
+    })
+}
+
+impl Respondable for BytesRespondable {
+    type Body = Vec<u8>;
+
+    fn status_code(&self) -> StatusCode {
+        self.status_code
+    }
+
+    fn headers(&self) -> HeaderMap {
+        self.headers.clone()
+    }
+
+    fn media_types(&self) -> Vec<&'static str> {
+        vec![self.media_type]
+    }
+
+    fn has_body(&self) -> bool {
+        self.status_code != StatusCode::RANGE_NOT_SATISFIABLE
+    }
+
+    fn body(self) -> Self::Body {
+        self.body.to_vec()
+    }
+
+    fn into_parts(self) -> (StatusCode, HeaderMap, Self::Body) {
+        (self.status_code, self.headers, self.body.to_vec())
+    }
+
+    fn serialize_body(body: Self::Body, _omit_nulls: bool) -> serde_json::Result<Vec<u8>> {
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{
+        body::AnyBody,
+        http::header::{ETag, EntityTag},
+        test::TestRequest,
+        Responder,
+    };
+    use assert2::{check, let_assert};
+
+    use super::*;
+    use crate::response::Response;
+
+    fn respond(range: &str, if_range: Option<&str>) -> actix_web::HttpResponse {
+        let mut req = TestRequest::default().insert_header((header::RANGE, range));
+        if let Some(if_range) = if_range {
+            req = req.insert_header((header::IF_RANGE, if_range));
+        }
+        let req = req.to_http_request();
+
+        let respondable = BytesRespondable::new("0123456789", "application/octet-stream")
+            .with_header(ETag(EntityTag::strong("v1".to_owned())))
+            .with_range(&req);
+        Response::new(respondable).respond_to(&req)
+    }
+
+    #[test]
+    fn parse_ranges() {
+        check!(parse_range("bytes=0-4", 10) == Some(Ok((0, 4))));
+        check!(parse_range("bytes=5-", 10) == Some(Ok((5, 9))));
+        check!(parse_range("bytes=-3", 10) == Some(Ok((7, 9))));
+        check!(parse_range("bytes=8-20", 10) == Some(Ok((8, 9))));
+        check!(parse_range("bytes=10-", 10) == Some(Err(())));
+        check!(parse_range("bytes=0-1,4-5", 10) == None);
+        check!(parse_range("items=0-1", 10) == None);
+    }
+
+    #[test]
+    fn partial_content() {
+        let response = respond("bytes=2-4", Some("\"v1\""));
+
+        check!(response.status() == StatusCode::PARTIAL_CONTENT);
+        check!(response.headers().get(header::CONTENT_RANGE).unwrap() == "bytes 2-4/10");
+        let_assert!(AnyBody::Bytes(body) = response.body());
+        check!(body.as_ref() == b"234");
+    }
+
+    #[test]
+    fn changed_resource_served_in_full() {
+        let response = respond("bytes=2-4", Some("\"v0\""));
+
+        check!(response.status() == StatusCode::OK);
+        check!(response.headers().get(header::CONTENT_RANGE).is_none());
+        let_assert!(AnyBody::Bytes(body) = response.body());
+        check!(body.as_ref() == b"0123456789");
+    }
+
+    #[test]
+    fn range_not_satisfiable() {
+        let response = respond("bytes=20-", None);
+
+        check!(response.status() == StatusCode::RANGE_NOT_SATISFIABLE);
+        check!(response.headers().get(header::CONTENT_RANGE).unwrap() == "bytes */10");
+    }
+}
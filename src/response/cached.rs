@@ -0,0 +1,300 @@
+use std::{
+    sync::{Arc, RwLock},
+    time::{Duration, Instant},
+};
+
+use actix_http::http::{HeaderMap, StatusCode};
+use actix_web::{web::Bytes, HttpRequest};
+use serde::{ser::Error as _, Serialize};
+
+use super::{conditional, NegotiationMode, Respondable};
+
+/// Factory that builds a fresh copy of a cached respondable.
+type Factory<R> = Box<dyn Fn() -> R + Send + Sync>;
+
+/// In-process cache of a fully serialized response, for resources that are expensive to build but rarely change,
+/// such as the root of an API.
+///
+/// The respondable is built and serialized the first time it is needed, and every later request is served from
+/// the stored status code, headers and bytes until it is invalidated or the TTL expires. Instances are cheap to
+/// clone and share the same cache, so a single one can be registered as app data and used by every worker.
+///
+/// The respondable is built without access to any request, so anything that depends on the request - e.g.
+/// sparse fieldsets or links generated from the route - can't be part of a cached response. Content negotiation
+/// still happens on every request, and conditional requests can be handled with `CachedResponse::conditional`.
+pub struct Cached<R> {
+    inner: Arc<CachedInner<R>>,
+}
+
+/// The state shared between every clone of a `Cached` instance.
+struct CachedInner<R> {
+    factory: Factory<R>,
+    ttl:     Option<Duration>,
+    entry:   RwLock<Option<CachedResponse>>,
+}
+
+impl<R> Cached<R>
+where
+    R: Respondable,
+{
+    /// Create a new cache of the respondable built by the provided factory.
+    ///
+    /// # Parameters
+    /// - `factory` - Function to build the respondable, called whenever the cache is empty
+    pub fn new<F>(factory: F) -> Self
+    where
+        F: Fn() -> R + Send + Sync + 'static,
+    {
+        Self {
+            inner: Arc::new(CachedInner {
+                factory: Box::new(factory),
+                ttl:     None,
+                entry:   RwLock::new(None),
+            }),
+        }
+    }
+
+    /// Create a new cache of the respondable built by the provided factory, which is rebuilt once it is older
+    /// than the provided TTL.
+    ///
+    /// # Parameters
+    /// - `factory` - Function to build the respondable, called whenever the cache is empty or expired
+    /// - `ttl` - How long the cached response remains valid for
+    pub fn with_ttl<F>(factory: F, ttl: Duration) -> Self
+    where
+        F: Fn() -> R + Send + Sync + 'static,
+    {
+        Self {
+            inner: Arc::new(CachedInner {
+                factory: Box::new(factory),
+                ttl:     Some(ttl),
+                entry:   RwLock::new(None),
+            }),
+        }
+    }
+
+    /// Get the cached response, building it first if the cache is empty or expired.
+    ///
+    /// A response whose body failed to serialize is never cached, so the next call tries again.
+    ///
+    /// # Returns
+    /// The cached response, ready to be wrapped in a `Response`
+    pub fn get(&self) -> CachedResponse {
+        if let Some(entry) = self.current() {
+            return entry;
+        }
+
+        let entry = self.build();
+        if entry.body.is_ok() {
+            *self.inner.entry.write().unwrap_or_else(|e| e.into_inner()) = Some(entry.clone());
+        }
+
+        entry
+    }
+
+    /// Invalidate the cache, so that the next call to `get` builds the response again.
+    pub fn invalidate(&self) {
+        *self.inner.entry.write().unwrap_or_else(|e| e.into_inner()) = None;
+    }
+
+    /// Get the currently cached response, if there is one that hasn't expired.
+    fn current(&self) -> Option<CachedResponse> {
+        let entry = self.inner.entry.read().unwrap_or_else(|e| e.into_inner());
+
+        entry
+            .as_ref()
+            .filter(|entry| self.inner.ttl.is_none_or(|ttl| entry.created.elapsed() < ttl))
+            .cloned()
+    }
+
+    /// Build and serialize a fresh copy of the respondable.
+    fn build(&self) -> CachedResponse {
+        let respondable = (self.inner.factory)();
+
+        let media_types = respondable.media_types();
+        let negotiation_mode = respondable.negotiation_mode();
+        let has_body = respondable.has_body();
+        let omit_nulls = respondable.omits_nulls();
+        let (status_code, headers, body) = respondable.into_parts();
+
+        let body = if has_body {
+            R::serialize_body(body, omit_nulls).map(|body| Some(Bytes::from(body))).map_err(|e| {
+                tracing::error!(e = ?e, "Failed to serialize cached response body");
+                e.to_string()
+            })
+        } else {
+            Ok(None)
+        };
+
+        CachedResponse {
+            status_code,
+            headers,
+            media_types,
+            negotiation_mode,
+            body,
+            created: Instant::now(),
+        }
+    }
+}
+
+impl<R> Clone for Cached<R> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+/// Respondable for a response served from a `Cached` instance.
+#[derive(Clone)]
+pub struct CachedResponse {
+    status_code:      StatusCode,
+    headers:          HeaderMap,
+    media_types:      Vec<&'static str>,
+    negotiation_mode: NegotiationMode,
+    body:             Result<Option<Bytes>, String>,
+    created:          Instant,
+}
+
+impl CachedResponse {
+    /// Turn this response into a `304 Not Modified` if the validators on the request match it.
+    ///
+    /// This behaves exactly as `HalRespondable::conditional`, using the `ETag` and `Last-Modified` headers of the
+    /// cached response.
+    ///
+    /// # Parameters
+    /// - `req` - The request being responded to
+    pub fn conditional(mut self, req: &HttpRequest) -> Self {
+        if conditional::is_not_modified(req, &self.headers) {
+            let mut headers = HeaderMap::new();
+            for name in conditional::NOT_MODIFIED_HEADERS {
+                for value in self.headers.get_all(name) {
+                    headers.append(name.clone(), value.clone());
+                }
+            }
+
+            self.status_code = StatusCode::NOT_MODIFIED;
+            self.headers = headers;
+            self.body = Ok(None);
+        }
+
+        self
+    }
+}
+
+impl Respondable for CachedResponse {
+    type Body = CachedBody;
+
+    fn status_code(&self) -> StatusCode {
+        self.status_code
+    }
+
+    fn headers(&self) -> HeaderMap {
+        self.headers.clone()
+    }
+
+    fn media_types(&self) -> Vec<&'static str> {
+        self.media_types.clone()
+    }
+
+    fn negotiation_mode(&self) -> NegotiationMode {
+        self.negotiation_mode
+    }
+
+    fn has_body(&self) -> bool {
+        !matches!(self.body, Ok(None))
+    }
+
+    fn body(self) -> Self::Body {
+        CachedBody(self.body)
+    }
+
+    fn into_parts(self) -> (StatusCode, HeaderMap, Self::Body) {
+        (self.status_code, self.headers, CachedBody(self.body))
+    }
+
+    fn serialize_body(body: Self::Body, _omit_nulls: bool) -> serde_json::Result<Vec<u8>> {
+        match body.0 {
+            Ok(body) => Ok(body.map(Into::into).unwrap_or_default()),
+            Err(e) => Err(serde_json::Error::custom(e)),
+        }
+    }
+}
+
+/// The serialized body of a cached response, or the message of the error it failed to serialize with.
+pub struct CachedBody(Result<Option<Bytes>, String>);
+
+impl Serialize for CachedBody {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        match &self.0 {
+            Ok(body) => serializer.serialize_bytes(body.as_deref().unwrap_or_default()),
+            Err(e) => Err(S::Error::custom(e)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use actix_http::http::header;
+    use actix_web::{body::AnyBody, test::TestRequest, Responder};
+    use assert2::{check, let_assert};
+
+    use super::*;
+    use crate::{hal::HalRespondable, response::Response};
+
+    #[test]
+    fn cached_until_invalidated() {
+        let built = Arc::new(AtomicUsize::new(0));
+        let counter = built.clone();
+        let cached = Cached::new(move || {
+            let count = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            HalRespondable::new(serde_json::json!({ "count": count })).with_etag("root")
+        });
+        let req = TestRequest::default().to_http_request();
+
+        for _ in 0..2 {
+            let response = Response::new(cached.clone().get()).respond_to(&req);
+            check!(response.headers().get(header::CONTENT_TYPE).unwrap() == "application/hal+json");
+            check!(response.headers().get(header::ETAG).unwrap() == "\"root\"");
+            let_assert!(AnyBody::Bytes(body) = response.body());
+            let json: serde_json::Value = serde_json::from_slice(body).unwrap();
+            check!(json["count"] == 1);
+        }
+
+        cached.invalidate();
+        let response = Response::new(cached.get()).respond_to(&req);
+        let_assert!(AnyBody::Bytes(body) = response.body());
+        let json: serde_json::Value = serde_json::from_slice(body).unwrap();
+        check!(json["count"] == 2);
+        check!(built.load(Ordering::SeqCst) == 2);
+    }
+
+    #[test]
+    fn expired_by_ttl() {
+        let built = Arc::new(AtomicUsize::new(0));
+        let counter = built.clone();
+        let cached = Cached::with_ttl(move || counter.fetch_add(1, Ordering::SeqCst), Duration::ZERO);
+
+        cached.get();
+        cached.get();
+
+        check!(built.load(Ordering::SeqCst) == 2);
+    }
+
+    #[test]
+    fn conditional_request() {
+        let cached = Cached::new(|| HalRespondable::new(()).with_etag("root"));
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "\"root\""))
+            .to_http_request();
+        let response = Response::new(cached.get().conditional(&req)).respond_to(&req);
+
+        check!(response.status() == StatusCode::NOT_MODIFIED);
+        check!(response.headers().get(header::CONTENT_TYPE).is_none());
+    }
+}
@@ -0,0 +1,220 @@
+use std::time::SystemTime;
+
+use actix_http::http::{
+    header::{self, Header, HttpDate},
+    HeaderMap, HeaderName, Method,
+};
+use actix_web::{
+    http::header::{CacheControl, CacheDirective},
+    HttpRequest,
+};
+
+/// The headers of a response that are retained when it is replaced by a `304 Not Modified`, as required by
+/// RFC 7232 section 4.1. Every other header is dropped.
+pub(crate) const NOT_MODIFIED_HEADERS: &[HeaderName] = &[
+    header::CACHE_CONTROL,
+    header::CONTENT_LOCATION,
+    header::DATE,
+    header::ETAG,
+    header::EXPIRES,
+    header::VARY,
+];
+
+/// Determine if a response with the provided headers is unmodified according to the validators on the request.
+///
+/// Only `GET` and `HEAD` requests are ever considered unmodified. If the request has an `If-None-Match` header
+/// then it is compared to the `ETag` of the response using the weak comparison function, and
+/// `If-Modified-Since` is ignored. Otherwise `If-Modified-Since` is compared to the `Last-Modified` of the
+/// response.
+///
+/// A request that forces revalidation, such as a forced refresh in a browser, is never considered unmodified, so
+/// that it always gets the full response. See `forces_revalidation`.
+///
+/// # Parameters
+/// - `req` - The request being responded to
+/// - `headers` - The headers of the response
+pub(crate) fn is_not_modified(req: &HttpRequest, headers: &HeaderMap) -> bool {
+    if req.method() != Method::GET && req.method() != Method::HEAD {
+        return false;
+    }
+    if forces_revalidation(req) {
+        return false;
+    }
+
+    if let Some(if_none_match) = req.headers().get(header::IF_NONE_MATCH) {
+        let if_none_match = if_none_match.to_str().unwrap_or_default();
+        return match headers.get(header::ETAG).and_then(|etag| etag.to_str().ok()) {
+            Some(etag) => etag_matches(if_none_match, etag),
+            None => false,
+        };
+    }
+
+    match (
+        http_date(req.headers(), header::IF_MODIFIED_SINCE),
+        http_date(headers, header::LAST_MODIFIED),
+    ) {
+        (Some(if_modified_since), Some(last_modified)) => last_modified <= if_modified_since,
+        _ => false,
+    }
+}
+
+/// Determine if the `Cache-Control` header of the request forces revalidation, by containing either `no-cache` or
+/// `max-age=0`. A `Cache-Control` header that can't be parsed is ignored.
+///
+/// # Parameters
+/// - `req` - The request being responded to
+pub(crate) fn forces_revalidation(req: &HttpRequest) -> bool {
+    let cache_control = match CacheControl::parse(req) {
+        Ok(cache_control) => cache_control,
+        Err(_) => return false,
+    };
+
+    cache_control
+        .0
+        .iter()
+        .any(|directive| matches!(directive, CacheDirective::NoCache | CacheDirective::MaxAge(0)))
+}
+
+/// Determine if the `If-Range` header of the request matches a response with the provided headers, as defined by
+/// RFC 7233 section 3.2, meaning that a requested range can be served from it.
+///
+/// The `If-Range` header holds either an entity tag, which must match the `ETag` of the response using the strong
+/// comparison function, or an HTTP-date, which must exactly match its `Last-Modified`. A request without an
+/// `If-Range` header always matches.
+///
+/// # Parameters
+/// - `req` - The request being responded to
+/// - `headers` - The headers of the response
+pub(crate) fn if_range_matches(req: &HttpRequest, headers: &HeaderMap) -> bool {
+    let if_range = match req.headers().get(header::IF_RANGE).and_then(|value| value.to_str().ok()) {
+        Some(if_range) => if_range.trim(),
+        None => return true,
+    };
+
+    if if_range.starts_with('"') || if_range.starts_with("W/") {
+        let etag = headers.get(header::ETAG).and_then(|etag| etag.to_str().ok());
+        return !if_range.starts_with("W/") && etag.is_some_and(|etag| etag.trim() == if_range);
+    }
+
+    match (
+        if_range.parse::<HttpDate>().ok().map(SystemTime::from),
+        http_date(headers, header::LAST_MODIFIED),
+    ) {
+        (Some(date), Some(last_modified)) => date == last_modified,
+        _ => false,
+    }
+}
+
+/// Determine if the provided entity tag matches any of a list of entity tags, using weak comparison.
+///
+/// # Parameters
+/// - `list` - The list of entity tags, e.g. from an `If-None-Match` header
+/// - `etag` - The entity tag to look for
+fn etag_matches(list: &str, etag: &str) -> bool {
+    let opaque = |tag: &str| tag.trim().trim_start_matches("W/").to_owned();
+
+    list.trim() == "*" || list.split(',').any(|tag| opaque(tag) == opaque(etag))
+}
+
+/// Parse the value of the named header as an HTTP-date.
+fn http_date(headers: &HeaderMap, name: HeaderName) -> Option<SystemTime> {
+    headers
+        .get(name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<HttpDate>().ok())
+        .map(SystemTime::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use actix_http::http::HeaderValue;
+    use actix_web::test::TestRequest;
+    use assert2::check;
+
+    use super::*;
+
+    fn headers(name: HeaderName, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn matching_etag() {
+        let req = TestRequest::default()
+            .insert_header((header::IF_NONE_MATCH, "\"abc\", W/\"def\""))
+            .to_http_request();
+
+        check!(is_not_modified(&req, &headers(header::ETAG, "\"def\"")));
+        check!(is_not_modified(&req, &headers(header::ETAG, "W/\"abc\"")));
+        check!(!is_not_modified(&req, &headers(header::ETAG, "\"ghi\"")));
+        check!(!is_not_modified(&req, &HeaderMap::new()));
+    }
+
+    #[test]
+    fn wildcard_etag() {
+        let req = TestRequest::default().insert_header((header::IF_NONE_MATCH, "*")).to_http_request();
+
+        check!(is_not_modified(&req, &headers(header::ETAG, "\"abc\"")));
+    }
+
+    #[test]
+    fn unsafe_method() {
+        let req = TestRequest::post().insert_header((header::IF_NONE_MATCH, "*")).to_http_request();
+
+        check!(!is_not_modified(&req, &headers(header::ETAG, "\"abc\"")));
+    }
+
+    #[test]
+    fn forced_revalidation() {
+        let etag = headers(header::ETAG, "\"abc\"");
+        let revalidate = |cache_control: &str| {
+            TestRequest::default()
+                .insert_header((header::IF_NONE_MATCH, "\"abc\""))
+                .insert_header((header::CACHE_CONTROL, cache_control))
+                .to_http_request()
+        };
+
+        check!(!is_not_modified(&revalidate("no-cache"), &etag));
+        check!(!is_not_modified(&revalidate("max-age=0"), &etag));
+        check!(is_not_modified(&revalidate("max-age=60"), &etag));
+        check!(is_not_modified(&revalidate("no-transform"), &etag));
+    }
+
+    #[test]
+    fn modified_since() {
+        let now = SystemTime::now();
+        let earlier = HttpDate::from(now - Duration::from_secs(60)).to_string();
+        let later = HttpDate::from(now + Duration::from_secs(60)).to_string();
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_MODIFIED_SINCE, earlier.as_str()))
+            .to_http_request();
+        check!(!is_not_modified(&req, &headers(header::LAST_MODIFIED, &later)));
+
+        let req = TestRequest::default()
+            .insert_header((header::IF_MODIFIED_SINCE, later.as_str()))
+            .to_http_request();
+        check!(is_not_modified(&req, &headers(header::LAST_MODIFIED, &earlier)));
+    }
+
+    #[test]
+    fn if_range() {
+        let date = "Tue, 15 Nov 1994 08:12:31 GMT";
+        let etag = headers(header::ETAG, "\"abc\"");
+        let if_range = |value: &str| {
+            TestRequest::default()
+                .insert_header((header::IF_RANGE, value))
+                .to_http_request()
+        };
+
+        check!(if_range_matches(&TestRequest::default().to_http_request(), &etag));
+        check!(if_range_matches(&if_range("\"abc\""), &etag));
+        check!(!if_range_matches(&if_range("W/\"abc\""), &headers(header::ETAG, "W/\"abc\"")));
+        check!(!if_range_matches(&if_range("\"def\""), &etag));
+        check!(if_range_matches(&if_range(date), &headers(header::LAST_MODIFIED, date)));
+        check!(!if_range_matches(&if_range(date), &etag));
+    }
+}
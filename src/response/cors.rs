@@ -0,0 +1,157 @@
+use std::time::Duration;
+
+use actix_http::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+
+use super::Respondable;
+
+/// Respondable for a complete response to a CORS preflight `OPTIONS` request, for endpoints that handle CORS
+/// themselves instead of through middleware.
+///
+/// This is a `204 No Content` response with no body, and is created with `cors_preflight`.
+#[derive(Debug)]
+pub struct CorsPreflightRespondable {
+    headers: HeaderMap,
+}
+
+/// Build the response to a CORS preflight request, allowing the provided origin to make requests with the
+/// provided methods and request headers, and allowing the client to cache that for `max_age`.
+///
+/// The origin must be either `*` or a serialized origin, e.g. `https://example.com:8443`, with no path. Any other
+/// value, e.g. an `Origin` header from the request that isn't well-formed, is logged and the
+/// `Access-Control-Allow-Origin` header is left out, so the preflight fails. A specific origin also adds `Vary:
+/// Origin`, since the response then depends on the request. The max age is sent in whole seconds, rounded down.
+///
+/// # Parameters
+/// - `origin` - The origin that is allowed, typically the `Origin` header of the request
+/// - `methods` - The methods that are allowed
+/// - `headers` - The request headers that are allowed. If empty then no `Access-Control-Allow-Headers` is sent
+/// - `max_age` - How long the client can cache the preflight response for
+pub fn cors_preflight(
+    origin: &str,
+    methods: &[Method],
+    headers: &[&str],
+    max_age: Duration,
+) -> CorsPreflightRespondable {
+    let mut response = HeaderMap::new();
+
+    if origin == "*" {
+        response.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+    } else {
+        match HeaderValue::from_str(origin).ok().filter(|_| is_origin(origin)) {
+            Some(value) => {
+                response.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, value);
+                response.insert(header::VARY, HeaderValue::from_static("Origin"));
+            },
+            None => {
+                tracing::error!(origin = ?origin, "Invalid CORS origin");
+            },
+        };
+    }
+
+    let methods = methods.iter().map(Method::as_str).collect::<Vec<_>>().join(", ");
+    if let Ok(value) = HeaderValue::from_str(&methods) {
+        response.insert(header::ACCESS_CONTROL_ALLOW_METHODS, value);
+    }
+
+    if !headers.is_empty() {
+        match HeaderValue::from_str(&headers.join(", ")) {
+            Ok(value) => {
+                response.insert(header::ACCESS_CONTROL_ALLOW_HEADERS, value);
+            },
+            Err(_) => {
+                tracing::error!(headers = ?headers, "Invalid CORS request headers");
+            },
+        };
+    }
+
+    response.insert(header::ACCESS_CONTROL_MAX_AGE, HeaderValue::from(max_age.as_secs()));
+
+    CorsPreflightRespondable { headers: response }
+}
+
+/// Determine if the provided string is a serialized origin, as defined by RFC 6454 section 6.2 - a scheme, a host
+/// and an optional port, with no path, query or fragment.
+fn is_origin(origin: &str) -> bool {
+    let (scheme, authority) = match origin.split_once("://") {
+        Some(parts) => parts,
+        None => return false,
+    };
+    // IPv6 hosts are in brackets, and contain colons of their own.
+    let host_end = if authority.starts_with('[') { authority.find(']').map_or(0, |end| end + 1) } else { 0 };
+    let (host, port) = match authority[host_end..].find(':') {
+        Some(colon) => (&authority[..host_end + colon], Some(&authority[host_end + colon + 1..])),
+        None => (authority, None),
+    };
+
+    let scheme_valid = scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme.chars().all(|c| c.is_ascii_alphanumeric() || "+-.".contains(c));
+    let host_valid = !host.is_empty() && host.chars().all(|c| c.is_ascii_alphanumeric() || "-.[]:".contains(c));
+    let port_valid = port.is_none_or(|port| !port.is_empty() && port.chars().all(|c| c.is_ascii_digit()));
+
+    scheme_valid && host_valid && port_valid
+}
+
+impl Respondable for CorsPreflightRespondable {
+    type Body = ();
+
+    fn status_code(&self) -> StatusCode {
+        StatusCode::NO_CONTENT
+    }
+
+    fn headers(&self) -> HeaderMap {
+        self.headers.clone()
+    }
+
+    fn has_body(&self) -> bool {
+        false
+    }
+
+    fn body(self) -> Self::Body {}
+
+    fn into_parts(self) -> (StatusCode, HeaderMap, Self::Body) {
+        (StatusCode::NO_CONTENT, self.headers, ())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{test::TestRequest, Responder};
+    use assert2::check;
+
+    use super::*;
+    use crate::response::Response;
+
+    #[test]
+    fn preflight() {
+        let req = TestRequest::default().to_http_request();
+        let respondable = cors_preflight(
+            "https://example.com:8443",
+            &[Method::GET, Method::PUT],
+            &["Content-Type", "If-Match"],
+            Duration::from_millis(600_500),
+        );
+        let response = Response::new(respondable).respond_to(&req);
+        let header = |name| response.headers().get(name).unwrap().to_str().unwrap();
+
+        check!(response.status() == StatusCode::NO_CONTENT);
+        check!(header(header::ACCESS_CONTROL_ALLOW_ORIGIN) == "https://example.com:8443");
+        check!(header(header::VARY) == "Origin");
+        check!(header(header::ACCESS_CONTROL_ALLOW_METHODS) == "GET, PUT");
+        check!(header(header::ACCESS_CONTROL_ALLOW_HEADERS) == "Content-Type, If-Match");
+        check!(header(header::ACCESS_CONTROL_MAX_AGE) == "600");
+    }
+
+    #[test]
+    fn origins() {
+        check!(is_origin("https://example.com"));
+        check!(is_origin("http://localhost:8080"));
+        check!(is_origin("http://[::1]:8080"));
+        check!(!is_origin("https://example.com/path"));
+        check!(!is_origin("https://example.com:port"));
+        check!(!is_origin("example.com"));
+
+        let respondable = cors_preflight("https://evil.com\r\nX-Injected: 1", &[Method::GET], &[], Duration::ZERO);
+        check!(respondable.headers().get(header::ACCESS_CONTROL_ALLOW_ORIGIN).is_none());
+        check!(respondable.headers().get(header::ACCESS_CONTROL_ALLOW_HEADERS).is_none());
+    }
+}
@@ -0,0 +1,380 @@
+use std::fmt;
+
+use actix_http::http::{
+    header::{self, Header},
+    HeaderMap, HeaderValue, StatusCode,
+};
+use actix_web::http::header::{ContentDisposition, DispositionParam, DispositionType};
+use serde::{
+    de::{MapAccess, Visitor},
+    ser::Error as _,
+    Deserialize, Deserializer, Serialize, Serializer,
+};
+use serde_json::Value;
+
+use super::Respondable;
+
+/// Respondable for a collection of flat records rendered as CSV, served as `text/csv; charset=utf-8`.
+///
+/// The header row holds the field names of the first record, in the order they are serialized, and every record
+/// is then written as a row with a cell for each of those fields. Cells are quoted as described by RFC 4180, and
+/// rows end with `\r\n`. Strings are written as they are, numbers and booleans in their JSON representation, and
+/// nulls and fields missing from a record as empty cells.
+///
+/// Spreadsheet applications treat cells starting with `=`, `+`, `-` or `@` as formulas, so a download built from
+/// user-provided strings could run arbitrary formulas when it is opened. To prevent this, strings starting with
+/// any of those, or with a tab or carriage return, are prefixed with `'` so that they are shown as text. Numbers
+/// are never escaped. This can be turned off with `with_formula_escaping` for clients that read the CSV directly.
+///
+/// Records must serialize as flat objects, such as structs whose fields are all scalars. A record that serializes
+/// as anything else, has a field that is itself an array or an object, or has a field that isn't in the header
+/// row fails to serialize, and so gives a `500 Internal Server Error` problem.
+///
+/// # Types
+/// - `T` - The type of the records
+pub struct CsvRespondable<T>
+where
+    T: Serialize,
+{
+    status_code:     StatusCode,
+    headers:         HeaderMap,
+    records:         Vec<T>,
+    escape_formulas: bool,
+}
+
+/// The body of a `CsvRespondable`, ready to be serialized.
+///
+/// Serializing it other than through `Respondable::serialize_body`, e.g. when it is embedded in a HAL response,
+/// gives the rendered CSV document as a single string, with formulas escaped in the same way.
+///
+/// # Types
+/// - `T` - The type of the records
+pub struct CsvBody<T> {
+    /// The records to render, one per row
+    pub records:         Vec<T>,
+    /// Whether strings that spreadsheets would treat as formulas are escaped
+    pub escape_formulas: bool,
+}
+
+impl<T> Serialize for CsvBody<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let csv = self.render().map_err(S::Error::custom)?;
+        let csv = String::from_utf8(csv).map_err(S::Error::custom)?;
+
+        serializer.serialize_str(&csv)
+    }
+}
+
+impl<T> CsvBody<T>
+where
+    T: Serialize,
+{
+    /// Render the records as a CSV document.
+    ///
+    /// # Errors
+    /// If any of the records isn't a flat object with the fields of the header row
+    fn render(&self) -> serde_json::Result<Vec<u8>> {
+        let mut csv = Vec::new();
+        let mut columns: Option<Vec<String>> = None;
+
+        for record in &self.records {
+            // Going through `Value` would sort the fields by name, so this parses the serialized record instead.
+            let Record(fields) = serde_json::from_slice(&serde_json::to_vec(record)?)?;
+
+            let columns = match &columns {
+                Some(columns) => columns,
+                None => {
+                    let names = fields.iter().map(|(name, _)| name.clone()).collect::<Vec<_>>();
+                    write_row(&mut csv, names.iter().map(String::as_str));
+                    columns.insert(names)
+                },
+            };
+
+            if let Some((name, _)) = fields.iter().find(|(name, _)| !columns.contains(name)) {
+                return Err(serde_json::Error::custom(format!("CSV record has unexpected field {}", name)));
+            }
+
+            let mut cells = Vec::with_capacity(columns.len());
+            for column in columns {
+                let cell = match fields.iter().find(|(name, _)| name == column).map(|(_, value)| value) {
+                    None | Some(Value::Null) => String::new(),
+                    Some(Value::String(value)) if self.escape_formulas && value.starts_with(FORMULA_PREFIXES) => {
+                        format!("'{}", value)
+                    },
+                    Some(Value::String(value)) => value.clone(),
+                    Some(Value::Array(_)) | Some(Value::Object(_)) => {
+                        return Err(serde_json::Error::custom(format!("CSV field {} is not a scalar", column)));
+                    },
+                    Some(value) => value.to_string(),
+                };
+                cells.push(cell);
+            }
+            write_row(&mut csv, cells.iter().map(String::as_str));
+        }
+
+        Ok(csv)
+    }
+}
+
+impl<T> CsvRespondable<T>
+where
+    T: Serialize,
+{
+    /// Create a new instance of the `CsvRespondable` struct rendering the provided records.
+    ///
+    /// # Parameters
+    /// - `records` - The records to render, one per row
+    pub fn new<C>(records: C) -> Self
+    where
+        C: IntoIterator<Item = T>,
+    {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/csv; charset=utf-8"));
+
+        Self {
+            status_code: StatusCode::OK,
+            headers,
+            records: records.into_iter().collect(),
+            escape_formulas: true,
+        }
+    }
+
+    /// Specify whether strings that spreadsheet applications would treat as formulas are prefixed with `'`.
+    /// Defaults to `true`.
+    ///
+    /// # Parameters
+    /// - `escape` - Whether to escape formulas
+    pub fn with_formula_escaping(mut self, escape: bool) -> Self {
+        self.escape_formulas = escape;
+        self
+    }
+
+    /// Specify the status code to use.
+    ///
+    /// # Parameters
+    /// - `status_code` - The status code to use
+    pub fn with_status_code(mut self, status_code: StatusCode) -> Self {
+        self.status_code = status_code;
+        self
+    }
+
+    /// Specify a header to include in the response.
+    ///
+    /// # Parameters
+    /// - `header` - The header to add to the response.
+    pub fn with_header<H>(mut self, header: H) -> Self
+    where
+        H: Header,
+    {
+        let name = H::name();
+        match header.try_into_value() {
+            Ok(value) => {
+                self.headers.append(name, value);
+            },
+            Err(_) => {
+                tracing::error!(name = ?name, "Failed to process header");
+            },
+        };
+
+        self
+    }
+
+    /// Specify that the response is a download to be saved with the provided filename, with a
+    /// `Content-Disposition: attachment` header.
+    ///
+    /// # Parameters
+    /// - `filename` - The filename to save the download as, e.g. `users.csv`
+    pub fn with_attachment<S>(self, filename: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.with_header(ContentDisposition {
+            disposition: DispositionType::Attachment,
+            parameters:  vec![DispositionParam::Filename(filename.into())],
+        })
+    }
+}
+
+impl<T> Respondable for CsvRespondable<T>
+where
+    T: Serialize,
+{
+    type Body = CsvBody<T>;
+
+    fn status_code(&self) -> StatusCode {
+        self.status_code
+    }
+
+    fn headers(&self) -> HeaderMap {
+        self.headers.clone()
+    }
+
+    fn media_types(&self) -> Vec<&'static str> {
+        vec!["text/csv"]
+    }
+
+    fn body(self) -> Self::Body {
+        CsvBody {
+            records:         self.records,
+            escape_formulas: self.escape_formulas,
+        }
+    }
+
+    fn into_parts(self) -> (StatusCode, HeaderMap, Self::Body) {
+        let body = CsvBody {
+            records:         self.records,
+            escape_formulas: self.escape_formulas,
+        };
+        (self.status_code, self.headers, body)
+    }
+
+    fn serialize_body(body: Self::Body, _omit_nulls: bool) -> serde_json::Result<Vec<u8>> {
+        body.render()
+    }
+}
+
+/// The characters that make spreadsheet applications treat a cell as a formula when it starts with them.
+const FORMULA_PREFIXES: &[char] = &['=', '+', '-', '@', '\t', '\r'];
+
+/// Write a single row of a CSV document, quoting any cells that need it.
+///
+/// # Parameters
+/// - `csv` - The CSV document to write to
+/// - `cells` - The cells of the row
+fn write_row<'a, I>(csv: &mut Vec<u8>, cells: I)
+where
+    I: Iterator<Item = &'a str>,
+{
+    for (index, cell) in cells.enumerate() {
+        if index > 0 {
+            csv.push(b',');
+        }
+        if cell.contains([',', '"', '\r', '\n']) {
+            csv.push(b'"');
+            csv.extend_from_slice(cell.replace('"', "\"\"").as_bytes());
+            csv.push(b'"');
+        } else {
+            csv.extend_from_slice(cell.as_bytes());
+        }
+    }
+    csv.extend_from_slice(b"\r\n");
+}
+
+/// The fields of a single record, in the order they were serialized.
+struct Record(Vec<(String, Value)>);
+
+impl<'de> Deserialize<'de> for Record {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(RecordVisitor)
+    }
+}
+
+/// Visitor to deserialize the fields of a record while retaining their order.
+struct RecordVisitor;
+
+impl<'de> Visitor<'de> for RecordVisitor {
+    type Value = Record;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a CSV record with named fields")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let mut fields = Vec::new();
+        while let Some(field) = map.next_entry()? {
+            fields.push(field);
+        }
+
+        Ok(Record(fields))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{body::AnyBody, test::TestRequest, Responder};
+    use assert2::{check, let_assert};
+    use serde_json::json;
+
+    use super::*;
+    use crate::{hal::HalRespondable, response::Response};
+
+    #[derive(Serialize)]
+    struct User {
+        name:  &'static str,
+        email: Option<&'static str>,
+        age:   u32,
+    }
+
+    #[test]
+    fn csv_rows() {
+        let users = vec![
+            User {
+                name:  "Graham",
+                email: Some("graham@example.com"),
+                age:   42,
+            },
+            User {
+                name:  "Smith, \"Jo\"",
+                email: None,
+                age:   7,
+            },
+        ];
+        let body = CsvRespondable::new(users).body();
+        let body = CsvRespondable::<User>::serialize_body(body, false).unwrap();
+
+        check!(body == b"name,email,age\r\nGraham,graham@example.com,42\r\n\"Smith, \"\"Jo\"\"\",,7\r\n");
+    }
+
+    #[test]
+    fn nested_fields_rejected() {
+        let body = CsvRespondable::new(vec![json!({"name": "Graham", "tags": ["admin"]})]).body();
+
+        check!(CsvRespondable::<Value>::serialize_body(body, false).is_err());
+    }
+
+    #[test]
+    fn formulas_escaped() {
+        let records = || vec![json!({"name": "=HYPERLINK(\"http://evil.com\")", "note": "@SUM(A1)", "balance": -5})];
+        let escaped = CsvRespondable::new(records()).body();
+        let unescaped = CsvRespondable::new(records()).with_formula_escaping(false).body();
+
+        let escaped = CsvRespondable::<Value>::serialize_body(escaped, false).unwrap();
+        check!(escaped == b"balance,name,note\r\n-5,\"'=HYPERLINK(\"\"http://evil.com\"\")\",'@SUM(A1)\r\n");
+        let unescaped = CsvRespondable::<Value>::serialize_body(unescaped, false).unwrap();
+        check!(unescaped == b"balance,name,note\r\n-5,\"=HYPERLINK(\"\"http://evil.com\"\")\",@SUM(A1)\r\n");
+    }
+
+    #[test]
+    fn embedded() {
+        let respondable = HalRespondable::new(json!({}))
+            .with_embedded("export", CsvRespondable::new(vec![json!({"name": "=1+1"})]));
+        let body = HalRespondable::<Value>::serialize_body(respondable.body(), false).unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+
+        check!(body["_embedded"]["export"] == "name\r\n'=1+1\r\n");
+    }
+
+    #[test]
+    fn attachment() {
+        let req = TestRequest::default().to_http_request();
+        let respondable = CsvRespondable::new(vec![json!({"id": 1})]).with_attachment("users.csv");
+        let response = Response::new(respondable).respond_to(&req);
+
+        check!(response.headers().get(header::CONTENT_TYPE).unwrap() == "text/csv; charset=utf-8");
+        check!(response.headers().get(header::CONTENT_DISPOSITION).unwrap() == "attachment; filename=\"users.csv\"");
+        let_assert!(AnyBody::Bytes(body) = response.body());
+        check!(body.as_ref() == b"id\r\n1\r\n");
+    }
+}
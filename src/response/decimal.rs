@@ -0,0 +1,98 @@
+/// Rewrite every number in a serialized JSON document that uses scientific notation, e.g. `1e-7`, in plain
+/// decimal notation instead, e.g. `0.0000001`.
+///
+/// Each number is rewritten as the shortest decimal that parses back to exactly the same `f64`, so no precision
+/// is lost and nothing is rounded beyond what serializing an `f64` already did. Numbers that become whole keep a
+/// trailing `.0`, so that they are still read as floats. Very large and very small magnitudes produce very long
+/// numbers, e.g. `1e300` becomes a 1 followed by 300 zeros. Numbers already in decimal notation, and the contents
+/// of strings, are copied untouched.
+///
+/// # Parameters
+/// - `json` - The serialized JSON document
+///
+/// # Returns
+/// The JSON document with every number in decimal notation
+pub(crate) fn normalize(json: &[u8]) -> Vec<u8> {
+    let mut normalized = Vec::with_capacity(json.len());
+    let mut in_string = false;
+    let mut escaped = false;
+    let mut i = 0;
+
+    while i < json.len() {
+        let byte = json[i];
+        if in_string {
+            match byte {
+                _ if escaped => escaped = false,
+                b'\\' => escaped = true,
+                b'"' => in_string = false,
+                _ => {},
+            }
+        } else if byte == b'"' {
+            in_string = true;
+        } else if byte == b'-' || byte.is_ascii_digit() {
+            let end = json[i..]
+                .iter()
+                .position(|b| !matches!(b, b'0'..=b'9' | b'-' | b'+' | b'.' | b'e' | b'E'))
+                .map_or(json.len(), |len| i + len);
+            write_decimal(&mut normalized, &json[i..end]);
+            i = end;
+            continue;
+        }
+
+        normalized.push(byte);
+        i += 1;
+    }
+
+    normalized
+}
+
+/// Write a single JSON number in decimal notation.
+///
+/// # Parameters
+/// - `output` - The buffer to write the number to
+/// - `number` - The number as it was serialized
+fn write_decimal(output: &mut Vec<u8>, number: &[u8]) {
+    let value = std::str::from_utf8(number)
+        .ok()
+        .filter(|number| number.contains(['e', 'E']))
+        .and_then(|number| number.parse::<f64>().ok())
+        .filter(|value| value.is_finite());
+
+    match value {
+        Some(value) => {
+            let decimal = value.to_string();
+            output.extend_from_slice(decimal.as_bytes());
+            if !decimal.contains('.') {
+                output.extend_from_slice(b".0");
+            }
+        },
+        None => output.extend_from_slice(number),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert2::check;
+    use serde_json::json;
+
+    use super::*;
+
+    fn normalized(value: serde_json::Value) -> String {
+        String::from_utf8(normalize(&serde_json::to_vec(&value).unwrap())).unwrap()
+    }
+
+    #[test]
+    fn scientific_notation() {
+        check!(normalized(json!(1e-7)) == "0.0000001");
+        check!(normalized(json!(-2.5e-10)) == "-0.00000000025");
+        check!(normalized(json!(1e21)) == "1000000000000000000000.0");
+        check!(normalized(json!([1.5e-7, 12, 0.25])) == "[0.00000015,12,0.25]");
+    }
+
+    #[test]
+    fn strings_untouched() {
+        let value = json!({"1e-7": "1e-7 \"2e-8\"", "v": 3e-9});
+
+        check!(normalized(value) == r#"{"1e-7":"1e-7 \"2e-8\"","v":0.000000003}"#);
+    }
+}
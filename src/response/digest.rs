@@ -0,0 +1,98 @@
+use actix_http::http::{HeaderName, HeaderValue};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use sha2::{Digest, Sha256, Sha512};
+
+/// The `Content-Digest` header, as defined by RFC 9530.
+pub(crate) const CONTENT_DIGEST: HeaderName = HeaderName::from_static("content-digest");
+
+/// The algorithms that can be used to compute the `Content-Digest` of a response.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    /// SHA-256, registered as `sha-256`
+    Sha256,
+    /// SHA-512, registered as `sha-512`
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    /// The key of the algorithm in the `Content-Digest` header.
+    fn key(self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha256 => "sha-256",
+            DigestAlgorithm::Sha512 => "sha-512",
+        }
+    }
+
+    /// Compute the digest of the provided bytes.
+    ///
+    /// # Parameters
+    /// - `bytes` - The bytes to compute the digest of
+    fn digest(self, bytes: &[u8]) -> Vec<u8> {
+        match self {
+            DigestAlgorithm::Sha256 => Sha256::digest(bytes).to_vec(),
+            DigestAlgorithm::Sha512 => Sha512::digest(bytes).to_vec(),
+        }
+    }
+}
+
+/// Build the `Content-Digest` header for a serialized body, as a structured field dictionary with the
+/// base64-encoded digest of every algorithm, e.g. `sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:`.
+///
+/// # Parameters
+/// - `algorithms` - The algorithms to compute the digest with
+/// - `body` - The serialized body
+pub(crate) fn header(algorithms: &[DigestAlgorithm], body: &[u8]) -> Option<HeaderValue> {
+    let value = algorithms
+        .iter()
+        .map(|algorithm| format!("{}=:{}:", algorithm.key(), STANDARD.encode(algorithm.digest(body))))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    HeaderValue::from_str(&value).ok()
+}
+
+/// Rebuild a `Content-Digest` header for a body that has changed, using the same algorithms as the original
+/// header. Algorithms that aren't supported are left out.
+///
+/// # Parameters
+/// - `original` - The original `Content-Digest` header
+/// - `body` - The new serialized body
+///
+/// # Returns
+/// The new header, or `None` if none of the original algorithms are supported
+pub(crate) fn recompute(original: &HeaderValue, body: &[u8]) -> Option<HeaderValue> {
+    let algorithms: Vec<_> = original
+        .to_str()
+        .ok()?
+        .split(',')
+        .filter_map(|entry| match entry.split('=').next().map(str::trim) {
+            Some("sha-256") => Some(DigestAlgorithm::Sha256),
+            Some("sha-512") => Some(DigestAlgorithm::Sha512),
+            _ => None,
+        })
+        .collect();
+
+    if algorithms.is_empty() {
+        None
+    } else {
+        header(&algorithms, body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert2::check;
+
+    use super::*;
+
+    #[test]
+    fn digest_header() {
+        let header = header(&[DigestAlgorithm::Sha256, DigestAlgorithm::Sha512], br#"{"hello": "world"}"#);
+
+        check!(
+            header.unwrap()
+                == "sha-256=:X48E9qOokqqrvdts8nOJRJN3OWDUoyWxBf7kbu9DBPE=:, \
+                    sha-512=:WZDPaVn/7XgHaAy8pmojAkGWoRx2UFChF41A2svX+TaPm+AbwAgBWnrIiYllu7BNNyealdVLvRwEmTHWXvJwew==:"
+        );
+    }
+}
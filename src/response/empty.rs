@@ -0,0 +1,79 @@
+use actix_http::http::StatusCode;
+
+use super::{Respondable, Response};
+
+/// Respondable for a response that has only a status code, with no body and no `Content-Type`.
+///
+/// `Respondable` can't be implemented directly for `()` or `StatusCode`, since `()` is already covered by the
+/// implementation for every `Serialize` type, and `StatusCode` could be given a `Serialize` implementation by
+/// its own crate. Instead a `StatusCode` converts into a `Response` of this type, so a handler can return
+/// `Response<EmptyRespondable>` and finish with `StatusCode::ACCEPTED.into()`.
+#[derive(Debug)]
+pub struct EmptyRespondable {
+    status_code: StatusCode,
+}
+
+impl EmptyRespondable {
+    /// Create a new empty response with the provided status code.
+    ///
+    /// # Parameters
+    /// - `status_code` - The status code to use
+    pub fn new(status_code: StatusCode) -> Self {
+        Self { status_code }
+    }
+}
+
+impl Default for EmptyRespondable {
+    /// Create a new `204 No Content` response.
+    fn default() -> Self {
+        Self::new(StatusCode::NO_CONTENT)
+    }
+}
+
+impl From<StatusCode> for EmptyRespondable {
+    fn from(status_code: StatusCode) -> Self {
+        Self::new(status_code)
+    }
+}
+
+impl From<StatusCode> for Response<EmptyRespondable> {
+    fn from(status_code: StatusCode) -> Self {
+        Self::new(status_code.into())
+    }
+}
+
+impl Respondable for EmptyRespondable {
+    type Body = ();
+
+    fn status_code(&self) -> StatusCode {
+        self.status_code
+    }
+
+    fn has_body(&self) -> bool {
+        false
+    }
+
+    fn body(self) -> Self::Body {}
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::http::header;
+    use actix_web::{test::TestRequest, Responder};
+    use assert2::check;
+
+    use super::*;
+
+    #[test]
+    fn empty_response() {
+        let req = TestRequest::default().to_http_request();
+        let response: Response<EmptyRespondable> = StatusCode::ACCEPTED.into();
+        let response = response.respond_to(&req);
+
+        check!(response.status() == StatusCode::ACCEPTED);
+        check!(response.headers().get(header::CONTENT_TYPE).is_none());
+
+        let response = Response::new(EmptyRespondable::default()).respond_to(&req);
+        check!(response.status() == StatusCode::NO_CONTENT);
+    }
+}
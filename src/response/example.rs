@@ -0,0 +1,94 @@
+use actix_http::http::{header, StatusCode};
+use serde::Serialize;
+
+use super::Respondable;
+
+/// An example of a response, for documentation tooling to include in the `examples` of an OpenAPI operation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseExample {
+    /// The status code of the response
+    pub status_code:  StatusCode,
+    /// The content type of the response, or `None` if it has no body
+    pub content_type: Option<String>,
+    /// The serialized body of the response, or `None` if it has no body
+    pub body:         Option<Vec<u8>>,
+}
+
+impl ResponseExample {
+    /// Parse the body of the example as JSON, for embedding directly in an OpenAPI document.
+    ///
+    /// # Returns
+    /// The body as JSON, or `None` if there is no body or it isn't JSON
+    pub fn json(&self) -> Option<serde_json::Value> {
+        self.body.as_ref().and_then(|body| serde_json::from_slice(body).ok())
+    }
+}
+
+/// Produce an example of the response that a respondable produces, without needing a request.
+///
+/// The example uses the default media type of the respondable, but is otherwise serialized exactly as it would be
+/// for a real response. This is intended for build and test time tooling, such as harvesting examples for API
+/// documentation, rather than for handling requests.
+///
+/// # Parameters
+/// - `respondable` - The respondable to produce an example of
+///
+/// # Errors
+/// If the body of the respondable fails to serialize
+pub fn example<R>(respondable: R) -> serde_json::Result<ResponseExample>
+where
+    R: Respondable,
+    R::Body: Serialize,
+{
+    let media_type = respondable.media_types()[0];
+    let has_body = respondable.has_body();
+    let omit_nulls = respondable.omits_nulls();
+    let (status_code, headers, body) = respondable.into_parts();
+
+    if !has_body {
+        return Ok(ResponseExample {
+            status_code,
+            content_type: None,
+            body: None,
+        });
+    }
+
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or(media_type)
+        .to_owned();
+
+    Ok(ResponseExample {
+        status_code,
+        content_type: Some(content_type),
+        body: Some(R::serialize_body(body, omit_nulls)?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use assert2::check;
+    use serde_json::json;
+
+    use super::*;
+    use crate::{hal::HalRespondable, response::EmptyRespondable};
+
+    #[test]
+    fn hal_example() {
+        let example = example(HalRespondable::new(json!({"id": 1})).with_status_code(StatusCode::CREATED)).unwrap();
+
+        check!(example.status_code == StatusCode::CREATED);
+        check!(example.content_type == Some("application/hal+json".to_owned()));
+        check!(example.json() == Some(json!({"id": 1, "_links": {}})));
+    }
+
+    #[test]
+    fn bodyless_example() {
+        let example = example(EmptyRespondable::default()).unwrap();
+
+        check!(example.status_code == StatusCode::NO_CONTENT);
+        check!(example.content_type == None);
+        check!(example.json() == None);
+    }
+}
@@ -0,0 +1,383 @@
+use std::{
+    io::{self, SeekFrom},
+    path::Path,
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_http::http::{
+    header::{self, Header, HttpDate, IntoHeaderValue},
+    HeaderMap, HeaderValue, Method, StatusCode,
+};
+use actix_web::{
+    body::{AnyBody, SizedStream},
+    web::Bytes,
+    HttpRequest, HttpResponse, Responder,
+};
+use futures_core::Stream;
+use tokio::{
+    fs::File,
+    io::{AsyncRead, AsyncSeek, ReadBuf},
+};
+
+use super::{bytes::parse_range, conditional};
+
+/// The maximum number of bytes of the file to read for each chunk sent to the client.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Response for the contents of a file, streamed from disk rather than read into memory first.
+///
+/// The `Content-Type` is guessed from the extension of the filename, falling back to `application/octet-stream`,
+/// and can be overridden with `with_content_type`. The `Content-Length` is the size of the file, and the
+/// `Last-Modified` header is its modification time where the platform provides one. Clients can request part of
+/// the file with a `Range` header, which is honoured by `with_range` exactly as for `BytesRespondable`.
+///
+/// The file is read in chunks of up to 64 KiB, and the next chunk is only read once the client has accepted the
+/// previous one. The status code and headers have already been sent by the time the file is read, so a failure to
+/// read it can't be reported to the client. Instead the error is logged and the connection is aborted, so that
+/// the client can tell the download is incomplete. The same happens if the file is shorter than it was when it
+/// was opened.
+///
+/// This responds directly rather than being a `Respondable`, since `Respondable` bodies are always serialized
+/// in full before being sent.
+pub struct FileRespondable {
+    status_code: StatusCode,
+    headers:     HeaderMap,
+    file:        File,
+    length:      u64,
+    range:       Option<(u64, u64)>,
+}
+
+impl FileRespondable {
+    /// Open the file at the provided path to send back to the client.
+    ///
+    /// # Parameters
+    /// - `path` - The path of the file, also used to guess its media type
+    ///
+    /// # Errors
+    /// If the file can't be opened or its metadata can't be read
+    pub async fn open<P>(path: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let file = File::open(path.as_ref()).await?;
+        Self::from_file(file, path).await
+    }
+
+    /// Send back an already open file to the client.
+    ///
+    /// The file is read from its start, whatever its current position.
+    ///
+    /// # Parameters
+    /// - `file` - The file to send back
+    /// - `filename` - The name of the file, used to guess its media type
+    ///
+    /// # Errors
+    /// If the metadata of the file can't be read
+    pub async fn from_file<P>(file: File, filename: P) -> io::Result<Self>
+    where
+        P: AsRef<Path>,
+    {
+        let metadata = file.metadata().await?;
+
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(guess_media_type(filename.as_ref())));
+        headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+        if let Ok(modified) = metadata.modified() {
+            if let Ok(value) = HttpDate::from(modified).try_into_value() {
+                headers.insert(header::LAST_MODIFIED, value);
+            }
+        }
+
+        Ok(Self {
+            status_code: StatusCode::OK,
+            headers,
+            file,
+            length: metadata.len(),
+            range: None,
+        })
+    }
+
+    /// Specify the media type of the file, instead of guessing it from the filename.
+    ///
+    /// # Parameters
+    /// - `media_type` - The media type to use for the `Content-Type` header
+    pub fn with_content_type(mut self, media_type: &str) -> Self {
+        match HeaderValue::from_str(media_type) {
+            Ok(value) => {
+                self.headers.insert(header::CONTENT_TYPE, value);
+            },
+            Err(_) => {
+                tracing::error!(media_type = ?media_type, "Failed to process content type");
+            },
+        };
+
+        self
+    }
+
+    /// Specify the status code to use.
+    ///
+    /// # Parameters
+    /// - `status_code` - The status code to use
+    pub fn with_status_code(mut self, status_code: StatusCode) -> Self {
+        self.status_code = status_code;
+        self
+    }
+
+    /// Specify a header to include in the response.
+    ///
+    /// # Parameters
+    /// - `header` - The header to add to the response.
+    pub fn with_header<H>(mut self, header: H) -> Self
+    where
+        H: Header,
+    {
+        let name = H::name();
+        match header.try_into_value() {
+            Ok(value) => {
+                self.headers.append(name, value);
+            },
+            Err(_) => {
+                tracing::error!(name = ?name, "Failed to process header");
+            },
+        };
+
+        self
+    }
+
+    /// Serve only the part of the file requested by the `Range` header of the request, as a `206 Partial
+    /// Content`. A range that can't be satisfied gives a `416 Range Not Satisfiable` instead.
+    ///
+    /// The range is only served if the `If-Range` header of the request, if any, matches the `ETag` or
+    /// `Last-Modified` header of this response. Otherwise the file has changed since the client fetched the
+    /// earlier part, so the whole file is served instead. The whole file is also served for anything other than a
+    /// `200 OK` response to a `GET` request, and for requests for several ranges at once.
+    ///
+    /// # Parameters
+    /// - `req` - The request being responded to
+    pub fn with_range(mut self, req: &HttpRequest) -> Self {
+        if req.method() != Method::GET || self.status_code != StatusCode::OK {
+            return self;
+        }
+
+        let range = match req.headers().get(header::RANGE).and_then(|value| value.to_str().ok()) {
+            Some(range) => range,
+            None => return self,
+        };
+        if !conditional::if_range_matches(req, &self.headers) {
+            return self;
+        }
+
+        match parse_range(range, self.length) {
+            Some(Ok((start, end))) => {
+                self.set_content_range(&format!("bytes {}-{}/{}", start, end, self.length));
+                self.status_code = StatusCode::PARTIAL_CONTENT;
+                self.range = Some((start, end - start + 1));
+            },
+            Some(Err(())) => {
+                self.set_content_range(&format!("bytes */{}", self.length));
+                self.status_code = StatusCode::RANGE_NOT_SATISFIABLE;
+                self.headers.remove(header::CONTENT_TYPE);
+            },
+            None => {},
+        }
+
+        self
+    }
+
+    /// Set the `Content-Range` header of the response.
+    ///
+    /// # Parameters
+    /// - `content_range` - The value of the header
+    fn set_content_range(&mut self, content_range: &str) {
+        if let Ok(value) = HeaderValue::from_str(content_range) {
+            self.headers.insert(header::CONTENT_RANGE, value);
+        }
+    }
+}
+
+/// Guess the media type of a file from the extension of its name.
+///
+/// # Parameters
+/// - `filename` - The name of the file
+///
+/// # Returns
+/// The media type, or `application/octet-stream` if the extension isn't recognised
+fn guess_media_type(filename: &Path) -> &'static str {
+    let extension = filename
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .map(str::to_ascii_lowercase)
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "html" | "htm" => "text/html; charset=utf-8",
+        "txt" => "text/plain; charset=utf-8",
+        "csv" => "text/csv; charset=utf-8",
+        "css" => "text/css; charset=utf-8",
+        "js" => "text/javascript; charset=utf-8",
+        "json" => "application/json",
+        "xml" => "application/xml",
+        "pdf" => "application/pdf",
+        "zip" => "application/zip",
+        "gz" => "application/gzip",
+        "xlsx" => "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "png" => "image/png",
+        "jpg" | "jpeg" => "image/jpeg",
+        "gif" => "image/gif",
+        "svg" => "image/svg+xml",
+        "webp" => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+impl Responder for FileRespondable {
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code);
+
+        for (key, value) in self.headers {
+            response.append_header((key, value));
+        }
+
+        let response = if self.status_code == StatusCode::RANGE_NOT_SATISFIABLE {
+            response.finish()
+        } else {
+            let (start, length) = self.range.unwrap_or((0, self.length));
+            let stream = FileStream {
+                file:      self.file,
+                seek:      Some(start),
+                seeking:   false,
+                remaining: length,
+            };
+            response.body(AnyBody::from_message(SizedStream::new(length, stream)))
+        };
+
+        #[cfg(feature = "metrics")]
+        super::metrics::record(&response);
+
+        response
+    }
+}
+
+/// Stream of the chunks of a file for a `FileRespondable`.
+struct FileStream {
+    file:      File,
+    /// The position to seek to before reading, or `None` once the seek has completed.
+    seek:      Option<u64>,
+    /// Whether the seek has been started.
+    seeking:   bool,
+    /// The number of bytes of the file still to send.
+    remaining: u64,
+}
+
+impl FileStream {
+    /// Stop the stream because of an error, which is logged.
+    ///
+    /// # Parameters
+    /// - `e` - The error
+    fn fail(&mut self, e: io::Error) -> Poll<Option<io::Result<Bytes>>> {
+        tracing::error!(e = ?e, "Failed to read file, aborting response");
+        self.seek = None;
+        self.remaining = 0;
+
+        Poll::Ready(Some(Err(e)))
+    }
+}
+
+impl Stream for FileStream {
+    type Item = io::Result<Bytes>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if let Some(position) = this.seek {
+            if !this.seeking {
+                if let Err(e) = Pin::new(&mut this.file).start_seek(SeekFrom::Start(position)) {
+                    return this.fail(e);
+                }
+                this.seeking = true;
+            }
+            match Pin::new(&mut this.file).poll_complete(cx) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) => return this.fail(e),
+                Poll::Ready(Ok(_)) => this.seek = None,
+            }
+        }
+
+        if this.remaining == 0 {
+            return Poll::Ready(None);
+        }
+
+        let mut chunk = vec![0; this.remaining.min(CHUNK_SIZE as u64) as usize];
+        let mut buf = ReadBuf::new(&mut chunk);
+        match Pin::new(&mut this.file).poll_read(cx, &mut buf) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Err(e)) => this.fail(e),
+            Poll::Ready(Ok(())) if buf.filled().is_empty() => {
+                this.fail(io::Error::new(io::ErrorKind::UnexpectedEof, "File is shorter than expected"))
+            },
+            Poll::Ready(Ok(())) => {
+                let read = buf.filled().len();
+                chunk.truncate(read);
+                this.remaining -= read as u64;
+                Poll::Ready(Some(Ok(Bytes::from(chunk))))
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{body, rt::System, test::TestRequest};
+    use assert2::check;
+
+    use super::*;
+
+    fn respond(name: &str, range: Option<&str>) -> (HttpResponse<()>, Bytes) {
+        let path = std::env::temp_dir().join(format!("sazzer-actix-response-{}", name));
+        std::fs::write(&path, "0123456789").unwrap();
+
+        System::new().block_on(async move {
+            let mut req = TestRequest::default();
+            if let Some(range) = range {
+                req = req.insert_header((header::RANGE, range));
+            }
+            let req = req.to_http_request();
+
+            let respondable = FileRespondable::open(&path).await.unwrap().with_range(&req);
+            let (response, body) = respondable.respond_to(&req).into_parts();
+            let body = body::to_bytes(body).await.unwrap();
+            std::fs::remove_file(&path).unwrap();
+
+            (response, body)
+        })
+    }
+
+    #[test]
+    fn whole_file() {
+        let (response, body) = respond("whole.csv", None);
+
+        check!(response.status() == StatusCode::OK);
+        check!(response.headers().get(header::CONTENT_TYPE).unwrap() == "text/csv; charset=utf-8");
+        check!(response.headers().get(header::LAST_MODIFIED).is_some());
+        check!(body.as_ref() == b"0123456789");
+    }
+
+    #[test]
+    fn partial_file() {
+        let (response, body) = respond("partial.bin", Some("bytes=3-5"));
+
+        check!(response.status() == StatusCode::PARTIAL_CONTENT);
+        check!(response.headers().get(header::CONTENT_TYPE).unwrap() == "application/octet-stream");
+        check!(response.headers().get(header::CONTENT_RANGE).unwrap() == "bytes 3-5/10");
+        check!(body.as_ref() == b"345");
+    }
+
+    #[test]
+    fn unsatisfiable_range() {
+        let (response, body) = respond("unsatisfiable.bin", Some("bytes=10-"));
+
+        check!(response.status() == StatusCode::RANGE_NOT_SATISFIABLE);
+        check!(body.is_empty());
+    }
+}
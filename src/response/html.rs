@@ -0,0 +1,125 @@
+use actix_http::http::{
+    header::{self, Header},
+    HeaderMap, HeaderValue, StatusCode,
+};
+
+use super::Respondable;
+
+/// Respondable for an HTML document, allowing resources to be browsed by humans.
+///
+/// The document is served as `text/html; charset=utf-8` exactly as provided.
+pub struct HtmlRespondable {
+    status_code: StatusCode,
+    headers:     HeaderMap,
+    body:        String,
+}
+
+impl HtmlRespondable {
+    /// Create a new instance of the `HtmlRespondable` struct wrapping the provided document.
+    ///
+    /// # Parameters
+    /// - `html` - The rendered HTML document to send back to the client.
+    pub fn new<S>(html: S) -> Self
+    where
+        S: Into<String>,
+    {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("text/html; charset=utf-8"));
+
+        Self {
+            status_code: StatusCode::OK,
+            headers,
+            body: html.into(),
+        }
+    }
+
+    /// Create a new instance of the `HtmlRespondable` struct by rendering the provided value with a template.
+    ///
+    /// # Parameters
+    /// - `value` - The value to render.
+    /// - `template` - The template to render the value with.
+    pub fn render<T, F>(value: &T, template: F) -> Self
+    where
+        F: FnOnce(&T) -> String,
+    {
+        Self::new(template(value))
+    }
+
+    /// Specify the status code to use.
+    ///
+    /// # Parameters
+    /// - `status_code` - The status code to use
+    pub fn with_status_code(mut self, status_code: StatusCode) -> Self {
+        self.status_code = status_code;
+        self
+    }
+
+    /// Specify a header to include in the response.
+    ///
+    /// # Parameters
+    /// - `header` - The header to add to the response.
+    pub fn with_header<H>(mut self, header: H) -> Self
+    where
+        H: Header,
+    {
+        let name = H::name();
+        match header.try_into_value() {
+            Ok(value) => {
+                self.headers.append(name, value);
+            },
+            Err(_) => {
+                tracing::error!(name = ?name, "Failed to process header");
+            },
+        };
+
+        self
+    }
+}
+
+impl Respondable for HtmlRespondable {
+    type Body = String;
+
+    fn status_code(&self) -> StatusCode {
+        self.status_code
+    }
+
+    fn headers(&self) -> HeaderMap {
+        self.headers.clone()
+    }
+
+    fn media_types(&self) -> Vec<&'static str> {
+        vec!["text/html"]
+    }
+
+    fn body(self) -> Self::Body {
+        self.body
+    }
+
+    fn into_parts(self) -> (StatusCode, HeaderMap, Self::Body) {
+        (self.status_code, self.headers, self.body)
+    }
+
+    fn serialize_body(body: Self::Body, _omit_nulls: bool) -> serde_json::Result<Vec<u8>> {
+        Ok(body.into_bytes())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{body::AnyBody, test::TestRequest, Responder};
+    use assert2::{check, let_assert};
+
+    use super::*;
+    use crate::response::Response;
+
+    #[test]
+    fn html_document() {
+        let req = TestRequest::default().to_http_request();
+        let respondable = HtmlRespondable::render(&"World", |name| format!("<p>Hello, {}</p>", name));
+        let response = Response::new(respondable).respond_to(&req);
+
+        check!(response.headers().get(header::CONTENT_TYPE).unwrap() == "text/html; charset=utf-8");
+        let_assert!(AnyBody::Bytes(body) = response.body());
+        check!(body.as_ref() == b"<p>Hello, World</p>");
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    cell::RefCell,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Whether to serialize response bodies into a buffer that is reused by every response on the same thread.
+static REUSE_BUFFERS: AtomicBool = AtomicBool::new(false);
+
+/// The largest buffer that is retained between responses. A larger response still serializes into the buffer, but
+/// it is then shrunk back to this size.
+const MAX_RETAINED_BUFFER: usize = 1024 * 1024;
+
+thread_local! {
+    static BUFFER: RefCell<Vec<u8>> = const { RefCell::new(Vec::new()) };
+}
+
+/// Specify whether response bodies are serialized into a per-thread buffer that is cleared and reused for every
+/// response on the thread.
+///
+/// Serializing into a fresh buffer grows it several times, reallocating and copying it each time. With this
+/// enabled the body is serialized into the retained buffer, which has usually already grown large enough, and is
+/// then copied out into a single allocation of exactly its size, so each response makes one allocation however
+/// large it is. This keeps up to 1 MiB of buffer per worker thread. Each actix worker runs on its own thread, so
+/// buffers are never shared between concurrent responses. Defaults to `false`.
+///
+/// # Parameters
+/// - `reuse` - Whether to reuse serialization buffers
+pub fn reuse_serialization_buffers(reuse: bool) {
+    REUSE_BUFFERS.store(reuse, Ordering::Relaxed);
+}
+
+/// Serialize the provided value as JSON, using the per-thread buffer if enabled.
+///
+/// # Parameters
+/// - `value` - The value to serialize
+pub(crate) fn to_vec<T>(value: &T) -> serde_json::Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    serialize(value, REUSE_BUFFERS.load(Ordering::Relaxed))
+}
+
+/// Serialize the provided value as JSON, optionally using the per-thread buffer.
+///
+/// # Parameters
+/// - `value` - The value to serialize
+/// - `reuse` - Whether to serialize into the per-thread buffer
+fn serialize<T>(value: &T, reuse: bool) -> serde_json::Result<Vec<u8>>
+where
+    T: Serialize + ?Sized,
+{
+    if !reuse {
+        return serde_json::to_vec(value).map_err(|e| annotate(value, e));
+    }
+
+    let result = BUFFER.with(|buffer| match buffer.try_borrow_mut() {
+        Ok(mut buffer) => {
+            buffer.clear();
+            let result = serde_json::to_writer(&mut *buffer, value).map(|_| buffer.to_vec());
+            if buffer.capacity() > MAX_RETAINED_BUFFER {
+                buffer.clear();
+                buffer.shrink_to(MAX_RETAINED_BUFFER);
+            }
+            result
+        },
+        Err(_) => serde_json::to_vec(value),
+    });
+
+    result.map_err(|e| annotate(value, e))
+}
+
+/// Convert the provided value into a JSON value.
+///
+/// # Parameters
+/// - `value` - The value to convert
+pub(crate) fn to_value<T>(value: &T) -> serde_json::Result<Value>
+where
+    T: Serialize + ?Sized,
+{
+    serde_json::to_value(value).map_err(|e| annotate(value, e))
+}
+
+/// Annotate a serialization error with the path to the value that failed to serialize.
+///
+/// This serializes the value a second time to track the path, so only happens once serialization has already
+/// failed.
+#[cfg(feature = "serde_path_to_error")]
+fn annotate<T>(value: &T, e: serde_json::Error) -> serde_json::Error
+where
+    T: Serialize + ?Sized,
+{
+    let mut serializer = serde_json::Serializer::new(std::io::sink());
+    match serde_path_to_error::serialize(value, &mut serializer) {
+        Err(path_error) => serde::ser::Error::custom(format!("{}: {}", path_error.path(), path_error.inner())),
+        Ok(_) => e,
+    }
+}
+
+/// Annotate a serialization error with the path to the value that failed to serialize.
+///
+/// Without the `serde_path_to_error` feature this leaves the error unchanged.
+#[cfg(not(feature = "serde_path_to_error"))]
+fn annotate<T>(_value: &T, e: serde_json::Error) -> serde_json::Error
+where
+    T: Serialize + ?Sized,
+{
+    e
+}
+
+/// Recursively remove every null-valued key from the provided JSON value.
+///
+/// Values nested inside `_links` are left untouched, as are null elements of arrays.
+///
+/// # Parameters
+/// - `value` - The value to remove nulls from
+pub(crate) fn strip_nulls(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.retain(|_, v| !v.is_null());
+            for (key, v) in map.iter_mut() {
+                if key != "_links" {
+                    strip_nulls(v);
+                }
+            }
+        },
+        Value::Array(values) => values.iter_mut().for_each(strip_nulls),
+        _ => {},
+    }
+}
+
+/// Remove the value identified by the provided JSON Pointer, as defined by RFC 6901.
+///
+/// Pointers that don't identify a value are ignored.
+///
+/// # Parameters
+/// - `value` - The value to remove from
+/// - `pointer` - The JSON Pointer to the value to remove
+pub(crate) fn remove_pointer(value: &mut Value, pointer: &str) {
+    let (parent, key) = match pointer.rfind('/') {
+        Some(index) => (&pointer[..index], &pointer[index + 1..]),
+        None => return,
+    };
+    let key = key.replace("~1", "/").replace("~0", "~");
+
+    match value.pointer_mut(parent) {
+        Some(Value::Object(map)) => {
+            map.remove(&key);
+        },
+        Some(Value::Array(values)) => {
+            if let Ok(index) = key.parse::<usize>() {
+                if index < values.len() {
+                    values.remove(index);
+                }
+            }
+        },
+        _ => {},
+    }
+}
+
+/// Compute a stable fingerprint of the provided JSON value, suitable for use as an entity tag.
+///
+/// This is a 64-bit FNV-1a hash of the compact serialization of the value. Object keys are always
+/// serialized in sorted order, so equal values always produce equal fingerprints.
+///
+/// # Parameters
+/// - `value` - The value to fingerprint
+pub(crate) fn fingerprint(value: &Value) -> String {
+    let hash = value.to_string().bytes().fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0000_0100_0000_01b3)
+    });
+
+    format!("{:016x}", hash)
+}
+
+#[cfg(test)]
+mod tests {
+    use assert2::check;
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn reused_buffer() {
+        let first = serialize(&json!({"a": "longer value"}), true).unwrap();
+        let retained = BUFFER.with(|buffer| buffer.borrow().as_ptr());
+        let second = serialize(&json!({"b": 1}), true).unwrap();
+
+        check!(BUFFER.with(|buffer| buffer.borrow().as_ptr()) == retained);
+        check!(first == br#"{"a":"longer value"}"#.to_vec());
+        check!(first.capacity() == first.len());
+        check!(second == br#"{"b":1}"#.to_vec());
+    }
+
+    #[cfg(feature = "serde_path_to_error")]
+    #[test]
+    fn error_includes_path() {
+        let mut inner = std::collections::HashMap::new();
+        inner.insert((1, 2), "Not a string key");
+        let mut outer = std::collections::BTreeMap::new();
+        outer.insert("inner", vec![inner]);
+
+        let e = to_vec(&outer).unwrap_err();
+        check!(e.to_string().starts_with("inner[0]"));
+    }
+
+    #[test]
+    fn remove_pointers() {
+        let mut value = json!({
+            "generated_at": "now",
+            "nested": {"a/b": 1, "c": 2},
+            "list": [1, 2, 3]
+        });
+        remove_pointer(&mut value, "/generated_at");
+        remove_pointer(&mut value, "/nested/a~1b");
+        remove_pointer(&mut value, "/list/1");
+        remove_pointer(&mut value, "/missing/value");
+
+        check!(value == json!({"nested": {"c": 2}, "list": [1, 3]}));
+    }
+
+    #[test]
+    fn fingerprint_is_stable() {
+        check!(fingerprint(&json!({"a": 1, "b": 2})) == fingerprint(&json!({"b": 2, "a": 1})));
+        check!(fingerprint(&json!({"a": 1})) != fingerprint(&json!({"a": 2})));
+    }
+
+    #[test]
+    fn strip_nested_nulls() {
+        let mut value = json!({
+            "name": null,
+            "age": 42,
+            "address": {
+                "line1": "Somewhere",
+                "line2": null
+            },
+            "tags": [null, {"a": null}],
+            "_links": {
+                "self": {"href": "/", "name": null}
+            }
+        });
+        strip_nulls(&mut value);
+
+        check!(
+            value
+                == json!({
+                    "age": 42,
+                    "address": {
+                        "line1": "Somewhere"
+                    },
+                    "tags": [null, {}],
+                    "_links": {
+                        "self": {"href": "/", "name": null}
+                    }
+                })
+        );
+    }
+}
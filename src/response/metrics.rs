@@ -0,0 +1,91 @@
+use actix_http::http::header;
+use actix_web::HttpResponse;
+
+/// The name of the counter incremented for every response, labelled with `status` and `content_type`.
+pub const RESPONSES_COUNTER: &str = "http_responses_total";
+
+/// Record that a response was produced, incrementing the `http_responses_total` counter.
+///
+/// # Parameters
+/// - `response` - The response that was produced
+pub(crate) fn record(response: &HttpResponse) {
+    let status = response.status().as_u16().to_string();
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(|value| value.split(';').next().unwrap_or_default().trim().to_owned())
+        .unwrap_or_default();
+
+    metrics::counter!(RESPONSES_COUNTER, "status" => status, "content_type" => content_type).increment(1);
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    };
+
+    use actix_http::http::StatusCode;
+    use assert2::check;
+    use metrics::{Counter, CounterFn, Gauge, Histogram, Key, KeyName, Metadata, Recorder, SharedString, Unit};
+
+    use super::*;
+
+    #[derive(Default)]
+    struct TestRecorder(Mutex<Vec<(Key, Arc<TestCounter>)>>);
+
+    #[derive(Default)]
+    struct TestCounter(AtomicU64);
+
+    impl CounterFn for TestCounter {
+        fn increment(&self, value: u64) {
+            self.0.fetch_add(value, Ordering::Relaxed);
+        }
+
+        fn absolute(&self, value: u64) {
+            self.0.store(value, Ordering::Relaxed);
+        }
+    }
+
+    impl Recorder for TestRecorder {
+        fn describe_counter(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+        fn describe_gauge(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+        fn describe_histogram(&self, _key: KeyName, _unit: Option<Unit>, _description: SharedString) {}
+
+        fn register_counter(&self, key: &Key, _metadata: &Metadata<'_>) -> Counter {
+            let counter = Arc::new(TestCounter::default());
+            self.0.lock().unwrap().push((key.clone(), counter.clone()));
+            Counter::from_arc(counter)
+        }
+
+        fn register_gauge(&self, _key: &Key, _metadata: &Metadata<'_>) -> Gauge {
+            Gauge::noop()
+        }
+
+        fn register_histogram(&self, _key: &Key, _metadata: &Metadata<'_>) -> Histogram {
+            Histogram::noop()
+        }
+    }
+
+    #[test]
+    fn record_response() {
+        let recorder = TestRecorder::default();
+        let response = HttpResponse::build(StatusCode::CREATED)
+            .insert_header((header::CONTENT_TYPE, "application/hal+json; profile=\"v3\""))
+            .finish();
+
+        metrics::with_local_recorder(&recorder, || record(&response));
+
+        let counters = recorder.0.lock().unwrap();
+        check!(counters.len() == 1);
+        let (key, counter) = &counters[0];
+        check!(key.name() == RESPONSES_COUNTER);
+        let labels: Vec<_> = key.labels().map(|label| (label.key(), label.value())).collect();
+        check!(labels == vec![("status", "201"), ("content_type", "application/hal+json")]);
+        check!(counter.0.load(Ordering::Relaxed) == 1);
+    }
+}
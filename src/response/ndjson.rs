@@ -0,0 +1,236 @@
+use std::{
+    pin::Pin,
+    task::{Context, Poll},
+};
+
+use actix_http::http::{
+    header::{self, Header},
+    HeaderMap, HeaderValue, StatusCode,
+};
+use actix_web::{web::Bytes, HttpRequest, HttpResponse, Responder};
+use futures_core::Stream;
+use serde::Serialize;
+
+/// The number of bytes of serialized lines to buffer before sending them to the client.
+const FLUSH_SIZE: usize = 8 * 1024;
+
+/// Response for a bulk export of items as newline-delimited JSON, served as `application/x-ndjson`.
+///
+/// Each item is serialized as compact JSON on a line of its own. The items are streamed to the client as they
+/// are serialized, in chunks of around 8 KiB, so the whole export is never held in memory and the next chunk is
+/// only produced once the client has accepted the previous one. Since items are pulled from the iterator while
+/// producing a chunk, the iterator should not block for long periods.
+///
+/// The status code and headers have already been sent by the time the items are serialized, so a failure to
+/// serialize an item can't be reported to the client. Instead the error is logged and the connection is
+/// aborted after every line before the failure has been sent, so that the client can tell the export is
+/// incomplete.
+///
+/// This responds directly rather than being a `Respondable`, since `Respondable` bodies are always serialized
+/// in full before being sent.
+pub struct NdjsonRespondable<I> {
+    status_code: StatusCode,
+    headers:     HeaderMap,
+    items:       I,
+}
+
+impl<I> NdjsonRespondable<I>
+where
+    I: Iterator,
+{
+    /// Create a new instance of the `NdjsonRespondable` struct exporting the provided items.
+    ///
+    /// # Parameters
+    /// - `items` - The items to export.
+    pub fn new<C>(items: C) -> Self
+    where
+        C: IntoIterator<IntoIter = I>,
+    {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static("application/x-ndjson"));
+
+        Self {
+            status_code: StatusCode::OK,
+            headers,
+            items: items.into_iter(),
+        }
+    }
+
+    /// Specify the status code to use.
+    ///
+    /// # Parameters
+    /// - `status_code` - The status code to use
+    pub fn with_status_code(mut self, status_code: StatusCode) -> Self {
+        self.status_code = status_code;
+        self
+    }
+
+    /// Specify a header to include in the response.
+    ///
+    /// # Parameters
+    /// - `header` - The header to add to the response.
+    pub fn with_header<H>(mut self, header: H) -> Self
+    where
+        H: Header,
+    {
+        let name = H::name();
+        match header.try_into_value() {
+            Ok(value) => {
+                self.headers.append(name, value);
+            },
+            Err(_) => {
+                tracing::error!(name = ?name, "Failed to process header");
+            },
+        };
+
+        self
+    }
+}
+
+impl<I> Responder for NdjsonRespondable<I>
+where
+    I: Iterator + Unpin + 'static,
+    I::Item: Serialize,
+{
+    fn respond_to(self, _req: &HttpRequest) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code);
+
+        for (key, value) in self.headers {
+            response.append_header((key, value));
+        }
+
+        let response = response.streaming(NdjsonStream {
+            items: Some(self.items),
+            error: None,
+        });
+
+        #[cfg(feature = "metrics")]
+        super::metrics::record(&response);
+
+        response
+    }
+}
+
+/// Stream of the chunks of serialized lines for an `NdjsonRespondable`.
+struct NdjsonStream<I> {
+    /// The items still to serialize, or `None` once they are exhausted or have failed.
+    items: Option<I>,
+    /// An error to report once the lines before it have been sent.
+    error: Option<serde_json::Error>,
+}
+
+impl<I> Stream for NdjsonStream<I>
+where
+    I: Iterator + Unpin,
+    I::Item: Serialize,
+{
+    type Item = Result<Bytes, serde_json::Error>;
+
+    fn poll_next(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        if let Some(e) = this.error.take() {
+            return Poll::Ready(Some(Err(e)));
+        }
+
+        let items = match &mut this.items {
+            Some(items) => items,
+            None => return Poll::Ready(None),
+        };
+
+        let mut chunk = Vec::with_capacity(FLUSH_SIZE);
+        while chunk.len() < FLUSH_SIZE {
+            let item = match items.next() {
+                Some(item) => item,
+                None => {
+                    this.items = None;
+                    break;
+                },
+            };
+
+            let line_start = chunk.len();
+            if let Err(e) = serde_json::to_writer(&mut chunk, &item) {
+                tracing::error!(e = ?e, "Failed to serialize NDJSON item, aborting response");
+                chunk.truncate(line_start);
+                this.items = None;
+                this.error = Some(e);
+                break;
+            }
+            chunk.push(b'\n');
+        }
+
+        if chunk.is_empty() {
+            Poll::Ready(this.error.take().map(Err))
+        } else {
+            Poll::Ready(Some(Ok(Bytes::from(chunk))))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashMap, task::Waker};
+
+    use assert2::{check, let_assert};
+    use serde_json::json;
+
+    use super::*;
+
+    fn poll<I>(stream: &mut NdjsonStream<I>) -> Option<Result<Bytes, serde_json::Error>>
+    where
+        I: Iterator + Unpin,
+        I::Item: Serialize,
+    {
+        let mut cx = Context::from_waker(Waker::noop());
+        let_assert!(Poll::Ready(item) = Pin::new(stream).poll_next(&mut cx));
+        item
+    }
+
+    #[test]
+    fn lines() {
+        let mut stream = NdjsonStream {
+            items: Some(vec![json!({"id": 1}), json!({"id": 2})].into_iter()),
+            error: None,
+        };
+
+        let_assert!(Some(Ok(chunk)) = poll(&mut stream));
+        check!(chunk.as_ref() == b"{\"id\":1}\n{\"id\":2}\n");
+        check!(poll(&mut stream).is_none());
+    }
+
+    #[test]
+    fn chunked() {
+        let mut stream = NdjsonStream {
+            items: Some((0..10_000).map(|id| json!({ "id": id }))),
+            error: None,
+        };
+
+        let mut chunks = 0;
+        let mut lines = 0;
+        while let Some(chunk) = poll(&mut stream) {
+            let chunk = chunk.unwrap();
+            check!(chunk.len() < FLUSH_SIZE + 64);
+            chunks += 1;
+            lines += chunk.iter().filter(|b| **b == b'\n').count();
+        }
+
+        check!(chunks > 1);
+        check!(lines == 10_000);
+    }
+
+    #[test]
+    fn failure_after_lines() {
+        let good = HashMap::new();
+        let mut bad = HashMap::new();
+        bad.insert((1, 2), "Not a string key");
+
+        let mut stream = NdjsonStream {
+            items: Some(vec![good, bad].into_iter()),
+            error: None,
+        };
+
+        let_assert!(Some(Ok(chunk)) = poll(&mut stream));
+        check!(chunk.as_ref() == b"{}\n");
+        let_assert!(Some(Err(_)) = poll(&mut stream));
+        check!(poll(&mut stream).is_none());
+    }
+}
@@ -0,0 +1,270 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use actix_http::http::header;
+use actix_web::{web, HttpRequest};
+
+/// How to respond when the `Accept` header of the request can't be satisfied by any media type the response
+/// supports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NegotiationMode {
+    /// Respond with the default media type anyway.
+    #[default]
+    ForceDefault,
+    /// Respond with a `406 Not Acceptable` problem listing the available media types.
+    NotAcceptable,
+}
+
+/// How to respond when the format query parameter of a request names a format the response doesn't support.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnknownFormat {
+    /// Respond with a `400 Bad Request` problem listing the available media types.
+    BadRequest,
+    /// Ignore the query parameter, and negotiate using the `Accept` header as normal.
+    Negotiate,
+}
+
+/// The query parameter that overrides content negotiation, and how to handle unknown formats.
+static FORMAT_PARAMETER: RwLock<Option<(String, UnknownFormat)>> = RwLock::new(None);
+
+/// Specify a query parameter that clients can use to choose the format of the response, regardless of their
+/// `Accept` header, e.g. `?format=hal` or `?format=json`. Defaults to no parameter.
+///
+/// A format matches a media type whose subtype is the format, the format with a `+json` suffix, or the format
+/// with an `x-` prefix. So `json` matches `application/json`, `hal` matches `application/hal+json`, and `yaml`
+/// matches `application/yaml`.
+///
+/// # Parameters
+/// - `param` - The name of the query parameter, or `None` to always negotiate using the `Accept` header
+/// - `unknown` - How to respond when the parameter names a format the response doesn't support
+pub fn format_query_parameter(param: Option<&str>, unknown: UnknownFormat) {
+    *FORMAT_PARAMETER.write().unwrap_or_else(|e| e.into_inner()) = param.map(|param| (param.to_owned(), unknown));
+}
+
+/// Get the format query parameter that is currently configured, and how to handle unknown formats.
+pub(crate) fn format_parameter() -> Option<(String, UnknownFormat)> {
+    FORMAT_PARAMETER.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// The format requested by the format query parameter of a request.
+pub(crate) enum FormatOverride<'a> {
+    /// The request named one of the available media types.
+    Selected(&'a str),
+    /// The request named a format that isn't available, and this should be rejected.
+    Unknown(String),
+}
+
+/// Determine the media type requested by the format query parameter of a request, if one is configured.
+///
+/// # Parameters
+/// - `req` - The request to check
+/// - `available` - The media types the response can be represented as
+/// - `format` - The format query parameter and how to handle unknown formats, typically from `format_parameter`
+///
+/// # Returns
+/// The requested format, or `None` if content negotiation should use the `Accept` header as normal
+pub(crate) fn format_override<'a>(
+    req: &HttpRequest,
+    available: &[&'a str],
+    format: Option<&(String, UnknownFormat)>,
+) -> Option<FormatOverride<'a>> {
+    let (param, unknown) = format?;
+
+    let query = match web::Query::<HashMap<String, String>>::from_query(req.query_string()) {
+        Ok(query) => query.into_inner(),
+        Err(e) => {
+            tracing::error!(e = ?e, "Failed to parse query string for format");
+            return None;
+        },
+    };
+    let format = query.get(param)?.trim().to_ascii_lowercase();
+
+    let matches = |media_type: &str| {
+        let subtype = media_type.split('/').nth(1).unwrap_or_default();
+        subtype == format
+            || subtype.strip_suffix("+json") == Some(format.as_str())
+            || subtype.strip_prefix("x-") == Some(format.as_str())
+    };
+
+    match available.iter().find(|media_type| matches(media_type)) {
+        Some(media_type) => Some(FormatOverride::Selected(media_type)),
+        None if *unknown == UnknownFormat::BadRequest => Some(FormatOverride::Unknown(format)),
+        None => None,
+    }
+}
+
+/// Select the media type to respond with for the provided request.
+///
+/// Each available media type is given the quality of the most specific media range in the `Accept` header that
+/// matches it, so `application/json` is preferred over `application/*`, which is preferred over `*/*`. The
+/// media type with the highest quality is chosen, with ties going to the one that was matched earliest in the
+/// `Accept` header, and then to the one that was listed first in `available`. A quality of `0` means that the
+/// media type is not acceptable at all.
+///
+/// # Parameters
+/// - `req` - The request to negotiate against
+/// - `available` - The media types the response can be represented as, in order of preference
+///
+/// # Returns
+/// The media type to respond with, or `None` if the request accepts none of the available media types.
+/// A request with no `Accept` header always gets the first available media type.
+pub fn negotiate<'a>(req: &HttpRequest, available: &[&'a str]) -> Option<&'a str> {
+    let accept = match req.headers().get(header::ACCEPT).and_then(|value| value.to_str().ok()) {
+        Some(accept) => accept,
+        None => return available.first().copied(),
+    };
+
+    let ranges: Vec<MediaRange> = accept.split(',').filter_map(MediaRange::parse).collect();
+
+    let mut best: Option<(&'a str, f32, usize)> = None;
+    for media_type in available {
+        let matched = ranges
+            .iter()
+            .enumerate()
+            .filter(|(_, range)| matches(&range.range, media_type))
+            .max_by_key(|(_, range)| specificity(&range.range));
+
+        if let Some((position, range)) = matched {
+            let better = match best {
+                None => true,
+                Some((_, quality, best_position)) => {
+                    range.quality > quality || (range.quality == quality && position < best_position)
+                },
+            };
+
+            if range.quality > 0.0 && better {
+                best = Some((media_type, range.quality, position));
+            }
+        }
+    }
+
+    best.map(|(media_type, _, _)| media_type)
+}
+
+/// A single media range from an `Accept` header, along with its quality.
+struct MediaRange {
+    range:   String,
+    quality: f32,
+}
+
+impl MediaRange {
+    /// Parse a single media range from an `Accept` header, e.g. `application/json;q=0.8`.
+    ///
+    /// # Returns
+    /// The media range, or `None` if it was empty or had an invalid quality.
+    fn parse(value: &str) -> Option<Self> {
+        let mut parts = value.split(';');
+        let range = parts.next().unwrap_or_default().trim().to_ascii_lowercase();
+        if range.is_empty() {
+            return None;
+        }
+
+        let mut quality = 1.0;
+        for param in parts {
+            if let Some((name, value)) = param.split_once('=') {
+                if name.trim().eq_ignore_ascii_case("q") {
+                    quality = value.trim().parse::<f32>().ok().filter(|q| (0.0..=1.0).contains(q))?;
+                }
+            }
+        }
+
+        Some(Self { range, quality })
+    }
+}
+
+/// Determine if the provided media range from an `Accept` header matches a media type.
+fn matches(range: &str, media_type: &str) -> bool {
+    match range.strip_suffix("/*") {
+        Some("*") => true,
+        Some(prefix) => media_type.split('/').next() == Some(prefix),
+        None => range == media_type,
+    }
+}
+
+/// Determine how specific a media range is, so that `type/subtype` takes precedence over `type/*`, which takes
+/// precedence over `*/*`.
+fn specificity(range: &str) -> u8 {
+    match range.strip_suffix("/*") {
+        Some("*") => 0,
+        Some(_) => 1,
+        None => 2,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::test::TestRequest;
+    use assert2::check;
+
+    use super::*;
+
+    const AVAILABLE: &[&str] = &["application/hal+json", "application/json"];
+
+    fn negotiate_accept(accept: &str) -> Option<&'static str> {
+        let req = TestRequest::default().insert_header((header::ACCEPT, accept)).to_http_request();
+        negotiate(&req, AVAILABLE)
+    }
+
+    #[test]
+    fn no_accept_header() {
+        let req = TestRequest::default().to_http_request();
+
+        check!(negotiate(&req, AVAILABLE) == Some("application/hal+json"));
+    }
+
+    #[test]
+    fn exact_match() {
+        check!(negotiate_accept("application/json") == Some("application/json"));
+        check!(negotiate_accept("text/html, application/hal+json") == Some("application/hal+json"));
+    }
+
+    #[test]
+    fn wildcard_match() {
+        check!(negotiate_accept("*/*") == Some("application/hal+json"));
+        check!(negotiate_accept("application/*") == Some("application/hal+json"));
+    }
+
+    #[test]
+    fn no_match() {
+        check!(negotiate_accept("text/html, text/*") == None);
+    }
+
+    #[test]
+    fn quality_values() {
+        check!(negotiate_accept("application/json;q=0.8, application/hal+json;q=0.9") == Some("application/hal+json"));
+        check!(negotiate_accept("application/hal+json;q=0.5, application/json") == Some("application/json"));
+        check!(negotiate_accept("application/json, application/hal+json") == Some("application/json"));
+    }
+
+    #[test]
+    fn most_specific_range_wins() {
+        check!(negotiate_accept("application/*;q=0.9, application/hal+json;q=0.1") == Some("application/json"));
+        check!(negotiate_accept("*/*, application/hal+json;q=0") == Some("application/json"));
+    }
+
+    #[test]
+    fn invalid_quality_ignored() {
+        check!(negotiate_accept("application/hal+json;q=2, application/json") == Some("application/json"));
+        check!(negotiate_accept("application/hal+json;q=0") == None);
+    }
+
+    #[test]
+    fn format_parameter() {
+        let param = ("format".to_owned(), UnknownFormat::BadRequest);
+        let format = |uri: &str, param| {
+            let req = TestRequest::with_uri(uri)
+                .insert_header((header::ACCEPT, "application/json"))
+                .to_http_request();
+            match format_override(&req, AVAILABLE, param) {
+                Some(FormatOverride::Selected(media_type)) => Some(Ok(media_type)),
+                Some(FormatOverride::Unknown(format)) => Some(Err(format)),
+                None => None,
+            }
+        };
+
+        check!(format("/?format=hal", Some(&param)) == Some(Ok("application/hal+json")));
+        check!(format("/?format=JSON", Some(&param)) == Some(Ok("application/json")));
+        check!(format("/?format=xml", Some(&param)) == Some(Err("xml".to_owned())));
+        check!(format("/", Some(&param)) == None);
+        check!(format("/?format=hal", None) == None);
+    }
+}
@@ -0,0 +1,123 @@
+use actix_http::http::{header::Header, HeaderMap, StatusCode};
+
+use super::Respondable;
+
+/// Respondable for a protobuf-encoded body, served as `application/protobuf`.
+///
+/// Clients that only accept the older `application/x-protobuf` media type get that instead. The message is
+/// encoded as soon as this is created, so options that work on serialized JSON, such as omitting nulls, have no
+/// effect.
+pub struct ProtobufRespondable {
+    status_code: StatusCode,
+    headers:     HeaderMap,
+    body:        Vec<u8>,
+}
+
+impl ProtobufRespondable {
+    /// Create a new instance of the `ProtobufRespondable` struct encoding the provided message.
+    ///
+    /// # Parameters
+    /// - `message` - The message to send back to the client.
+    pub fn new<M>(message: M) -> Self
+    where
+        M: prost::Message,
+    {
+        Self {
+            status_code: StatusCode::OK,
+            headers:     HeaderMap::new(),
+            body:        message.encode_to_vec(),
+        }
+    }
+
+    /// Specify the status code to use.
+    ///
+    /// # Parameters
+    /// - `status_code` - The status code to use
+    pub fn with_status_code(mut self, status_code: StatusCode) -> Self {
+        self.status_code = status_code;
+        self
+    }
+
+    /// Specify a header to include in the response.
+    ///
+    /// # Parameters
+    /// - `header` - The header to add to the response.
+    pub fn with_header<H>(mut self, header: H) -> Self
+    where
+        H: Header,
+    {
+        let name = H::name();
+        match header.try_into_value() {
+            Ok(value) => {
+                self.headers.append(name, value);
+            },
+            Err(_) => {
+                tracing::error!(name = ?name, "Failed to process header");
+            },
+        };
+
+        self
+    }
+}
+
+impl Respondable for ProtobufRespondable {
+    type Body = Vec<u8>;
+
+    fn status_code(&self) -> StatusCode {
+        self.status_code
+    }
+
+    fn headers(&self) -> HeaderMap {
+        self.headers.clone()
+    }
+
+    fn media_types(&self) -> Vec<&'static str> {
+        vec!["application/protobuf", "application/x-protobuf"]
+    }
+
+    fn body(self) -> Self::Body {
+        self.body
+    }
+
+    fn into_parts(self) -> (StatusCode, HeaderMap, Self::Body) {
+        (self.status_code, self.headers, self.body)
+    }
+
+    fn serialize_body(body: Self::Body, _omit_nulls: bool) -> serde_json::Result<Vec<u8>> {
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::http::header;
+    use actix_web::{body::AnyBody, test::TestRequest, Responder};
+    use assert2::{check, let_assert};
+
+    use super::*;
+    use crate::response::Response;
+
+    #[derive(Clone, PartialEq, prost::Message)]
+    struct User {
+        #[prost(uint64, tag = "1")]
+        id:   u64,
+        #[prost(string, tag = "2")]
+        name: String,
+    }
+
+    #[test]
+    fn protobuf_body() {
+        let req = TestRequest::default()
+            .insert_header((header::ACCEPT, "application/x-protobuf"))
+            .to_http_request();
+        let user = User {
+            id:   1,
+            name: "Graham".to_owned(),
+        };
+        let response = Response::new(ProtobufRespondable::new(user.clone())).respond_to(&req);
+
+        check!(response.headers().get(header::CONTENT_TYPE).unwrap() == "application/x-protobuf");
+        let_assert!(AnyBody::Bytes(body) = response.body());
+        check!(<User as prost::Message>::decode(body.as_ref()).unwrap() == user);
+    }
+}
@@ -0,0 +1,144 @@
+use actix_http::http::{
+    header::{self, Header},
+    HeaderMap, HeaderValue, StatusCode,
+};
+use actix_web::web::Bytes;
+
+use super::Respondable;
+
+/// Respondable for a JSON document that has already been serialized, e.g. one that was cached.
+///
+/// The bytes are served exactly as provided, without going through serde at all. This means that the crate
+/// trusts the caller that they are valid JSON - nothing checks this, so invalid bytes are sent to the client
+/// as-is. It also means that options that work on the serialized JSON, such as omitting nulls, have no effect.
+pub struct RawJsonRespondable {
+    status_code: StatusCode,
+    headers:     HeaderMap,
+    media_type:  &'static str,
+    body:        Bytes,
+}
+
+impl RawJsonRespondable {
+    /// Create a new instance of the `RawJsonRespondable` struct serving the provided JSON as
+    /// `application/json`.
+    ///
+    /// # Parameters
+    /// - `json` - The serialized JSON to send back to the client. This must be valid JSON.
+    pub fn new<B>(json: B) -> Self
+    where
+        B: Into<Bytes>,
+    {
+        Self::with_media_type(json, "application/json")
+    }
+
+    /// Create a new instance of the `RawJsonRespondable` struct serving the provided HAL document as
+    /// `application/hal+json`.
+    ///
+    /// # Parameters
+    /// - `json` - The serialized HAL document to send back to the client. This must be valid JSON.
+    pub fn hal<B>(json: B) -> Self
+    where
+        B: Into<Bytes>,
+    {
+        Self::with_media_type(json, "application/hal+json")
+    }
+
+    /// Create a new instance of the `RawJsonRespondable` struct serving the provided JSON as the given media type.
+    ///
+    /// # Parameters
+    /// - `json` - The serialized JSON to send back to the client. This must be valid JSON.
+    /// - `media_type` - The media type to serve the JSON as.
+    fn with_media_type<B>(json: B, media_type: &'static str) -> Self
+    where
+        B: Into<Bytes>,
+    {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(media_type));
+
+        Self {
+            status_code: StatusCode::OK,
+            headers,
+            media_type,
+            body: json.into(),
+        }
+    }
+
+    /// Specify the status code to use.
+    ///
+    /// # Parameters
+    /// - `status_code` - The status code to use
+    pub fn with_status_code(mut self, status_code: StatusCode) -> Self {
+        self.status_code = status_code;
+        self
+    }
+
+    /// Specify a header to include in the response.
+    ///
+    /// # Parameters
+    /// - `header` - The header to add to the response.
+    pub fn with_header<H>(mut self, header: H) -> Self
+    where
+        H: Header,
+    {
+        let name = H::name();
+        match header.try_into_value() {
+            Ok(value) => {
+                self.headers.append(name, value);
+            },
+            Err(_) => {
+                tracing::error!(name = ?name, "Failed to process header");
+            },
+        };
+
+        self
+    }
+}
+
+impl Respondable for RawJsonRespondable {
+    type Body = Vec<u8>;
+
+    fn status_code(&self) -> StatusCode {
+        self.status_code
+    }
+
+    fn headers(&self) -> HeaderMap {
+        self.headers.clone()
+    }
+
+    fn media_types(&self) -> Vec<&'static str> {
+        vec![self.media_type]
+    }
+
+    fn body(self) -> Self::Body {
+        self.body.into()
+    }
+
+    fn into_parts(self) -> (StatusCode, HeaderMap, Self::Body) {
+        (self.status_code, self.headers, self.body.into())
+    }
+
+    fn serialize_body(body: Self::Body, _omit_nulls: bool) -> serde_json::Result<Vec<u8>> {
+        Ok(body)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_web::{body::AnyBody, test::TestRequest, Responder};
+    use assert2::{check, let_assert};
+
+    use super::*;
+    use crate::response::Response;
+
+    #[test]
+    fn raw_json_document() {
+        let req = TestRequest::default().to_http_request();
+        let respondable = RawJsonRespondable::hal(r#"{"id":1}"#).with_status_code(StatusCode::CREATED);
+        let response = Response::new(respondable).respond_to(&req);
+
+        check!(response.status() == StatusCode::CREATED);
+        check!(response.headers().get(header::CONTENT_TYPE).unwrap() == "application/hal+json");
+        let_assert!(AnyBody::Bytes(body) = response.body());
+        check!(body.as_ref() == br#"{"id":1}"#);
+    }
+}
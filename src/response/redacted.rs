@@ -0,0 +1,221 @@
+use actix_http::http::{HeaderMap, StatusCode};
+use actix_web::HttpRequest;
+use serde::{ser::Error as _, Serialize, Serializer};
+use serde_json::Value;
+
+use super::{json, NegotiationMode, Respondable};
+
+/// How a redacted field is treated.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Redaction {
+    /// Remove the field from the body entirely.
+    Remove,
+    /// Replace the value of the field with `"***"`.
+    Mask,
+}
+
+/// Wrapper around any respondable with a JSON body that redacts some of the fields of the serialized body, e.g.
+/// those that the caller isn't allowed to see.
+///
+/// Fields are identified by JSON Pointers, as defined by RFC 6901, and pointers that don't identify a field
+/// are ignored. Redaction works on the serialized JSON, so the wrapped respondable must serialize to JSON.
+///
+/// # Types
+/// - `R` - The type of respondable to wrap
+pub struct Redacted<R> {
+    respondable: R,
+    redactions:  Vec<(String, Redaction)>,
+}
+
+/// Redact the provided fields from the body of a respondable, removing them entirely.
+///
+/// # Parameters
+/// - `respondable` - The respondable to redact
+/// - `pointers` - JSON Pointers to the fields to remove, e.g. `/account/number`
+pub fn redact<R>(respondable: R, pointers: &[&str]) -> Redacted<R> {
+    pointers
+        .iter()
+        .fold(Redacted::new(respondable), |redacted, pointer| redacted.removing(*pointer))
+}
+
+impl<R> Redacted<R> {
+    /// Wrap the provided respondable, initially without redacting anything.
+    ///
+    /// # Parameters
+    /// - `respondable` - The respondable to redact
+    pub fn new(respondable: R) -> Self {
+        Self {
+            respondable,
+            redactions: vec![],
+        }
+    }
+
+    /// Redact a field by removing it from the body.
+    ///
+    /// # Parameters
+    /// - `pointer` - JSON Pointer to the field to remove
+    pub fn removing<S>(mut self, pointer: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.redactions.push((pointer.into(), Redaction::Remove));
+        self
+    }
+
+    /// Redact a field by replacing its value with `"***"`.
+    ///
+    /// # Parameters
+    /// - `pointer` - JSON Pointer to the field to mask
+    pub fn masking<S>(mut self, pointer: S) -> Self
+    where
+        S: Into<String>,
+    {
+        self.redactions.push((pointer.into(), Redaction::Mask));
+        self
+    }
+}
+
+/// The body of a redacted respondable, carrying the redactions through to serialization.
+pub struct RedactedBody<B> {
+    body:       B,
+    redactions: Vec<(String, Redaction)>,
+}
+
+impl<B> Serialize for RedactedBody<B>
+where
+    B: Serialize,
+{
+    /// Serialize the body with its fields redacted, so that they stay hidden when the body is serialized other
+    /// than through `Respondable::serialize_body`, e.g. when it is embedded in a HAL response.
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut value = serde_json::to_value(&self.body).map_err(S::Error::custom)?;
+        apply(&mut value, &self.redactions);
+
+        value.serialize(serializer)
+    }
+}
+
+impl<R> Respondable for Redacted<R>
+where
+    R: Respondable,
+{
+    type Body = RedactedBody<R::Body>;
+
+    fn status_code(&self) -> StatusCode {
+        self.respondable.status_code()
+    }
+
+    fn headers(&self) -> HeaderMap {
+        self.respondable.headers()
+    }
+
+    fn media_types(&self) -> Vec<&'static str> {
+        self.respondable.media_types()
+    }
+
+    fn negotiation_mode(&self) -> NegotiationMode {
+        self.respondable.negotiation_mode()
+    }
+
+    fn has_body(&self) -> bool {
+        self.respondable.has_body()
+    }
+
+    fn omits_nulls(&self) -> bool {
+        self.respondable.omits_nulls()
+    }
+
+    fn prepare(&mut self, req: &HttpRequest) {
+        self.respondable.prepare(req);
+    }
+
+    fn body(self) -> Self::Body {
+        RedactedBody {
+            body:       self.respondable.body(),
+            redactions: self.redactions,
+        }
+    }
+
+    fn into_parts(self) -> (StatusCode, HeaderMap, Self::Body) {
+        let (status_code, headers, body) = self.respondable.into_parts();
+        let body = RedactedBody {
+            body,
+            redactions: self.redactions,
+        };
+
+        (status_code, headers, body)
+    }
+
+    fn serialize_body(body: Self::Body, omit_nulls: bool) -> serde_json::Result<Vec<u8>> {
+        let serialized = R::serialize_body(body.body, omit_nulls)?;
+        if body.redactions.is_empty() {
+            return Ok(serialized);
+        }
+
+        let mut value: Value = serde_json::from_slice(&serialized)?;
+        apply(&mut value, &body.redactions);
+
+        json::to_vec(&value)
+    }
+}
+
+/// Apply redactions to a serialized body.
+///
+/// # Parameters
+/// - `value` - The serialized body to redact
+/// - `redactions` - The redactions to apply
+fn apply(value: &mut Value, redactions: &[(String, Redaction)]) {
+    for (pointer, redaction) in redactions {
+        match redaction {
+            Redaction::Remove => json::remove_pointer(value, pointer),
+            Redaction::Mask => {
+                if let Some(field) = value.pointer_mut(pointer) {
+                    *field = Value::String("***".to_owned());
+                }
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use assert2::check;
+    use serde_json::json;
+
+    use super::*;
+    use crate::hal::HalRespondable;
+
+    fn serialize<R>(respondable: R) -> Value
+    where
+        R: Respondable,
+    {
+        let bytes = R::serialize_body(respondable.body(), false).unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+
+    #[test]
+    fn redact_fields() {
+        let body = json!({"name": "Graham", "ssn": "123-45-6789", "account": {"number": "12345678", "sort": "12"}});
+
+        check!(serialize(redact(body.clone(), &["/ssn", "/account/number", "/missing"])) == json!({
+            "name": "Graham",
+            "account": {"sort": "12"},
+        }));
+
+        check!(serialize(Redacted::new(body).removing("/ssn").masking("/account/number")) == json!({
+            "name": "Graham",
+            "account": {"number": "***", "sort": "12"},
+        }));
+    }
+
+    #[test]
+    fn redacted_when_embedded() {
+        let respondable = HalRespondable::new(json!({}))
+            .with_embedded("person", redact(json!({"id": 1, "ssn": "123-45-6789"}), &["/ssn"]));
+
+        check!(serialize(respondable)["_embedded"] == json!({"person": {"id": 1}}));
+    }
+}
@@ -1,6 +1,9 @@
 use actix_http::http::{HeaderMap, StatusCode};
+use actix_web::HttpRequest;
 use serde::Serialize;
 
+use super::{json, NegotiationMode};
+
 /// Trait that anything able to represent a response can implement.
 pub trait Respondable {
     type Body: Serialize;
@@ -21,11 +24,94 @@ pub trait Respondable {
         HeaderMap::new()
     }
 
+    /// The media types that the response can be represented as, in order of preference.
+    ///
+    /// The first of these is the default, used when the client expresses no preference. The `Content-Type`
+    /// header is only replaced when content negotiation selects a different one.
+    ///
+    /// # Returns
+    /// The available media types. This must never be empty
+    fn media_types(&self) -> Vec<&'static str> {
+        vec!["application/json"]
+    }
+
+    /// How to respond when the client accepts none of the available media types.
+    ///
+    /// # Returns
+    /// The negotiation mode to use
+    fn negotiation_mode(&self) -> NegotiationMode {
+        NegotiationMode::ForceDefault
+    }
+
+    /// Whether the response has a body at all.
+    ///
+    /// If not then `body` is never called, and no `Content-Type` header is added.
+    ///
+    /// # Returns
+    /// True if the response has a body
+    fn has_body(&self) -> bool {
+        true
+    }
+
+    /// Whether to remove all null-valued keys from the serialized body, except those inside `_links`.
+    ///
+    /// # Returns
+    /// True to remove null-valued keys from the body
+    fn omits_nulls(&self) -> bool {
+        false
+    }
+
+    /// Prepare the response for the request it is being sent in response to, immediately before it is built.
+    ///
+    /// This is only called when responding through `Responder::respond_to`, and never when the response is built
+    /// directly with `Response::into_http_response`. The default implementation does nothing.
+    ///
+    /// # Parameters
+    /// - `req` - The request being responded to
+    fn prepare(&mut self, _req: &HttpRequest) {}
+
     /// Retrieve the body of the response
     ///
     /// # Returns
     /// The body to send back to the client
     fn body(self) -> Self::Body;
+
+    /// Break the response up into its status code, headers and body.
+    ///
+    /// The default implementation uses `status_code`, `headers` and `body`. Implementations that own their
+    /// headers should override this to move them out instead of cloning them.
+    ///
+    /// # Returns
+    /// The status code, headers and body to send back to the client
+    fn into_parts(self) -> (StatusCode, HeaderMap, Self::Body)
+    where
+        Self: Sized,
+    {
+        (self.status_code(), self.headers(), self.body())
+    }
+
+    /// Serialize the body of the response into the bytes to send back to the client.
+    ///
+    /// By default the body is serialized as JSON. Respondables for other formats override this.
+    ///
+    /// # Parameters
+    /// - `body` - The body to serialize
+    /// - `omit_nulls` - Whether to remove null-valued keys from the body, as returned by `omits_nulls`
+    ///
+    /// # Returns
+    /// The serialized body, or the error if it failed to serialize
+    fn serialize_body(body: Self::Body, omit_nulls: bool) -> serde_json::Result<Vec<u8>>
+    where
+        Self: Sized,
+    {
+        if omit_nulls {
+            let mut value = json::to_value(&body)?;
+            json::strip_nulls(&mut value);
+            json::to_vec(&value)
+        } else {
+            json::to_vec(&body)
+        }
+    }
 }
 
 impl<T> Respondable for T
@@ -0,0 +1,70 @@
+use actix_web::{HttpRequest, HttpResponse, Responder};
+use serde::Serialize;
+
+use super::{Respondable, Response};
+
+/// Response that is either a success or an error, each of which is a respondable with its own status code,
+/// headers and body. This lets a handler return a `Result` of two respondables without matching on it, e.g. one
+/// of a `HalRespondable` or a `Problem`.
+///
+/// `Respondable` can't be implemented for `Result` itself, since it is already covered by the implementation for
+/// every `Serialize` type, and actix already implements `Responder` for `Result`. Instead a `Result` converts
+/// into this, so a handler can return `ResultResponse<R, E>` and finish with `result.into()`.
+///
+/// # Types
+/// - `R` - The respondable for a success
+/// - `E` - The respondable for an error
+pub struct ResultResponse<R, E>(pub Result<R, E>);
+
+impl<R, E> From<Result<R, E>> for ResultResponse<R, E> {
+    fn from(result: Result<R, E>) -> Self {
+        Self(result)
+    }
+}
+
+impl<R, E> Responder for ResultResponse<R, E>
+where
+    R: Respondable,
+    R::Body: Serialize,
+    E: Respondable,
+    E::Body: Serialize,
+{
+    fn respond_to(self, req: &HttpRequest) -> HttpResponse {
+        match self.0 {
+            Ok(respondable) => Response::new(respondable).respond_to(req),
+            Err(respondable) => Response::new(respondable).respond_to(req),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::http::{header, StatusCode};
+    use actix_web::test::TestRequest;
+    use assert2::check;
+
+    use super::*;
+    use crate::{hal::HalRespondable, problem::Problem};
+
+    fn respond(result: Result<HalRespondable<serde_json::Value>, Problem>) -> HttpResponse {
+        let req = TestRequest::default().to_http_request();
+        ResultResponse::from(result).respond_to(&req)
+    }
+
+    #[test]
+    fn success() {
+        let respondable = HalRespondable::new(serde_json::json!({"id": 42})).with_status_code(StatusCode::CREATED);
+        let response = respond(Ok(respondable));
+
+        check!(response.status() == StatusCode::CREATED);
+        check!(response.headers().get(header::CONTENT_TYPE).unwrap() == "application/hal+json");
+    }
+
+    #[test]
+    fn error() {
+        let response = respond(Err(Problem::for_status(StatusCode::CONFLICT)));
+
+        check!(response.status() == StatusCode::CONFLICT);
+        check!(response.headers().get(header::CONTENT_TYPE).unwrap() == "application/problem+json");
+    }
+}
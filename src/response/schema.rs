@@ -0,0 +1,182 @@
+use actix_http::http::{HeaderMap, StatusCode};
+use actix_web::HttpRequest;
+use serde::{ser::Error as _, Serialize, Serializer};
+use serde_json::Value;
+
+use super::{NegotiationMode, Respondable};
+
+/// Wrapper around any respondable with a JSON body that checks the serialized body against a JSON Schema, to
+/// catch handlers drifting away from their documented contract during development.
+///
+/// The check only happens in debug builds, where a body that doesn't match the schema logs every validation
+/// error and then panics. In release builds the body is passed through untouched, without being checked. A
+/// schema that is itself invalid is logged and the body is not checked.
+///
+/// # Types
+/// - `R` - The type of respondable to wrap
+pub struct SchemaChecked<R> {
+    respondable: R,
+    schema:      Value,
+}
+
+/// Check the body of a respondable against a JSON Schema in debug builds.
+///
+/// # Parameters
+/// - `respondable` - The respondable to check
+/// - `schema` - The JSON Schema that the serialized body must match
+pub fn schema_checked<R>(respondable: R, schema: Value) -> SchemaChecked<R> {
+    SchemaChecked { respondable, schema }
+}
+
+/// The body of a schema-checked respondable, carrying the schema through to serialization.
+pub struct SchemaCheckedBody<B> {
+    body:   B,
+    #[cfg_attr(not(debug_assertions), allow(dead_code))]
+    schema: Value,
+}
+
+impl<B> Serialize for SchemaCheckedBody<B>
+where
+    B: Serialize,
+{
+    /// Serialize the body, checking it against the schema in debug builds, so that it is also checked when it is
+    /// serialized other than through `Respondable::serialize_body`, e.g. when it is embedded in a HAL response.
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let value = serde_json::to_value(&self.body).map_err(S::Error::custom)?;
+
+        #[cfg(debug_assertions)]
+        check(&self.schema, &value);
+
+        value.serialize(serializer)
+    }
+}
+
+impl<R> Respondable for SchemaChecked<R>
+where
+    R: Respondable,
+{
+    type Body = SchemaCheckedBody<R::Body>;
+
+    fn status_code(&self) -> StatusCode {
+        self.respondable.status_code()
+    }
+
+    fn headers(&self) -> HeaderMap {
+        self.respondable.headers()
+    }
+
+    fn media_types(&self) -> Vec<&'static str> {
+        self.respondable.media_types()
+    }
+
+    fn negotiation_mode(&self) -> NegotiationMode {
+        self.respondable.negotiation_mode()
+    }
+
+    fn has_body(&self) -> bool {
+        self.respondable.has_body()
+    }
+
+    fn omits_nulls(&self) -> bool {
+        self.respondable.omits_nulls()
+    }
+
+    fn prepare(&mut self, req: &HttpRequest) {
+        self.respondable.prepare(req);
+    }
+
+    fn body(self) -> Self::Body {
+        SchemaCheckedBody {
+            body:   self.respondable.body(),
+            schema: self.schema,
+        }
+    }
+
+    fn into_parts(self) -> (StatusCode, HeaderMap, Self::Body) {
+        let (status_code, headers, body) = self.respondable.into_parts();
+        let body = SchemaCheckedBody {
+            body,
+            schema: self.schema,
+        };
+
+        (status_code, headers, body)
+    }
+
+    fn serialize_body(body: Self::Body, omit_nulls: bool) -> serde_json::Result<Vec<u8>> {
+        let serialized = R::serialize_body(body.body, omit_nulls)?;
+
+        #[cfg(debug_assertions)]
+        check(&body.schema, &serde_json::from_slice(&serialized)?);
+
+        Ok(serialized)
+    }
+}
+
+/// Check a serialized body against a JSON Schema, panicking if it doesn't match.
+///
+/// # Parameters
+/// - `schema` - The JSON Schema to check against
+/// - `instance` - The serialized body
+#[cfg(debug_assertions)]
+fn check(schema: &Value, instance: &Value) {
+    let validator = match jsonschema::validator_for(schema) {
+        Ok(validator) => validator,
+        Err(e) => {
+            tracing::error!(e = %e, "Invalid JSON Schema for response body");
+            return;
+        },
+    };
+
+    let errors: Vec<String> = validator
+        .iter_errors(instance)
+        .map(|e| format!("{} at {}", e, e.instance_path))
+        .collect();
+    for e in &errors {
+        tracing::error!(e = %e, "Response body does not match its schema");
+    }
+    assert!(errors.is_empty(), "Response body does not match its schema: {}", errors.join(", "));
+}
+
+#[cfg(test)]
+mod tests {
+    use assert2::check;
+    use serde_json::json;
+
+    use super::*;
+    use crate::hal::HalRespondable;
+
+    fn schema() -> Value {
+        json!({
+            "type": "object",
+            "properties": {"id": {"type": "integer"}},
+            "required": ["id"]
+        })
+    }
+
+    #[test]
+    fn matching_body() {
+        let respondable = schema_checked(json!({"id": 1}), schema());
+        let body = SchemaChecked::<Value>::serialize_body(respondable.body(), false);
+
+        check!(body.unwrap() == br#"{"id":1}"#);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "Response body does not match its schema")]
+    fn mismatched_body() {
+        let respondable = schema_checked(json!({"id": "one"}), schema());
+        let _ = SchemaChecked::<Value>::serialize_body(respondable.body(), false);
+    }
+
+    #[cfg(debug_assertions)]
+    #[test]
+    #[should_panic(expected = "Response body does not match its schema")]
+    fn mismatched_embedded_body() {
+        let respondable = HalRespondable::new(json!({})).with_embedded("item", schema_checked(json!({}), schema()));
+        let _ = HalRespondable::<Value>::serialize_body(respondable.body(), false);
+    }
+}
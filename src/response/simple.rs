@@ -1,4 +1,5 @@
 use actix_http::http::{header::Header, HeaderMap, StatusCode};
+use actix_web::web::Json;
 use serde::Serialize;
 
 use super::Respondable;
@@ -13,9 +14,13 @@ where
 {
     status_code: StatusCode,
     headers:     HeaderMap,
+    media_type:  &'static str,
     body:        T,
 }
 
+/// Respondable for a plain `application/json` body.
+pub type JsonRespondable<T> = SimpleRespondable<T>;
+
 impl<T> SimpleRespondable<T>
 where
     T: Serialize,
@@ -28,10 +33,29 @@ where
         Self {
             status_code: StatusCode::OK,
             headers: HeaderMap::new(),
+            media_type: "application/json",
             body,
         }
     }
 
+    /// Create a new instance of the `SimpleRespondable` struct wrapping the provided body, served as
+    /// `application/merge-patch+json` as defined by RFC 7396.
+    ///
+    /// # Parameters
+    /// - `body` - The JSON Merge Patch document to send back to the client.
+    pub fn merge_patch(body: T) -> Self {
+        Self::new(body).with_content_type("application/merge-patch+json")
+    }
+
+    /// Specify the media type to serve the JSON body as, instead of `application/json`.
+    ///
+    /// # Parameters
+    /// - `media_type` - The media type to use for the `Content-Type` header
+    pub fn with_content_type(mut self, media_type: &'static str) -> Self {
+        self.media_type = media_type;
+        self
+    }
+
     /// Specify the status code to use.
     ///
     /// # Parameters
@@ -77,7 +101,53 @@ where
         self.headers.clone()
     }
 
+    fn media_types(&self) -> Vec<&'static str> {
+        vec![self.media_type]
+    }
+
     fn body(self) -> Self::Body {
         self.body
     }
+
+    fn into_parts(self) -> (StatusCode, HeaderMap, Self::Body) {
+        (self.status_code, self.headers, self.body)
+    }
+}
+
+impl<T> From<Json<T>> for SimpleRespondable<T>
+where
+    T: Serialize,
+{
+    fn from(json: Json<T>) -> Self {
+        Self::new(json.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use actix_http::http::header;
+    use actix_web::{test::TestRequest, Responder};
+    use assert2::check;
+
+    use super::*;
+    use crate::response::Response;
+
+    #[test]
+    fn merge_patch_content_type() {
+        let req = TestRequest::default().to_http_request();
+        let respondable = SimpleRespondable::merge_patch(serde_json::json!({"name": null}));
+        let response = Response::new(respondable).respond_to(&req);
+
+        check!(response.headers().get(header::CONTENT_TYPE).unwrap() == "application/merge-patch+json");
+    }
+
+    #[test]
+    fn from_json() {
+        let req = TestRequest::default().to_http_request();
+        let respondable: JsonRespondable<_> = Json(42).into();
+        let response = Response::new(respondable.with_status_code(StatusCode::CREATED)).respond_to(&req);
+
+        check!(response.status() == StatusCode::CREATED);
+        check!(response.headers().get(header::CONTENT_TYPE).unwrap() == "application/json");
+    }
 }
@@ -0,0 +1,54 @@
+use serde::ser::Error;
+use serde_json::Value;
+
+/// The media type that YAML representations are served as.
+pub(crate) const MEDIA_TYPE: &str = "application/yaml";
+
+/// Determine if a response that can be represented as the provided media types can also be represented as YAML.
+///
+/// This is the case whenever one of them is JSON, since the JSON can then be transcoded into YAML.
+///
+/// # Parameters
+/// - `media_types` - The media types the response can be represented as
+pub(crate) fn is_available(media_types: &[&str]) -> bool {
+    media_types
+        .iter()
+        .any(|media_type| *media_type == "application/json" || media_type.ends_with("+json"))
+}
+
+/// Transcode a serialized JSON body into YAML.
+///
+/// Transcoding the serialized JSON means that everything that works on the JSON, such as omitting nulls, applies
+/// to the YAML representation as well. The keys of objects end up sorted, since the JSON is parsed on the way.
+///
+/// # Parameters
+/// - `json` - The serialized JSON to transcode
+///
+/// # Returns
+/// The YAML representation of the JSON
+pub(crate) fn transcode(json: &[u8]) -> serde_json::Result<Vec<u8>> {
+    let value: Value = serde_json::from_slice(json)?;
+    serde_yaml::to_string(&value)
+        .map(String::into_bytes)
+        .map_err(serde_json::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use assert2::check;
+
+    use super::*;
+
+    #[test]
+    fn transcode_json() {
+        let yaml = transcode(br#"{"id":1,"_links":{"self":{"href":"/users/1"}}}"#).unwrap();
+
+        check!(String::from_utf8(yaml).unwrap() == "_links:\n  self:\n    href: /users/1\nid: 1\n");
+    }
+
+    #[test]
+    fn available_for_json() {
+        check!(is_available(&["application/hal+json", "application/json"]));
+        check!(!is_available(&["text/html"]));
+    }
+}